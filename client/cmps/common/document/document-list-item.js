@@ -82,6 +82,8 @@ class DocumentListItem extends PolymerElement {
                             theme="small"
                             file-name="[[document.media.fileName]]"
                             preview-data="[[document.media.previewData]]"
+                            media-type="[[document.media.type]]"
+                            renditions="[[document.media.renditions]]"
                         >
                         </document-media>
 
@@ -107,7 +109,7 @@ class DocumentListItem extends PolymerElement {
                     <footer
                         id="matchContainer"
                         class="app__bordered list-item__match-container"
-                        hidden="[[!document.matches]]"
+                        hidden="[[!document.highlights]]"
                     >
                     </footer>
                 </div>
@@ -133,132 +135,16 @@ class DocumentListItem extends PolymerElement {
         };
     }
 
-    static _buildNameMatchMap(termMatches) {
-        /* Example input into this function
-        {
-            "term": [
-                {
-                    "array_positions": [ 1 ],
-                    "end": 4,
-                    "start": 0
-                },
-                {
-                    "array_positions": [ 1 ],
-                    "end": 13,
-                    "start": 9
-                }
-            ]
-            "term2": ...
-        }
-        */
-
-        let nameMatchMap = new Map();
-        Object.values(termMatches).forEach(matches => {
-            matches.forEach(match => {
-                match.array_positions.forEach(nameIndex => {
-                    if (!nameMatchMap[nameIndex]) {
-                        nameMatchMap[nameIndex] = [];
-                    }
-
-                    nameMatchMap[nameIndex].push({
-                        start: match.start,
-                        end: match.end,
-                    });
-                });
-            });
-        });
-
-        // Make sure we sort by start position
-        Object.values(nameMatchMap).forEach(infoArray => {
-            infoArray.sort((left, right) => left.start - right.start);
-        });
-
-        return nameMatchMap;
-    }
-
-    static _escapeText(unsafe) {
-        return unsafe.replace(/[<>&'"]/g, function (c) {
-            switch (c) {
-            case '<': return '&lt;';
-            case '>': return '&gt;';
-            case '&': return '&amp;';
-            case '\'': return '&apos;';
-            case '"': return '&quot;';
-            }
-        });
-    }
-
-    static _markName(name, nameIndex, nameMatchMap) {
-        const matchInfoArray = nameMatchMap[nameIndex];
-
-        // Assuming we have no overlap across matches...
-        // break the name into parts using the match positions.
-        // Then join them back together with mark names.
-        let parts = [];
-        let currentPos = 0;
-        matchInfoArray.forEach(info => {
-            if (currentPos < info.start) {
-                parts.push({
-                    text: name.substring(currentPos, info.start),
-                    mark: false
-                });
-            }
-
-            parts.push({
-                text: name.substring(info.start, info.end),
-                mark: true
-            });
-
-            currentPos = info.end;
-        });
-
-        if (currentPos < name.length) {
-            parts.push({
-                text: name.substring(currentPos),
-                mark: false
-            });
-        }
-
-        return parts.map(part => {
-            const escaped = DocumentListItem._escapeText(part.text);
-            return part.mark ? `<mark>${escaped}</mark>` : escaped;
-        }).join('');
-    }
-
-    static _buildElementsForNames(names, termMatches) {
-        const nameMatchMap = DocumentListItem._buildNameMatchMap(termMatches);
-
-        let result = [];
-        names.forEach((name, index) => {
-            // Continue if we don't have a match
-            if (!nameMatchMap[index]) return;
-
-            // Otherwise build the element
-            const marked = DocumentListItem._markName(name, index, nameMatchMap);
-            result.push(`<span>${marked}</span>`);
-        });
-
-        return result;
-    }
-
     _onDocumentChanged(document) {
-        if (!document || !document.matches) {
+        if (!document || !document.highlights) {
             return;
         }
 
-        const parentNames = document.parentNames ?
-            DocumentListItem._buildElementsForNames(
-                document.parentNames,
-                document.matches.parentNames || {}
-            )
-            : [];
-
-        const names = DocumentListItem._buildElementsForNames(
-            document.names,
-            document.matches.names || {}
-        );
+        // Highlights are html fragments escaped by the server
+        const highlights = (document.highlights.parentNames || [])
+            .concat(document.highlights.names || []);
 
-        const html = parentNames.concat(names).join('');
+        const html = highlights.map(fragment => `<span>${fragment}</span>`).join('');
         this.shadowRoot.querySelector('#matchContainer').innerHTML = html;
     }
 
@@ -1,5 +1,7 @@
+import '../base/base-button.js';
 import '../styles/app-styles.js';
 import '@polymer/polymer/lib/elements/dom-if.js';
+import '@polymer/polymer/lib/elements/dom-repeat.js';
 import { PolymerElement, html } from '@polymer/polymer/polymer-element.js';
 
 class DocumentMedia extends PolymerElement {
@@ -57,6 +59,18 @@ class DocumentMedia extends PolymerElement {
                     animation-fill-mode: forwards;
                 }
 
+                .frame-controls:not([hidden]) {
+                    display: flex;
+                    align-items: center;
+                    justify-content: center;
+                    padding: var(--space-small);
+                }
+
+                .frame-controls__label {
+                    min-width: 8rem;
+                    text-align: center;
+                }
+
                 @keyframes fade-in {
                     from {
                         filter: blur(0.1rem);
@@ -82,7 +96,26 @@ class DocumentMedia extends PolymerElement {
 
                 <template
                     is="dom-if"
-                    if="[[_fileName]]"
+                    if="[[_isImage(_fileName, mediaType)]]"
+                    restamp
+                >
+                    <picture>
+                        <source
+                            srcset="/uploads/[[_fileName]].[[_getImageWidth(theme)]].webp"
+                            type="image/webp"
+                        >
+
+                        <img
+                            class\$="media media--[[theme]]"
+                            src="/uploads/[[_fileName]].[[_getImageWidth(theme)]].jpg"
+                            alt=""
+                        />
+                    </picture>
+                </template>
+
+                <template
+                    is="dom-if"
+                    if="[[_isVideo(_fileName, mediaType)]]"
                     restamp
                 >
                     <video
@@ -92,23 +125,43 @@ class DocumentMedia extends PolymerElement {
                         autoplay
                         playsinline
                         on-loadeddata="_onLoadedData"
+                        on-timeupdate="_onTimeUpdate"
+                        on-seeked="_onTimeUpdate"
                         poster="data:image/jpeg;charset=utf-8;base64,[[previewData]]"
                     >
-                        <source
-                            src="/uploads/[[_fileName]].webm"
-                            type="video/webm"
-                        >
-
-                        <source
-                            src="/uploads/[[_fileName]].mp4"
-                            type="video/mp4"
+                        <template
+                            is="dom-repeat"
+                            items="[[_getVideoSources(_fileName, renditions, theme)]]"
                         >
+                            <source
+                                src="/uploads/[[item.file]]"
+                                type\$="[[item.type]]"
+                            >
+                        </template>
 
                         <!-- TODO: Make string localizable -->
                         Your browser does not support the video element
                     </video>
                 </template>
             </figure>
+
+            <div
+                class="frame-controls"
+                hidden="[[!_showFrameControls(_frameTimes, theme)]]"
+            >
+                <!-- TODO: Make strings localizable -->
+                <base-button on-click="_onPreviousFrameClicked">
+                    Previous
+                </base-button>
+
+                <span class="frame-controls__label">
+                    Frame [[_getFrameNumber(_frameIndex)]] of [[_frameTimes.length]]
+                </span>
+
+                <base-button on-click="_onNextFrameClicked">
+                    Next
+                </base-button>
+            </div>
         `;
     }
 
@@ -129,6 +182,16 @@ class DocumentMedia extends PolymerElement {
                 value: null
             },
 
+            mediaType: {
+                type: String,
+                value: null
+            },
+
+            renditions: {
+                type: Array,
+                value: null
+            },
+
             theme: {
                 type: String,
                 value: 'large'
@@ -144,6 +207,16 @@ class DocumentMedia extends PolymerElement {
                 value: false
             },
 
+            _frameTimes: {
+                type: Array,
+                value: null
+            },
+
+            _frameIndex: {
+                type: Number,
+                value: 0
+            },
+
             _visibilityChangedHandler: {
                 type: Object
             }
@@ -190,8 +263,96 @@ class DocumentMedia extends PolymerElement {
         }
     }
 
-    _onLoadedData() {
-        this._loaded = true;
+    _isImage(fileName, mediaType) {
+        return !!fileName && mediaType === 'image';
+    }
+
+    _isVideo(fileName, mediaType) {
+        return !!fileName && mediaType !== 'image';
+    }
+
+    _getVideoSources(fileName, renditions, theme) {
+        if (!renditions || !renditions.length) {
+            // Videos published before transcoding profiles existed
+            return [
+                { file: `${fileName}.webm`, type: 'video/webm' },
+                { file: `${fileName}.mp4`, type: 'video/mp4' }
+            ];
+        }
+
+        // Prefer the smallest renditions for small themes and the largest
+        // otherwise. The browser plays the first source it supports.
+        const direction = theme === 'small' ? 1 : -1;
+        return renditions
+            .slice()
+            .sort((a, b) => direction * (a.width - b.width));
+    }
+
+    _getImageWidth(theme) {
+        return theme === 'small' ? 320 : 960;
+    }
+
+    _onLoadedData(event) {
+        // Use the frame times of the rendition the browser picked
+        const video = event.target;
+        const rendition = (this.renditions || []).find(
+            item => video.currentSrc.endsWith(`/uploads/${item.file}`)
+        );
+
+        this.setProperties({
+            _loaded: true,
+            _frameTimes: rendition && rendition.frameTimes ? rendition.frameTimes : null,
+            _frameIndex: 0
+        });
+    }
+
+    _onTimeUpdate(event) {
+        const times = this._frameTimes;
+        if (!times) {
+            return;
+        }
+
+        // Find the last frame shown at or before the current time
+        const time = event.target.currentTime + 0.0005;
+        let index = 0;
+        while (index + 1 < times.length && times[index + 1] <= time) {
+            index++;
+        }
+
+        this._frameIndex = index;
+    }
+
+    _onPreviousFrameClicked() {
+        this._stepFrames(-1);
+    }
+
+    _onNextFrameClicked() {
+        this._stepFrames(1);
+    }
+
+    _stepFrames(count) {
+        const video = this.shadowRoot.querySelector('video');
+        const times = this._frameTimes;
+        if (!video || !times) {
+            return;
+        }
+
+        video.pause();
+        const index = Math.min(Math.max(this._frameIndex + count, 0), times.length - 1);
+        const next = index + 1 < times.length ? times[index + 1] : video.duration;
+
+        // Seek to the middle of the frame so browsers do not show its
+        // neighbour
+        this._frameIndex = index;
+        video.currentTime = (times[index] + next) / 2;
+    }
+
+    _showFrameControls(frameTimes, theme) {
+        return !!frameTimes && frameTimes.length > 1 && theme !== 'small';
+    }
+
+    _getFrameNumber(frameIndex) {
+        return frameIndex + 1;
     }
 
     _getLoadedString(loaded) {
@@ -202,7 +363,8 @@ class DocumentMedia extends PolymerElement {
         // Set the '_fileName' property on another frame to restamp the video element
         this.setProperties({
             _fileName: null,
-            _loaded: false
+            _loaded: false,
+            _frameTimes: null
         });
 
         window.requestAnimationFrame(() => this._fileName = fileName);
@@ -4,6 +4,7 @@ import '../styles/app-styles.js';
 import '@polymer/iron-ajax/iron-ajax.js';
 import '@polymer/polymer/lib/elements/dom-if.js';
 import { PolymerElement, html } from '@polymer/polymer/polymer-element.js';
+import { getUserClaims } from '../util/cookie.js';
 
 class SearchResultsLoader extends PolymerElement {
     static get template() {
@@ -95,6 +96,7 @@ class SearchResultsLoader extends PolymerElement {
 
                 <document-list
                     documents="[[_documents]]"
+                    on-click="_onDocumentClicked"
                     show-stencil="[[_showStencil]]"
                     full-crumb-path
                 >
@@ -228,7 +230,7 @@ class SearchResultsLoader extends PolymerElement {
 
     _onGetResponse(event) {
         const response = event.detail.response;
-        const results = Array.isArray(response) ? response : [response];
+        const results = response && response.page || [];
         const documents = results.slice(0, Math.min(this.pageSize, results.length));
 
         this.setProperties({
@@ -246,6 +248,37 @@ class SearchResultsLoader extends PolymerElement {
         }
     }
 
+    _onDocumentClicked(event) {
+        // Only signed in users can report clicks
+        if (!getUserClaims()) {
+            return;
+        }
+
+        // Only report clicks on links to a document
+        const path = event.composedPath();
+        if (!path.some(element => element.tagName === 'A')) {
+            return;
+        }
+
+        const item = path.find(element => element.tagName === 'DOCUMENT-LIST-ITEM');
+        if (!item || !item.document) {
+            return;
+        }
+
+        const click = {
+            query: this.query.trim(),
+            type: item.document.type,
+            id: item.document.id
+        };
+
+        if (this.target) {
+            click[this.targetType] = this.target;
+        }
+
+        const body = new Blob([JSON.stringify(click)], { type: 'application/json' });
+        navigator.sendBeacon('/search-api/v1/clicks', body);
+    }
+
     _getPagingLink(query, targetType, target, page) {
         return `?query=${query}${targetType ? `&${targetType}=${target}` : ''}&page=${page}`;
     }
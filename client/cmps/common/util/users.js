@@ -0,0 +1,19 @@
+/**
+ * Gets the url of the provider account of a user, e.g. their Twitter
+ * profile. User ids of Twitter accounts are 'u:' followed by the account id.
+ */
+function getAccountUrl(userId) {
+    return `https://twitter.com/intent/user?user_id=${userId.slice(2)}`;
+}
+
+/**
+ * Fetches the profile of the signed in user. Resolves to null if it cannot
+ * be fetched.
+ */
+function fetchCurrentUser() {
+    return fetch('/user-api/v1/users/me', { credentials: 'same-origin' })
+        .then(response => response.ok ? response.json() : null)
+        .catch(() => null);
+}
+
+export { getAccountUrl, fetchCurrentUser };
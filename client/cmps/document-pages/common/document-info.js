@@ -6,6 +6,7 @@ import '../../common/styles/app-styles.js';
 import '../../common/styles/stencil-styles.js';
 import '@polymer/polymer/lib/elements/dom-repeat.js';
 import { IMPORTERS } from '../../common/util/importers.js';
+import { getAccountUrl } from '../../common/util/users.js';
 import { PolymerElement, html } from '@polymer/polymer/polymer-element.js';
 
 class DocumentInfo extends PolymerElement {
@@ -127,6 +128,8 @@ class DocumentInfo extends PolymerElement {
                     class="app__section"
                     file-name="[[document.media.fileName]]"
                     preview-data="[[document.media.previewData]]"
+                    media-type="[[document.media.type]]"
+                    renditions="[[document.media.renditions]]"
                 >
                 </document-media>
 
@@ -208,8 +211,8 @@ class DocumentInfo extends PolymerElement {
             }
 
             return {
-                display: `@${author.name}`,
-                url: `https://twitter.com/${author.name}`
+                display: author.name,
+                url: getAccountUrl(author.id)
             };
         });
     }
@@ -12,6 +12,7 @@ import '@polymer/app-route/app-route.js';
 import '@polymer/polymer/lib/elements/dom-if.js';
 import { PolymerElement, html } from '@polymer/polymer/polymer-element.js';
 import { getUserClaims } from './common/util/cookie.js';
+import { fetchCurrentUser } from './common/util/users.js';
 
 class FbApp extends PolymerElement {
     static get template() {
@@ -201,7 +202,7 @@ class FbApp extends PolymerElement {
 
                     <!-- TODO: Make strings localizable -->
                     <nav class="app__container app__heavy-shadowed profile-menu">
-                        <span class="profile-menu__item profile-menu__item--header">@[[_userInfo.name]]</span>
+                        <span class="profile-menu__item profile-menu__item--header">[[_userInfo.name]]</span>
                         <a
                             class="app__hyperlink profile-menu__item"
                             href="/props/list/all"
@@ -434,11 +435,14 @@ class FbApp extends PolymerElement {
         const category = matches && matches[1] || '';
         const relativePath = matches && matches[2] || '';
 
+        // Keep the user's name while they stay signed in
         const claims = getUserClaims();
-        const userInfo = claims ? {
-            name: claims.screenName,
-            id: claims.sub
-        } : null;
+        const userId = claims && claims.sub;
+        const currentUserId = this._userInfo && this._userInfo.id;
+        let userInfo = null;
+        if (userId) {
+            userInfo = userId === currentUserId ? this._userInfo : { id: userId, name: '' };
+        }
 
         // Always set this first before relative path.
         this.$.shownPages.set(routePageMap[category] || 'notFound');
@@ -446,6 +450,19 @@ class FbApp extends PolymerElement {
             _relativePath: relativePath,
             _userInfo: userInfo
         });
+
+        if (userId && userId !== currentUserId) {
+            this._fetchUserName(userId);
+        }
+    }
+
+    _fetchUserName(userId) {
+        // Names are not part of the session so they are always current
+        fetchCurrentUser().then(user => {
+            if (user && this._userInfo && this._userInfo.id === userId) {
+                this.set('_userInfo.name', user.displayName);
+            }
+        });
     }
 }
 
@@ -40,10 +40,26 @@ class MediaSelector extends PolymerElement {
             <iron-ajax
                 id="postAjax"
                 method="POST"
-                url="/upload-api/v1/video"
+                url="[[_getVideoUrl(trimStart, trimEnd, frameRate)]]"
                 on-response="_onPostResponse"
-                on-error="_onPostError"
-                loading="{{_uploading}}"
+                on-error="_onUploadError"
+            >
+            </iron-ajax>
+
+            <iron-ajax
+                id="imageAjax"
+                method="POST"
+                url="/upload-api/v1/image"
+                on-response="_onImageResponse"
+                on-error="_onUploadError"
+            >
+            </iron-ajax>
+
+            <iron-ajax
+                id="jobAjax"
+                url="/upload-api/v1/jobs/[[_jobId]]"
+                on-response="_onJobResponse"
+                on-error="_onUploadError"
             >
             </iron-ajax>
 
@@ -52,7 +68,7 @@ class MediaSelector extends PolymerElement {
             <input
                 id="mediaInput"
                 type="file"
-                accept="video/webm,video/mp4"
+                accept\$="[[accept]]"
                 on-change="_onFileChanged"
                 hidden
             >
@@ -65,6 +81,8 @@ class MediaSelector extends PolymerElement {
                         theme="small"
                         file-name="[[fileName]]"
                         preview-data="[[previewData]]"
+                        media-type="[[mediaType]]"
+                        renditions="[[renditions]]"
                         hidden="[[_uploading]]"
                     >
                     </document-media>
@@ -100,9 +118,44 @@ class MediaSelector extends PolymerElement {
                 notify: true
             },
 
+            mediaType: {
+                type: String,
+                notify: true
+            },
+
+            renditions: {
+                type: Array,
+                notify: true
+            },
+
+            trimStart: {
+                type: String,
+                value: ''
+            },
+
+            trimEnd: {
+                type: String,
+                value: ''
+            },
+
+            frameRate: {
+                type: String,
+                value: ''
+            },
+
+            accept: {
+                type: String,
+                value: 'video/webm,video/mp4'
+            },
+
             _uploading: {
                 type: Boolean,
                 value: false
+            },
+
+            _jobId: {
+                type: String,
+                value: null
             }
         };
     }
@@ -116,32 +169,91 @@ class MediaSelector extends PolymerElement {
             return;
         }
 
-        let ajax = this.$.postAjax;
-        if (ajax.lastRequest) {
-            ajax.lastRequest.abort();
-        }
+        [this.$.postAjax, this.$.imageAjax].forEach(ajax => {
+            if (ajax.lastRequest) {
+                ajax.lastRequest.abort();
+            }
+        });
 
+        const isImage = file.type.startsWith('image/');
+        const ajax = isImage ? this.$.imageAjax : this.$.postAjax;
+        this._cancelJobPolling();
+        this._uploading = true;
         ajax.body = file;
         ajax.generateRequest();
     }
 
+    _getVideoUrl(trimStart, trimEnd, frameRate) {
+        // Only pass the clip options that were specified
+        const params = new URLSearchParams();
+        Object.entries({ trimStart, trimEnd, frameRate })
+            .filter(([, value]) => value && value.trim())
+            .forEach(([name, value]) => params.set(name, value.trim()));
+
+        const query = params.toString();
+        return `/upload-api/v1/video${query ? `?${query}` : ''}`;
+    }
+
     _onChangeClicked() {
         this.$.mediaInput.click();
     }
 
     _onPostResponse(event) {
+        // The video is processed in the background; poll its job until done
+        this._jobId = event.detail.response.jobId;
+        this.$.jobAjax.generateRequest();
+    }
+
+    _onImageResponse(event) {
         const response = event.detail.response;
         this.setProperties({
             fileName: response.fileName,
-            previewData: response.previewData
+            previewData: response.previewData,
+            mediaType: 'image',
+            renditions: [],
+            _uploading: false
         });
     }
 
-    _onPostError(event) {
+    _onJobResponse(event) {
+        const job = event.detail.response;
+        switch (job.status) {
+        case 'done':
+            this.setProperties({
+                fileName: job.fileName,
+                previewData: job.previewData,
+                mediaType: 'video',
+                renditions: job.renditions,
+                _uploading: false
+            });
+            break;
+        case 'failed':
+            this._uploading = false;
+            this.dispatchEvent(new CustomEvent('file-upload-error'));
+            break;
+        default:
+            this._pollTimeout = setTimeout(() => this.$.jobAjax.generateRequest(), 1000);
+        }
+    }
+
+    _onUploadError(event) {
         if (!event.detail.request.aborted) {
+            this._uploading = false;
             this.dispatchEvent(new CustomEvent('file-upload-error'));
         }
     }
+
+    _cancelJobPolling() {
+        clearTimeout(this._pollTimeout);
+        if (this.$.jobAjax.lastRequest) {
+            this.$.jobAjax.lastRequest.abort();
+        }
+    }
+
+    disconnectedCallback() {
+        super.disconnectedCallback();
+        this._cancelJobPolling();
+    }
 }
 
 customElements.define(MediaSelector.is, MediaSelector);
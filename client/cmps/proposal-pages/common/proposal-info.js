@@ -2,6 +2,7 @@ import '../../common/styles/app-styles.js';
 import { PolymerElement, html } from '@polymer/polymer/polymer-element.js';
 import { getTimeString } from '../../common/util/time.js';
 import { IMPORTERS } from '../../common/util/importers.js';
+import { getAccountUrl } from '../../common/util/users.js';
 
 class ProposalInfo extends PolymerElement {
     static get template() {
@@ -109,8 +110,8 @@ class ProposalInfo extends PolymerElement {
             };
         } else {
             this._authorInfo = {
-                display: `@${proposal.authorName}`,
-                url: `https://twitter.com/${proposal.authorName}`
+                display: proposal.authorName,
+                url: getAccountUrl(proposal.authorId)
             };
         }
     }
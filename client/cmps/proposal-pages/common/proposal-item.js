@@ -54,6 +54,8 @@ class ProposalItem extends PolymerElement {
                     class="app__section"
                     file-name="[[proposal.document.media.fileName]]"
                     preview-data="[[proposal.document.media.previewData]]"
+                    media-type="[[proposal.document.media.type]]"
+                    renditions="[[proposal.document.media.renditions]]"
                 >
                 </document-media>
 
@@ -155,6 +155,10 @@ class ProposalList extends PolymerElement {
 
             _ready: {
                 type: Boolean
+            },
+
+            _eventSource: {
+                type: Object
             }
         };
     }
@@ -170,6 +174,50 @@ class ProposalList extends PolymerElement {
         this._ready = true;
     }
 
+    connectedCallback() {
+        super.connectedCallback();
+        if (this._ready) {
+            this._openEvents();
+        }
+    }
+
+    disconnectedCallback() {
+        super.disconnectedCallback();
+        this._closeEvents();
+    }
+
+    _openEvents() {
+        this._closeEvents();
+        if (!window.EventSource) {
+            return;
+        }
+
+        // Reload the list when a proposal it could show changes
+        const url = this.target ?
+            `/event-api/v1/events?target=${encodeURIComponent(this.target)}` :
+            '/event-api/v1/events';
+
+        const onEvent = event => {
+            const data = JSON.parse(event.data);
+            if (event.type === 'resync' || !this.author || data.authorId === this.author) {
+                this.fetch();
+            }
+        };
+
+        const source = new EventSource(url);
+        ['proposal.created', 'proposal.closed', 'resync'].forEach(name =>
+            source.addEventListener(name, onEvent));
+
+        this._eventSource = source;
+    }
+
+    _closeEvents() {
+        if (this._eventSource) {
+            this._eventSource.close();
+            this._eventSource = null;
+        }
+    }
+
     fetch() {
         this.setProperties({
             _currentPage: 0,
@@ -186,6 +234,7 @@ class ProposalList extends PolymerElement {
         }
 
         this.fetch();
+        this._openEvents();
     }
 
     _onGetResponse(event) {
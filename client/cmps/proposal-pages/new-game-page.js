@@ -115,11 +115,14 @@ class NewGamePage extends PolymerElement {
                     class="page-item"
                     file-name="{{_media.fileName}}"
                     preview-data="{{_media.previewData}}"
+                    media-type="{{_media.type}}"
+                    renditions="{{_media.renditions}}"
+                    accept="video/webm,video/mp4,image/png,image/jpeg,image/webp"
                     on-file-size-error="_onFileSizeError"
                     on-file-upload-error="_onFileUploadError"
                 >
-                    Video
-                    <span class="page-note">(Upload a video that's less than 1 MB)</span>
+                    Video or image
+                    <span class="page-note">(Upload a video or image that's less than 1 MB)</span>
                 </media-selector>
 
                 <name-set-editor
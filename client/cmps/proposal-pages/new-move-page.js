@@ -111,11 +111,46 @@ class NewMovePage extends PolymerElement {
                     <span class="page-note">(May only contain characters 'a-z', '0-9', '-' and '_')</span>
                 </text-input>
 
-                <!-- TODO: Make the label localizable -->
+                <!-- TODO: Make the labels localizable -->
+                <text-input
+                    class="page-item"
+                    value="{{_trimStart}}"
+                    placeholder="e.g. 12"
+                    pattern="\\s*\\d*\\s*"
+                >
+                    First frame
+                    <span class="page-note">(Optional. The first frame of the video to keep, counting from 0)</span>
+                </text-input>
+
+                <text-input
+                    class="page-item"
+                    value="{{_trimEnd}}"
+                    placeholder="e.g. 54"
+                    pattern="\\s*\\d*\\s*"
+                >
+                    Last frame
+                    <span class="page-note">(Optional. The last frame of the video to keep)</span>
+                </text-input>
+
+                <text-input
+                    class="page-item"
+                    value="{{_frameRate}}"
+                    placeholder="e.g. 60"
+                    pattern="\\s*\\d*\\s*"
+                >
+                    Frame rate
+                    <span class="page-note">(Optional. Set to the game's frame rate to step through each frame)</span>
+                </text-input>
+
                 <media-selector
                     class="page-item"
+                    trim-start="[[_trimStart]]"
+                    trim-end="[[_trimEnd]]"
+                    frame-rate="[[_frameRate]]"
                     file-name="{{_media.fileName}}"
                     preview-data="{{_media.previewData}}"
+                    media-type="{{_media.type}}"
+                    renditions="{{_media.renditions}}"
                     on-file-size-error="_onFileSizeError"
                     on-file-upload-error="_onFileUploadError"
                 >
@@ -190,6 +225,21 @@ class NewMovePage extends PolymerElement {
                 }
             },
 
+            _trimStart: {
+                type: String,
+                value: ''
+            },
+
+            _trimEnd: {
+                type: String,
+                value: ''
+            },
+
+            _frameRate: {
+                type: String,
+                value: ''
+            },
+
             _media: {
                 type: Object,
                 value: () => {
@@ -4,8 +4,11 @@ import minifyHTML from 'rollup-plugin-minify-html-literals';
 import { nodeResolve } from '@rollup/plugin-node-resolve';
 import { terser } from 'rollup-plugin-terser';
 import * as path from 'path';
+import { brotliCompress } from 'zlib';
+import { promisify } from 'util';
 
 const currentDir = path.resolve('./');
+const brotliCompressAsync = promisify(brotliCompress);
 
 export default {
     input: [
@@ -21,6 +24,10 @@ export default {
         }),
         minifyHTML(),
         gzipPlugin(),
+        gzipPlugin({
+            customCompression: content => brotliCompressAsync(Buffer.from(content)),
+            fileName: '.br'
+        }),
     ],
     output: {
         entryFileNames: info => {
@@ -14,6 +14,7 @@ dataRamSize=2048
 indexRamSize=2048
 bktPublishedRamSize=1024
 bktProposedRamSize=1024
+bktInternalRamSize=256
 createBktIndices=0
 createFtsIndices=0
 
@@ -28,6 +29,7 @@ while [ $# -gt 0 ]; do
         --index-ram-size) indexRamSize=$2; shift ;;
         --bkt-published-ram-size) bktPublishedRamSize=$2; shift ;;
         --bkt-proposed-ram-size) bktProposedRamSize=$2; shift ;;
+        --bkt-internal-ram-size) bktInternalRamSize=$2; shift ;;
         --create-bkt-indices) createBktIndices=1 ;;
         --create-fts-indices) createFtsIndices=1 ;;
         *) showInvalidOption "$1" ;;
@@ -116,6 +118,7 @@ setupBuckets() {
 
     createBucket "published" "$bktPublishedRamSize"
     createBucket "proposed" "$bktProposedRamSize"
+    createBucket "internal" "$bktInternalRamSize"
 }
 
 createFtsIndices() {
@@ -183,6 +186,7 @@ createBucketIndices() {
     printInfo "Creating indices..."
     checkExitCode "$(runQueryFile "$CURRENT_DIR/n1ql/proposed_indices.n1ql")"
     checkExitCode "$(runQueryFile "$CURRENT_DIR/n1ql/published_indices.n1ql")"
+    checkExitCode "$(runQueryFile "$CURRENT_DIR/n1ql/internal_indices.n1ql")"
     printInfo "Done."
 }
 
@@ -5,11 +5,14 @@ use super::{
     ServiceConfig,
     Session,
 };
-use crate::http_service::{
-    util as http_util,
-    HttpError,
-    HttpResponse,
-    HttpResult,
+use crate::{
+    database_adapters::couchbase as couchbase_adapters,
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResponse,
+        HttpResult,
+    },
 };
 use crypto::{
     digest::Digest,
@@ -48,9 +51,11 @@ use log::{
     info,
     warn,
 };
+use serde_json::json;
 use std::{
     collections::HashMap,
     str,
+    sync::Arc,
     time::{
         SystemTime,
         UNIX_EPOCH,
@@ -65,12 +70,16 @@ const TOKEN_MAX_LIFE_SECONDS: u64 = 86400;
 
 pub struct Service {
     config: ServiceConfig,
+    users_adapter: Arc<couchbase_adapters::Users>,
 }
 
 impl Service {
     /// Creates a new instance of the auth service
-    pub fn new(config: ServiceConfig) -> Self {
-        Self { config }
+    pub fn new(config: ServiceConfig, users_adapter: Arc<couchbase_adapters::Users>) -> Self {
+        Self {
+            config,
+            users_adapter,
+        }
     }
 
     /// Modifies response headers based on the state of the session
@@ -228,11 +237,11 @@ impl Service {
             })?;
 
         let body = Self::get_body_from_response(response).await?;
-        self.handle_access_token_response(body)
+        self.handle_access_token_response(body).await
     }
 
     /// Handles an access token request response from Twitter
-    fn handle_access_token_response(&self, body: Bytes) -> HttpResult {
+    async fn handle_access_token_response(&self, body: Bytes) -> HttpResult {
         // Get the params from the response
         let query_params = str::from_utf8(body.as_ref())
             .map(|params| http_util::parse_query_string(params))
@@ -247,7 +256,23 @@ impl Service {
 
         match (query_params.get("user_id"), query_params.get("screen_name")) {
             (Some(user_id), Some(screen_name)) => {
-                let (nonce, token) = self.create_jwt(&format!("u:{}", user_id), *screen_name);
+                let sub = format!("u:{}", user_id);
+                let account = json!({
+                    "provider": "twitter",
+                    "id": user_id,
+                    "screenName": screen_name,
+                });
+
+                // A missing profile is created on the next sign in
+                if let Err(error) = self
+                    .users_adapter
+                    .record_login(&sub, account, screen_name, Self::get_current_time_secs())
+                    .await
+                {
+                    error!("Unable to record the sign in of {}: {:?}", sub, error);
+                }
+
+                let (nonce, token) = self.create_jwt(&sub);
 
                 info!("User {} = {} logged in ", screen_name, user_id);
                 let response = Response::builder()
@@ -339,7 +364,7 @@ impl Service {
     }
 
     fn refresh_jwt(&self, header_map: &mut HeaderMap, claims: &Claims) {
-        let (nonce, token) = self.create_jwt(&claims.sub(), &claims.screen_name());
+        let (nonce, token) = self.create_jwt(claims.sub());
 
         let nonce_string = format!("nonce={}; Path=/; HttpOnly", nonce);
         let token_string = format!("token={}; Path=/", token);
@@ -355,9 +380,9 @@ impl Service {
         );
     }
 
-    // TODO: The user id alone should be used. The screen_name should be dynamically
-    // displayed
-    fn create_jwt(&self, user_id: &str, screen_name: &str) -> (String, String) {
+    /// Creates a jwt for a user. Display names are looked up from the user's
+    /// profile when needed so that they are never stale.
+    fn create_jwt(&self, user_id: &str) -> (String, String) {
         let exp = Self::get_current_time_secs() + TOKEN_EXP_SECONDS;
 
         let nonce = build_nonce();
@@ -374,7 +399,6 @@ impl Service {
             hashed_nonce,
             "framebastard.com".to_owned(),
             user_id.to_owned(),
-            screen_name.to_owned(),
             is_admin,
             exp,
         );
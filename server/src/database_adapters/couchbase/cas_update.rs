@@ -0,0 +1,85 @@
+use super::super::AdapterError;
+use couchbase::{
+    Collection,
+    CouchbaseError,
+    GetOptions,
+    InsertOptions,
+    ReplaceOptions,
+};
+use log::error;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Updates a document, retrying when it is created or changed in the
+/// meantime. The update function receives the current document, if any, and
+/// returns the new document along with a result, or nothing to leave the
+/// document as it is. Returns the result of the update that was written.
+pub async fn update_with_cas<F, T>(
+    collection: &Collection,
+    db_id: &str,
+    mut update: F,
+) -> Result<Option<T>, AdapterError>
+where
+    F: FnMut(Option<Value>) -> Option<(Value, T)>,
+{
+    loop {
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+        let current = collection
+            .get(db_id, options)
+            .await
+            .and_then(|result| {
+                let cas = result.cas();
+                result.content::<Value>().map(|value| Some((cas, value)))
+            })
+            .or_else(|error| {
+                // Ignore document not found errors
+                match error {
+                    CouchbaseError::DocumentNotFound { ctx: _ } => Ok(None),
+                    _ => Err(error),
+                }
+            })
+            .map_err(|error| {
+                error!("Unexpected couchbase error: {:?}", error);
+                AdapterError::InternalError
+            })?;
+
+        let (cas, document) = match current {
+            Some((cas, document)) => (Some(cas), Some(document)),
+            None => (None, None),
+        };
+
+        let (document, output) = match update(document) {
+            Some(updated) => updated,
+            None => break Ok(None),
+        };
+
+        let result = match cas {
+            Some(cas) => {
+                let options = ReplaceOptions::default()
+                    .timeout(Duration::from_secs(30))
+                    .cas(cas);
+
+                collection.replace(db_id, document, options).await
+            }
+            None => {
+                let options = InsertOptions::default().timeout(Duration::from_secs(30));
+                collection.insert(db_id, document, options).await
+            }
+        };
+
+        match result {
+            Ok(_) => break Ok(Some(output)),
+            Err(CouchbaseError::DocumentExists { ctx: _ })
+            | Err(CouchbaseError::DocumentNotFound { ctx: _ })
+            | Err(CouchbaseError::CasMismatch { ctx: _ }) => {
+                // The document was created, removed or updated since we last
+                // queried. Try again
+                continue;
+            }
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                break Err(AdapterError::InternalError);
+            }
+        }
+    }
+}
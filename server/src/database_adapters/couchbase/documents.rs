@@ -41,6 +41,10 @@ impl Documents {
         self.document_exists(format!("char::{}", id)).await
     }
 
+    pub async fn move_exists(&self, id: &str) -> Result<bool, AdapterError> {
+        self.document_exists(format!("move::{}", id)).await
+    }
+
     pub async fn upsert_game<T>(&self, id: &str, content: T) -> Result<(), AdapterError>
     where
         T: Serialize,
@@ -137,6 +141,44 @@ impl Documents {
             .await
     }
 
+    /// Gets the number of published games, characters and moves
+    pub async fn get_sitemap_count(&self) -> Result<u64, AdapterError> {
+        let options = QueryOptions::default().adhoc(false);
+        self.query_exec
+            .query_expect_one("sitemap/get_count", options)
+            .await
+            .map(|count| count.as_u64().unwrap_or(0))
+    }
+
+    /// Gets the type, id and last approval time of every published game,
+    /// character and move
+    pub async fn get_sitemap_entries(&self) -> Result<Vec<Value>, AdapterError> {
+        let options = QueryOptions::default().adhoc(false);
+        self.query_exec.query("sitemap/get_all", options).await
+    }
+
+    /// Gets the id and last approval time of every published game
+    pub async fn get_sitemap_games(&self) -> Result<Vec<Value>, AdapterError> {
+        let options = QueryOptions::default().adhoc(false);
+        self.query_exec.query("sitemap/get_games", options).await
+    }
+
+    /// Gets the type, id and last approval time of the characters and moves
+    /// of a game
+    pub async fn get_sitemap_game_entries(
+        &self,
+        game_id: &str,
+    ) -> Result<Vec<Value>, AdapterError> {
+        let named_params = json!({ "game_id": game_id });
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query("sitemap/get_game_docs", options)
+            .await
+    }
+
     async fn document_exists(&self, db_id: String) -> Result<bool, AdapterError> {
         let options = ExistsOptions::default().timeout(Duration::from_secs(30));
 
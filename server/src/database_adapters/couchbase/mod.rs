@@ -1,10 +1,19 @@
+mod cas_update;
 mod documents;
 mod name_sets;
+mod notifications;
 mod proposals;
 mod query_executor;
 mod query_store;
 mod search;
+mod search_stats;
+mod upload_jobs;
+mod upload_media;
+mod upload_usage;
+mod users;
+mod webhook_deliveries;
 
+use crate::search_index::SearchIndex;
 use couchbase;
 use query_executor::QueryExecutor;
 use std::sync::Arc;
@@ -12,18 +21,49 @@ use std::sync::Arc;
 pub use self::{
     documents::Documents,
     name_sets::NameSets,
+    notifications::Notifications,
     proposals::Proposals,
     query_store::QueryStore,
-    search::Search,
+    search::{
+        AttributeFilter,
+        Search,
+        SearchQuery,
+    },
+    search_stats::{
+        SearchScope,
+        SearchStats,
+    },
+    upload_jobs::UploadJobs,
+    upload_media::UploadMedia,
+    upload_usage::UploadUsage,
+    users::Users,
+    webhook_deliveries::WebhookDeliveries,
 };
 
-/// Creates instances of all adapters for a couchbase database
+/// The adapters of a couchbase database
+pub struct Adapters {
+    pub documents: Documents,
+    pub proposals: Proposals,
+    pub name_sets: NameSets,
+    pub search: Search,
+    pub search_stats: SearchStats,
+    pub upload_jobs: UploadJobs,
+    pub upload_media: UploadMedia,
+    pub upload_usage: UploadUsage,
+    pub webhook_deliveries: WebhookDeliveries,
+    pub notifications: Notifications,
+    pub users: Users,
+}
+
+/// Creates instances of all adapters for a couchbase database. Searches use
+/// the specified embedded search index instead of couchbase FTS if present.
 pub fn create_adapters(
     host: &str,
     user: &str,
     password: &str,
     query_store: QueryStore,
-) -> (Documents, Proposals, NameSets, Search) {
+    search_index: Option<Arc<SearchIndex>>,
+) -> Adapters {
     let cluster = couchbase::Cluster::connect(
         format!("couchbase://{host}", host = host),
         user.to_owned(),
@@ -32,10 +72,17 @@ pub fn create_adapters(
 
     let query_exec = QueryExecutor::new(cluster, query_store);
     let query_exec = Arc::new(query_exec);
-    (
-        Documents::new(query_exec.clone()),
-        Proposals::new(query_exec.clone()),
-        NameSets::new(query_exec.clone()),
-        Search::new(query_exec),
-    )
+    Adapters {
+        documents: Documents::new(query_exec.clone()),
+        proposals: Proposals::new(query_exec.clone()),
+        name_sets: NameSets::new(query_exec.clone(), search_index.clone()),
+        search: Search::new(query_exec.clone(), search_index),
+        search_stats: SearchStats::new(query_exec.clone()),
+        upload_jobs: UploadJobs::new(query_exec.clone()),
+        upload_media: UploadMedia::new(query_exec.clone()),
+        upload_usage: UploadUsage::new(query_exec.clone()),
+        webhook_deliveries: WebhookDeliveries::new(query_exec.clone()),
+        notifications: Notifications::new(query_exec.clone()),
+        users: Users::new(query_exec),
+    }
 }
@@ -2,18 +2,57 @@ use super::{
     super::AdapterError,
     QueryExecutor,
 };
-use couchbase::QueryOptions;
-use serde_json::json;
+use crate::search_index::{
+    NameSet,
+    SearchIndex,
+};
+use couchbase::{
+    QueryOptions,
+    QueryScanConsistency,
+};
+use log::{
+    error,
+    info,
+};
+use serde_json::{
+    json,
+    Value,
+};
 use std::sync::Arc;
 
 /// The couchbase name_sets adapter
 pub struct NameSets {
     query_exec: Arc<QueryExecutor>,
+    search_index: Option<Arc<SearchIndex>>,
 }
 
 impl NameSets {
-    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
-        Self { query_exec }
+    pub fn new(query_exec: Arc<QueryExecutor>, search_index: Option<Arc<SearchIndex>>) -> Self {
+        Self {
+            query_exec,
+            search_index,
+        }
+    }
+
+    /// Rebuilds the embedded search index, if any, from all name_sets
+    pub async fn rebuild_index(&self) -> Result<(), AdapterError> {
+        let search_index = match &self.search_index {
+            Some(search_index) => search_index,
+            None => return Ok(()),
+        };
+
+        let rows = self
+            .query_exec
+            .query("name_sets/get_all", QueryOptions::default())
+            .await?;
+
+        search_index.rebuild(Self::parse_name_sets(rows)?);
+        info!(
+            "Built search index with {} name sets",
+            search_index.document_count()
+        );
+
+        Ok(())
     }
 
     /// Updates name_sets for the specified game and its children
@@ -35,7 +74,8 @@ impl NameSets {
             .query("name_sets/update_game_moves", get_options())
             .await?;
 
-        Ok(())
+        self.refresh_index(&format!("game::{}", id), Some(id), None)
+            .await
     }
 
     /// Updates name_sets for the specified character and its children
@@ -53,7 +93,8 @@ impl NameSets {
             .query("name_sets/update_char_moves", get_options())
             .await?;
 
-        Ok(())
+        self.refresh_index(&format!("char::{}", id), None, Some(id))
+            .await
     }
 
     /// Updates name_sets for the specified character and its children
@@ -64,6 +105,50 @@ impl NameSets {
             .query("name_sets/update_move", options)
             .await?;
 
+        self.refresh_index(&format!("move::{}", id), None, None)
+            .await
+    }
+
+    /// Updates the embedded search index, if any, with the name_set of a
+    /// document and those of its descendants
+    async fn refresh_index(
+        &self,
+        key: &str,
+        game: Option<&str>,
+        character: Option<&str>,
+    ) -> Result<(), AdapterError> {
+        let search_index = match &self.search_index {
+            Some(search_index) => search_index,
+            None => return Ok(()),
+        };
+
+        let named_params = json!({
+            "key": format!("nset::{}", key),
+            "game": game,
+            "character": character,
+        });
+
+        let options = QueryOptions::default()
+            .named_parameters(named_params)
+            .scan_consistency(QueryScanConsistency::RequestPlus);
+
+        let rows = self
+            .query_exec
+            .query("name_sets/get_related", options)
+            .await?;
+
+        search_index.upsert(Self::parse_name_sets(rows)?);
         Ok(())
     }
+
+    fn parse_name_sets(rows: Vec<Value>) -> Result<Vec<NameSet>, AdapterError> {
+        rows.into_iter()
+            .map(|row| {
+                serde_json::from_value::<NameSet>(row).map_err(|error| {
+                    error!("Unable to parse name_set: {}", error);
+                    AdapterError::InternalError
+                })
+            })
+            .collect()
+    }
 }
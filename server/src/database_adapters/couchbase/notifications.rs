@@ -0,0 +1,149 @@
+use super::{
+    super::AdapterError,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    CouchbaseError,
+    GetOptions,
+    InsertOptions,
+    QueryOptions,
+    UpsertOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase notifications adapter. Stores the notifications of users
+/// and the events each user wants to be notified of. Records are stored in
+/// the `internal` bucket; notifications expire after the configured
+/// retention period.
+pub struct Notifications {
+    query_exec: Arc<QueryExecutor>,
+    data_bucket: Bucket,
+}
+
+impl Notifications {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+            query_exec,
+        }
+    }
+
+    /// Inserts a notification of a user, which expires after the specified
+    /// number of seconds
+    pub async fn insert(
+        &self,
+        user_id: &str,
+        id: &str,
+        content: &Value,
+        retention: u32,
+    ) -> Result<(), AdapterError> {
+        let db_id = format!("ntfy::{}::{}", user_id, id);
+        let options = InsertOptions::default()
+            .timeout(Duration::from_secs(30))
+            .expiry(Duration::from_secs(retention as u64));
+
+        self.data_bucket
+            .default_collection()
+            .insert(&db_id, content, options)
+            .await
+            .map(|_| ())
+            .map_err(|error| {
+                error!("Unexpected couchbase error: {:?}", error);
+                AdapterError::InternalError
+            })
+    }
+
+    /// Gets the most recent notifications of a user along with the number of
+    /// unread ones
+    pub async fn get_list(
+        &self,
+        user_id: &str,
+        unread_only: bool,
+        offset: u16,
+        limit: u16,
+    ) -> Result<Value, AdapterError> {
+        let named_params = json!({
+            "userId": user_id,
+            "unreadOnly": unread_only,
+            "offset": offset,
+            "limit": limit,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query_expect_one("notifications/get_list", options)
+            .await
+    }
+
+    /// Marks a notification of a user as read, or all of them if the id is
+    /// empty. Returns the ids of the notifications that were updated.
+    pub async fn mark_read(&self, user_id: &str, id: &str) -> Result<Vec<Value>, AdapterError> {
+        let named_params = json!({
+            "userId": user_id,
+            "id": id,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query("notifications/mark_read", options)
+            .await
+    }
+
+    /// Gets the notification settings of a user. Users that never changed
+    /// them have none.
+    pub async fn get_settings(&self, user_id: &str) -> Result<Option<Value>, AdapterError> {
+        let db_id = format!("nset::{}", user_id);
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+
+        let result = self
+            .data_bucket
+            .default_collection()
+            .get(db_id, options)
+            .await
+            .and_then(|result| result.content::<Value>());
+
+        match result {
+            Ok(settings) => Ok(Some(settings)),
+            Err(CouchbaseError::DocumentNotFound { ctx: _ }) => Ok(None),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                Err(AdapterError::InternalError)
+            }
+        }
+    }
+
+    /// Upserts the notification settings of a user
+    pub async fn upsert_settings(
+        &self,
+        user_id: &str,
+        settings: &Value,
+    ) -> Result<(), AdapterError> {
+        let db_id = format!("nset::{}", user_id);
+        let options = UpsertOptions::default().timeout(Duration::from_secs(30));
+
+        self.data_bucket
+            .default_collection()
+            .upsert(&db_id, settings, options)
+            .await
+            .map(|_| ())
+            .map_err(|error| {
+                error!("Unexpected couchbase error: {:?}", error);
+                AdapterError::InternalError
+            })
+    }
+}
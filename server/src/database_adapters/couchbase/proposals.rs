@@ -169,6 +169,56 @@ impl Proposals {
             .await
     }
 
+    /// Gets the most recently approved proposals of a document and its
+    /// children, or of all documents if the scope is empty. Each proposal
+    /// includes the document of the proposal approved before it, if any.
+    pub async fn get_approved_feed(
+        &self,
+        scope: &str,
+        limit: u16,
+    ) -> Result<Vec<Value>, AdapterError> {
+        let named_params = json!({
+            "scope": scope,
+            "limit": limit,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query("proposals/get_approved_feed", options)
+            .await
+    }
+
+    /// Gets the contribution statistics of an author: their number of
+    /// proposals by status, the documents and games their approved proposals
+    /// touched, their latest name and the time of their first proposal
+    pub async fn get_author_stats(&self, author_id: &str) -> Result<Value, AdapterError> {
+        let named_params = json!({ "authorId": author_id });
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query_expect_one("proposals/get_author_stats", options)
+            .await
+    }
+
+    /// Gets the version and author of each pending proposal of a target.
+    /// Waits for the index to include recently closed proposals.
+    pub async fn get_pending_authors(&self, target: &str) -> Result<Vec<Value>, AdapterError> {
+        let named_params = json!({ "target": target });
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params)
+            .scan_consistency(QueryScanConsistency::RequestPlus);
+
+        self.query_exec
+            .query("proposals/get_pending_authors", options)
+            .await
+    }
+
     /// Gets the latest authors for a target document
     pub async fn get_latest_authors(
         &self,
@@ -2,66 +2,102 @@ use super::{
     super::AdapterError,
     QueryExecutor,
 };
+use crate::search_index::{
+    Scope,
+    SearchIndex,
+};
 use couchbase::QueryOptions;
+use serde_derive::Serialize;
 use serde_json::{
     json,
+    Map,
     Value,
 };
 use std::sync::Arc;
 
-/// Search adapter
+/// The maximum number of full text matches considered for filtering and
+/// faceting. `totalCount` and the facets of a search never exceed it.
+const CANDIDATE_LIMIT: u16 = 500;
+
+/// A filter on the attributes of a searched document
+#[derive(Serialize, Debug)]
+pub struct AttributeFilter {
+    /// The normalized (lower case alpha-numeric) attribute title
+    pub title: String,
+    /// One of `eq`, `contains`, `sentiment`, `lt`, `lte`, `gt` or `gte`
+    pub op: String,
+    /// The lower case value to compare against
+    pub value: String,
+    /// The numeric value to compare against for numeric operations
+    pub number: Option<f64>,
+}
+
+/// The term, filters and paging of a search
+pub struct SearchQuery<'a> {
+    pub search_term: &'a str,
+    /// Limits the results, but not the type facets, to a document type
+    pub doc_type: Option<&'a str>,
+    pub filters: &'a [AttributeFilter],
+    pub offset: u16,
+    pub limit: u16,
+}
+
+/// Search adapter. Uses the couchbase FTS `name_set` index unless an
+/// embedded search index is specified.
 pub struct Search {
     query_exec: Arc<QueryExecutor>,
+    search_index: Option<Arc<SearchIndex>>,
 }
 
 impl Search {
-    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
-        Self { query_exec }
+    pub fn new(query_exec: Arc<QueryExecutor>, search_index: Option<Arc<SearchIndex>>) -> Self {
+        Self {
+            query_exec,
+            search_index,
+        }
     }
 
     /// Searches all documents
-    pub async fn search_all(
-        &self,
-        search_term: &str,
-        offset: u16,
-        limit: u16,
-    ) -> Result<Vec<Value>, AdapterError> {
+    pub async fn search_all(&self, query: &SearchQuery<'_>) -> Result<Value, AdapterError> {
+        if let Some(search_index) = &self.search_index {
+            return self.search_embedded(search_index, Scope::All, query).await;
+        }
+
         // Build the named params
         let named_params = json!({
-            "offset": offset,
-            "limit": limit,
-            "searchTerm": search_term,
+            "offset": query.offset,
+            "limit": query.limit,
+            "candidateLimit": CANDIDATE_LIMIT,
+            "searchTerm": query.search_term,
+            "docType": query.doc_type,
+            "filters": query.filters,
         });
 
         let options = QueryOptions::default()
             .adhoc(false)
             .named_parameters(named_params);
 
-        self.query_exec.query("search/search_all", options).await
+        self.query_exec
+            .query_expect_one("search/search_all", options)
+            .await
     }
 
     /// Searches documents within a specified game
     pub async fn search_game(
         &self,
         target: &str,
-        search_term: &str,
-        offset: u16,
-        limit: u16,
-    ) -> Result<Vec<Value>, AdapterError> {
-        self.search_target(target, "game", search_term, offset, limit)
-            .await
+        query: &SearchQuery<'_>,
+    ) -> Result<Value, AdapterError> {
+        self.search_target(target, "game", query).await
     }
 
     /// Searches documents within a specified character
     pub async fn search_character(
         &self,
         target: &str,
-        search_term: &str,
-        offset: u16,
-        limit: u16,
-    ) -> Result<Vec<Value>, AdapterError> {
-        self.search_target(target, "character", search_term, offset, limit)
-            .await
+        query: &SearchQuery<'_>,
+    ) -> Result<Value, AdapterError> {
+        self.search_target(target, "character", query).await
     }
 
     /// Searches documents within a specified target
@@ -69,23 +105,94 @@ impl Search {
         &self,
         target: &str,
         target_type: &str,
-        search_term: &str,
-        offset: u16,
-        limit: u16,
-    ) -> Result<Vec<Value>, AdapterError> {
+        query: &SearchQuery<'_>,
+    ) -> Result<Value, AdapterError> {
+        if let Some(search_index) = &self.search_index {
+            let scope = match target_type {
+                "game" => Scope::Game(target),
+                _ => Scope::Character(target),
+            };
+
+            return self.search_embedded(search_index, scope, query).await;
+        }
+
         // Build the named params
         let named_params = json!({
-            "offset": offset,
-            "limit": limit,
-            "searchTerm": search_term,
+            "offset": query.offset,
+            "limit": query.limit,
+            "candidateLimit": CANDIDATE_LIMIT,
+            "searchTerm": query.search_term,
             "target": target,
             "targetType": target_type,
+            "docType": query.doc_type,
+            "filters": query.filters,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query_expect_one("search/search_target", options)
+            .await
+    }
+
+    /// Gets the distinct names of all searchable documents
+    pub async fn get_names(&self) -> Result<Vec<String>, AdapterError> {
+        if let Some(search_index) = &self.search_index {
+            return Ok(search_index.names());
+        }
+
+        let rows = self
+            .query_exec
+            .query("search/get_names", QueryOptions::default())
+            .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| row.as_str().map(|name| name.to_owned()))
+            .collect())
+    }
+
+    /// Searches documents using the embedded search index
+    async fn search_embedded(
+        &self,
+        search_index: &SearchIndex,
+        scope: Scope<'_>,
+        query: &SearchQuery<'_>,
+    ) -> Result<Value, AdapterError> {
+        let hits = search_index.search(query.search_term, scope, CANDIDATE_LIMIT as usize);
+        let keys: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+        let candidates: Map<String, Value> = hits
+            .iter()
+            .map(|hit| {
+                let candidate = json!({
+                    "score": hit.score,
+                    "matches": hit.locations,
+                    "names": hit.names,
+                    "parentNames": hit.parent_names,
+                });
+
+                (hit.id.clone(), candidate)
+            })
+            .collect();
+
+        // Build the named params
+        let named_params = json!({
+            "offset": query.offset,
+            "limit": query.limit,
+            "keys": keys,
+            "candidates": candidates,
+            "docType": query.doc_type,
+            "filters": query.filters,
         });
 
         let options = QueryOptions::default()
             .adhoc(false)
             .named_parameters(named_params);
 
-        self.query_exec.query("search/search_target", options).await
+        self.query_exec
+            .query_expect_one("search/search_keys", options)
+            .await
     }
 }
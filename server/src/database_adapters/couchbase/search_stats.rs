@@ -0,0 +1,189 @@
+use super::{
+    super::AdapterError,
+    cas_update::update_with_cas,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    QueryOptions,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+
+/// The number of seconds in a day. Stats are aggregated per day.
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The maximum number of bytes of a search term that is recorded. Keeps
+/// document keys below couchbase's limit of 250 bytes.
+const MAX_TERM_LENGTH: usize = 64;
+
+/// The scope of a recorded search
+pub struct SearchScope<'a> {
+    pub game: Option<&'a str>,
+    pub character: Option<&'a str>,
+}
+
+/// The couchbase search statistics adapter. Searches and clicked results are
+/// aggregated per day, term and scope in the `internal` bucket.
+pub struct SearchStats {
+    query_exec: Arc<QueryExecutor>,
+    data_bucket: Bucket,
+}
+
+impl SearchStats {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+            query_exec,
+        }
+    }
+
+    /// Records a search and the number of results it returned
+    pub async fn record_search(
+        &self,
+        search_term: &str,
+        scope: &SearchScope<'_>,
+        result_count: u64,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        let term = Self::normalize_term(search_term);
+        let day = time_stamp - time_stamp % SECONDS_PER_DAY;
+        let id = format!("sstat::{}::{}::{}", day, Self::scope_key(scope), term);
+        let initial = json!({
+            "type": "search_stat",
+            "day": day,
+            "term": term,
+            "game": scope.game,
+            "character": scope.character,
+            "searches": 0,
+            "zeroResults": 0,
+            "clicks": 0,
+        });
+
+        self.update(&id, initial, |stat| {
+            stat["searches"] = json!(stat["searches"].as_u64().unwrap_or(0) + 1);
+            stat["lastResultCount"] = json!(result_count);
+            if result_count == 0 {
+                stat["zeroResults"] = json!(stat["zeroResults"].as_u64().unwrap_or(0) + 1);
+            }
+        })
+        .await
+    }
+
+    /// Records a clicked search result
+    pub async fn record_click(
+        &self,
+        search_term: &str,
+        scope: &SearchScope<'_>,
+        doc_type: &str,
+        doc_id: &str,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        let term = Self::normalize_term(search_term);
+        let day = time_stamp - time_stamp % SECONDS_PER_DAY;
+        let scope_key = Self::scope_key(scope);
+
+        // The click count of the search itself
+        let id = format!("sstat::{}::{}::{}", day, scope_key, term);
+        let initial = json!({
+            "type": "search_stat",
+            "day": day,
+            "term": term,
+            "game": scope.game,
+            "character": scope.character,
+            "searches": 0,
+            "zeroResults": 0,
+            "clicks": 0,
+        });
+
+        self.update(&id, initial, |stat| {
+            stat["clicks"] = json!(stat["clicks"].as_u64().unwrap_or(0) + 1);
+        })
+        .await?;
+
+        // The click count of the clicked document
+        let id = format!("sclk::{}::{}::{}::{}", day, scope_key, term, doc_id);
+
+        let initial = json!({
+            "type": "search_click",
+            "day": day,
+            "term": term,
+            "game": scope.game,
+            "character": scope.character,
+            "docType": doc_type,
+            "docId": doc_id,
+            "clicks": 0,
+        });
+
+        self.update(&id, initial, |click| {
+            click["clicks"] = json!(click["clicks"].as_u64().unwrap_or(0) + 1);
+        })
+        .await
+    }
+
+    /// Gets the top searches, zero result searches and clicked results
+    /// recorded since the specified time stamp
+    pub async fn get_summary(&self, since: u64, limit: u16) -> Result<Value, AdapterError> {
+        let named_params = json!({
+            "since": since - since % SECONDS_PER_DAY,
+            "limit": limit,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query_expect_one("search_stats/get_summary", options)
+            .await
+    }
+
+    /// Normalizes a search term so equivalent searches are aggregated
+    /// together
+    fn normalize_term(search_term: &str) -> String {
+        let mut term = search_term
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ")
+            .to_lowercase();
+
+        if term.len() > MAX_TERM_LENGTH {
+            // Truncate at a character boundary
+            let end = (0..=MAX_TERM_LENGTH)
+                .rev()
+                .find(|index| term.is_char_boundary(*index))
+                .unwrap_or(0);
+
+            term.truncate(end);
+        }
+
+        term
+    }
+
+    fn scope_key(scope: &SearchScope) -> String {
+        match (scope.game, scope.character) {
+            (Some(game), _) => format!("game.{}", game),
+            (None, Some(character)) => format!("char.{}", character),
+            (None, None) => "all".to_owned(),
+        }
+    }
+
+    /// Applies an update to a document, creating it from the initial value if
+    /// it does not exist. Retries if the document is concurrently modified.
+    async fn update<F>(&self, id: &str, initial: Value, apply: F) -> Result<(), AdapterError>
+    where
+        F: Fn(&mut Value),
+    {
+        let collection = self.data_bucket.default_collection();
+        update_with_cas(&collection, id, |current| {
+            let mut value = current.unwrap_or_else(|| initial.clone());
+            apply(&mut value);
+            Some((value, ()))
+        })
+        .await
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,105 @@
+use super::{
+    super::AdapterError,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    CouchbaseError,
+    GetOptions,
+    QueryOptions,
+    UpsertOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase upload jobs adapter. Jobs track the background processing
+/// of uploaded media and are stored in the `internal` bucket.
+pub struct UploadJobs {
+    query_exec: Arc<QueryExecutor>,
+    data_bucket: Bucket,
+}
+
+impl UploadJobs {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+            query_exec,
+        }
+    }
+
+    /// Gets an upload job given its id
+    pub async fn get(&self, id: &str) -> Result<Value, AdapterError> {
+        let db_id = format!("ujob::{}", id);
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+
+        self.data_bucket
+            .default_collection()
+            .get(db_id, options)
+            .await
+            .and_then(|result| result.content::<Value>())
+            .map_err(|error| {
+                match error {
+                    CouchbaseError::DocumentNotFound { ctx: _ } => AdapterError::DocumentNotFound,
+                    _ => {
+                        error!("Unexpected couchbase error: {:?}", error);
+                        AdapterError::InternalError
+                    }
+                }
+            })
+    }
+
+    /// Upserts an upload job. Jobs that have finished are removed after the
+    /// specified retention period in seconds.
+    pub async fn upsert(
+        &self,
+        id: &str,
+        content: &Value,
+        retention: Option<u32>,
+    ) -> Result<(), AdapterError> {
+        let db_id = format!("ujob::{}", id);
+        let mut options = UpsertOptions::default().timeout(Duration::from_secs(30));
+        if let Some(retention) = retention {
+            options = options.expiry(Duration::from_secs(retention as u64));
+        }
+
+        self.data_bucket
+            .default_collection()
+            .upsert(&db_id, content, options)
+            .await
+            .map(|_| ())
+            .map_err(|error| {
+                error!("Unexpected couchbase error: {:?}", error);
+                AdapterError::InternalError
+            })
+    }
+
+    /// Marks all jobs that are still queued or running as failed. Used at
+    /// start up as such jobs were interrupted. The failed jobs are removed
+    /// after the specified retention period in seconds.
+    pub async fn fail_unfinished(
+        &self,
+        time_stamp: u64,
+        retention: u32,
+    ) -> Result<(), AdapterError> {
+        let named_params = json!({
+            "updated": time_stamp,
+            "expiration": time_stamp + retention as u64,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query("upload_jobs/fail_unfinished", options)
+            .await
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,212 @@
+use super::{
+    super::AdapterError,
+    cas_update::update_with_cas,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    CouchbaseError,
+    GetOptions,
+    InsertOptions,
+    QueryOptions,
+    RemoveOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase upload media adapter. Each published upload is stored by
+/// the hash of its content along with the ids of the documents and proposals
+/// that reference it. Records are stored in the `internal` bucket.
+pub struct UploadMedia {
+    query_exec: Arc<QueryExecutor>,
+    data_bucket: Bucket,
+}
+
+impl UploadMedia {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+            query_exec,
+        }
+    }
+
+    /// Gets the record of an upload given its file name
+    pub async fn get(&self, file_name: &str) -> Result<Value, AdapterError> {
+        let db_id = format!("umed::{}", file_name);
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+
+        self.data_bucket
+            .default_collection()
+            .get(db_id, options)
+            .await
+            .and_then(|result| result.content::<Value>())
+            .map_err(|error| {
+                match error {
+                    CouchbaseError::DocumentNotFound { ctx: _ } => AdapterError::DocumentNotFound,
+                    _ => {
+                        error!("Unexpected couchbase error: {:?}", error);
+                        AdapterError::InternalError
+                    }
+                }
+            })
+    }
+
+    /// Inserts the record of a newly published upload. Existing records are
+    /// left untouched.
+    pub async fn insert(&self, file_name: &str, content: &Value) -> Result<(), AdapterError> {
+        let db_id = format!("umed::{}", file_name);
+        let options = InsertOptions::default().timeout(Duration::from_secs(30));
+
+        match self
+            .data_bucket
+            .default_collection()
+            .insert(&db_id, content, options)
+            .await
+        {
+            Ok(_) | Err(CouchbaseError::DocumentExists { ctx: _ }) => Ok(()),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                Err(AdapterError::InternalError)
+            }
+        }
+    }
+
+    /// Adds a reference to an upload. Uploads without a record (e.g. those
+    /// published before uploads were tracked) are ignored.
+    pub async fn add_ref(
+        &self,
+        file_name: &str,
+        ref_id: &str,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        self.update(file_name, |media| {
+            let refs = media["refs"].as_array_mut();
+            match refs {
+                Some(refs) if refs.iter().any(|r| r == ref_id) => return,
+                Some(refs) => refs.push(json!(ref_id)),
+                None => media["refs"] = json!([ref_id]),
+            }
+
+            media["updated"] = json!(time_stamp);
+        })
+        .await
+    }
+
+    /// Marks an upload as recently used
+    pub async fn touch(&self, file_name: &str, time_stamp: u64) -> Result<(), AdapterError> {
+        self.update(file_name, |media| media["updated"] = json!(time_stamp))
+            .await
+    }
+
+    /// Removes a reference from an upload
+    pub async fn remove_ref(
+        &self,
+        file_name: &str,
+        ref_id: &str,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        self.update(file_name, |media| {
+            if let Some(refs) = media["refs"].as_array_mut() {
+                let count = refs.len();
+                refs.retain(|r| r != ref_id);
+                if refs.len() != count {
+                    media["updated"] = json!(time_stamp);
+                }
+            }
+        })
+        .await
+    }
+
+    /// Gets the file names of uploads that have not been referenced since
+    /// the specified time stamp
+    pub async fn get_unreferenced(
+        &self,
+        before: u64,
+        limit: u16,
+    ) -> Result<Vec<Value>, AdapterError> {
+        let named_params = json!({
+            "before": before,
+            "limit": limit,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query("upload_media/get_unreferenced", options)
+            .await
+    }
+
+    /// Removes the record of an upload if it is still unreferenced. Returns
+    /// the removed record.
+    pub async fn remove_unreferenced(
+        &self,
+        file_name: &str,
+    ) -> Result<Option<Value>, AdapterError> {
+        let db_id = format!("umed::{}", file_name);
+        let collection = self.data_bucket.default_collection();
+
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+        let (cas, media) = match collection.get(&db_id, options).await.and_then(|result| {
+            let cas = result.cas();
+            result.content::<Value>().map(|value| (cas, value))
+        }) {
+            Ok(result) => result,
+            Err(CouchbaseError::DocumentNotFound { ctx: _ }) => return Ok(None),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                return Err(AdapterError::InternalError);
+            }
+        };
+
+        if media["refs"]
+            .as_array()
+            .map_or(false, |refs| !refs.is_empty())
+        {
+            return Ok(None);
+        }
+
+        // Only remove the record if it was not referenced in the meantime
+        let options = RemoveOptions::default()
+            .timeout(Duration::from_secs(30))
+            .cas(cas);
+
+        match collection.remove(&db_id, options).await {
+            Ok(_) => Ok(Some(media)),
+            Err(CouchbaseError::DocumentNotFound { ctx: _ })
+            | Err(CouchbaseError::DocumentExists { ctx: _ })
+            | Err(CouchbaseError::CasMismatch { ctx: _ }) => Ok(None),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                Err(AdapterError::InternalError)
+            }
+        }
+    }
+
+    /// Applies an update to the record of an upload if it exists. Retries if
+    /// the record is concurrently modified.
+    async fn update<F>(&self, file_name: &str, apply: F) -> Result<(), AdapterError>
+    where
+        F: Fn(&mut Value),
+    {
+        let db_id = format!("umed::{}", file_name);
+        let collection = self.data_bucket.default_collection();
+
+        // Uploads that are not recorded are left alone
+        update_with_cas(&collection, &db_id, |media| {
+            let mut media = media?;
+            apply(&mut media);
+            Some((media, ()))
+        })
+        .await
+        .map(|_| ())
+    }
+}
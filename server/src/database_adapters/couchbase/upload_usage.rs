@@ -0,0 +1,191 @@
+use super::{
+    super::AdapterError,
+    cas_update::update_with_cas,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    CouchbaseError,
+    GetOptions,
+    RemoveOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase upload usage adapter. Records the time and size of each
+/// upload of a user so upload quotas can be enforced over rolling windows.
+/// Bytes are reserved before an upload starts so concurrent uploads cannot
+/// exceed a quota.
+/// Records are stored in the `internal` bucket.
+pub struct UploadUsage {
+    data_bucket: Bucket,
+}
+
+impl UploadUsage {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+        }
+    }
+
+    /// Gets the uploads recorded for a user. Users that have not uploaded
+    /// anything have no uploads.
+    pub async fn get(&self, user_id: &str) -> Result<Vec<Value>, AdapterError> {
+        let db_id = format!("uuse::{}", user_id);
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+
+        let result = self
+            .data_bucket
+            .default_collection()
+            .get(db_id, options)
+            .await
+            .and_then(|result| result.content::<Value>());
+
+        match result {
+            Ok(mut usage) => Ok(Self::take_uploads(&mut usage)),
+            Err(CouchbaseError::DocumentNotFound { ctx: _ }) => Ok(Vec::new()),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                Err(AdapterError::InternalError)
+            }
+        }
+    }
+
+    /// Reserves bytes for an upload if `reserve` accepts it. `reserve`
+    /// receives the uploads recorded since `since` and returns the number of
+    /// bytes to reserve, or nothing to reject the upload. Older uploads are
+    /// discarded. Returns whether the upload was accepted.
+    pub async fn reserve<F>(
+        &self,
+        user_id: &str,
+        upload_id: &str,
+        time_stamp: u64,
+        since: u64,
+        mut reserve: F,
+    ) -> Result<bool, AdapterError>
+    where
+        F: FnMut(&[Value]) -> Option<u64>,
+    {
+        let db_id = format!("uuse::{}", user_id);
+        let collection = self.data_bucket.default_collection();
+
+        update_with_cas(&collection, &db_id, |usage| {
+            let mut uploads = match usage {
+                Some(mut usage) => Self::take_uploads(&mut usage),
+                None => Vec::new(),
+            };
+
+            uploads.retain(|upload| upload["time"].as_u64().map_or(false, |time| time >= since));
+            let bytes = reserve(&uploads)?;
+            uploads.push(json!({
+                "id": upload_id,
+                "time": time_stamp,
+                "bytes": bytes,
+            }));
+
+            Some((Self::build_usage(user_id, uploads, time_stamp), ()))
+        })
+        .await
+        .map(|reserved| reserved.is_some())
+    }
+
+    /// Changes the number of bytes reserved for an upload, e.g. once the
+    /// actual size of an upload is known
+    pub async fn resize(
+        &self,
+        user_id: &str,
+        upload_id: &str,
+        bytes: u64,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        self.update_upload(user_id, upload_id, time_stamp, |upload| {
+            upload["bytes"] = json!(bytes);
+            true
+        })
+        .await
+    }
+
+    /// Releases the bytes reserved for an upload that failed
+    pub async fn release(
+        &self,
+        user_id: &str,
+        upload_id: &str,
+        time_stamp: u64,
+    ) -> Result<(), AdapterError> {
+        self.update_upload(user_id, upload_id, time_stamp, |_| false)
+            .await
+    }
+
+    /// Removes all uploads recorded for a user
+    pub async fn reset(&self, user_id: &str) -> Result<(), AdapterError> {
+        let db_id = format!("uuse::{}", user_id);
+        let options = RemoveOptions::default().timeout(Duration::from_secs(30));
+
+        match self
+            .data_bucket
+            .default_collection()
+            .remove(&db_id, options)
+            .await
+        {
+            Ok(_) | Err(CouchbaseError::DocumentNotFound { ctx: _ }) => Ok(()),
+            Err(error) => {
+                error!("Unexpected couchbase error: {:?}", error);
+                Err(AdapterError::InternalError)
+            }
+        }
+    }
+
+    /// Applies an update to a recorded upload. The upload is removed if the
+    /// update returns false.
+    async fn update_upload<F>(
+        &self,
+        user_id: &str,
+        upload_id: &str,
+        time_stamp: u64,
+        update: F,
+    ) -> Result<(), AdapterError>
+    where
+        F: Fn(&mut Value) -> bool,
+    {
+        let db_id = format!("uuse::{}", user_id);
+        let collection = self.data_bucket.default_collection();
+
+        update_with_cas(&collection, &db_id, |usage| {
+            let mut uploads = Self::take_uploads(&mut usage?);
+            let position = uploads
+                .iter()
+                .position(|upload| upload["id"] == upload_id)?;
+
+            if !update(&mut uploads[position]) {
+                uploads.remove(position);
+            }
+
+            Some((Self::build_usage(user_id, uploads, time_stamp), ()))
+        })
+        .await
+        .map(|_| ())
+    }
+
+    fn build_usage(user_id: &str, uploads: Vec<Value>, time_stamp: u64) -> Value {
+        json!({
+            "type": "upload_usage",
+            "userId": user_id,
+            "uploads": uploads,
+            "updated": time_stamp,
+        })
+    }
+
+    fn take_uploads(usage: &mut Value) -> Vec<Value> {
+        match usage["uploads"].take() {
+            Value::Array(uploads) => uploads,
+            _ => Vec::new(),
+        }
+    }
+}
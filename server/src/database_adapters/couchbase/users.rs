@@ -0,0 +1,135 @@
+use super::{
+    super::AdapterError,
+    cas_update::update_with_cas,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    CouchbaseError,
+    GetOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase users adapter. Stores the profile of each user that signed
+/// in along with the provider accounts linked to it. Records are stored in
+/// the `internal` bucket.
+pub struct Users {
+    data_bucket: Bucket,
+}
+
+impl Users {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+        }
+    }
+
+    /// Gets the profile of a user
+    pub async fn get(&self, user_id: &str) -> Result<Value, AdapterError> {
+        let db_id = format!("user::{}", user_id);
+        let options = GetOptions::default().timeout(Duration::from_secs(30));
+
+        self.data_bucket
+            .default_collection()
+            .get(db_id, options)
+            .await
+            .and_then(|result| result.content::<Value>())
+            .map_err(|error| {
+                match error {
+                    CouchbaseError::DocumentNotFound { ctx: _ } => AdapterError::DocumentNotFound,
+                    _ => {
+                        error!("Unexpected couchbase error: {:?}", error);
+                        AdapterError::InternalError
+                    }
+                }
+            })
+    }
+
+    /// Records a sign in of a user with a provider account, e.g. a Twitter
+    /// account. Creates the user's profile on their first sign in. Returns
+    /// the profile.
+    pub async fn record_login(
+        &self,
+        user_id: &str,
+        account: Value,
+        display_name: &str,
+        time_stamp: u64,
+    ) -> Result<Value, AdapterError> {
+        self.update(user_id, |profile| {
+            let mut profile = profile.unwrap_or_else(|| {
+                json!({
+                    "type": "user",
+                    "id": user_id,
+                    "displayName": display_name,
+                    "avatar": null,
+                    "accounts": [],
+                    "joined": time_stamp,
+                })
+            });
+
+            // Replace the previous details of the account
+            let mut accounts = match profile["accounts"].take() {
+                Value::Array(accounts) => accounts,
+                _ => Vec::new(),
+            };
+
+            accounts.retain(|linked| {
+                linked["provider"] != account["provider"] || linked["id"] != account["id"]
+            });
+
+            accounts.push(account.clone());
+            profile["accounts"] = Value::Array(accounts);
+            profile["lastLogin"] = time_stamp.into();
+            Some(profile)
+        })
+        .await
+    }
+
+    /// Changes the display name and avatar of a user. Fields that are not
+    /// specified are kept. Returns the profile.
+    pub async fn update_profile(
+        &self,
+        user_id: &str,
+        display_name: Option<&str>,
+        avatar: Option<Option<&str>>,
+    ) -> Result<Value, AdapterError> {
+        self.update(user_id, |profile| {
+            let mut profile = profile?;
+            if let Some(display_name) = display_name {
+                profile["displayName"] = display_name.into();
+            }
+
+            if let Some(avatar) = avatar {
+                profile["avatar"] = avatar.into();
+            }
+
+            Some(profile)
+        })
+        .await
+    }
+
+    /// Updates the profile of a user, retrying when it changed in the
+    /// meantime. The update function receives the current profile, if any,
+    /// and returns the new one, or nothing if it cannot be updated.
+    async fn update<F>(&self, user_id: &str, update: F) -> Result<Value, AdapterError>
+    where
+        F: Fn(Option<Value>) -> Option<Value>,
+    {
+        let db_id = format!("user::{}", user_id);
+        let collection = self.data_bucket.default_collection();
+
+        update_with_cas(&collection, &db_id, |profile| {
+            update(profile).map(|profile| (profile.clone(), profile))
+        })
+        .await?
+        .ok_or(AdapterError::DocumentNotFound)
+    }
+}
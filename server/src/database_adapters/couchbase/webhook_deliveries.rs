@@ -0,0 +1,82 @@
+use super::{
+    super::AdapterError,
+    QueryExecutor,
+};
+use couchbase::{
+    Bucket,
+    QueryOptions,
+    UpsertOptions,
+};
+use log::error;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
+/// The couchbase webhook deliveries adapter. Records each delivery of a
+/// webhook event along with its attempts. Records are stored in the
+/// `internal` bucket and expire after the configured retention period.
+pub struct WebhookDeliveries {
+    query_exec: Arc<QueryExecutor>,
+    data_bucket: Bucket,
+}
+
+impl WebhookDeliveries {
+    pub fn new(query_exec: Arc<QueryExecutor>) -> Self {
+        Self {
+            data_bucket: query_exec.get_cluster().bucket("internal"),
+            query_exec,
+        }
+    }
+
+    /// Upserts a delivery, which expires after the specified number of
+    /// seconds
+    pub async fn upsert(
+        &self,
+        id: &str,
+        content: &Value,
+        retention: u32,
+    ) -> Result<(), AdapterError> {
+        let db_id = format!("whdl::{}", id);
+        let options = UpsertOptions::default()
+            .timeout(Duration::from_secs(30))
+            .expiry(Duration::from_secs(retention as u64));
+
+        self.data_bucket
+            .default_collection()
+            .upsert(&db_id, content, options)
+            .await
+            .map(|_| ())
+            .map_err(|error| {
+                error!("Unexpected couchbase error: {:?}", error);
+                AdapterError::InternalError
+            })
+    }
+
+    /// Gets the most recent deliveries, optionally only those with the
+    /// specified status
+    pub async fn get_list(
+        &self,
+        offset: u16,
+        limit: u16,
+        status: Option<&str>,
+    ) -> Result<Value, AdapterError> {
+        let named_params = json!({
+            "status": status.unwrap_or(""),
+            "offset": offset,
+            "limit": limit,
+        });
+
+        let options = QueryOptions::default()
+            .adhoc(false)
+            .named_parameters(named_params);
+
+        self.query_exec
+            .query_expect_one("webhook_deliveries/get_list", options)
+            .await
+    }
+}
@@ -3,4 +3,7 @@ mod request_handlers;
 mod service;
 mod util;
 
-pub use self::service::Service;
+pub use self::{
+    request_handlers::Notifiers,
+    service::Service,
+};
@@ -7,12 +7,14 @@ use std::collections::HashSet;
 /// Proposed item trait
 pub trait Proposed {
     fn target(&self) -> &str;
+    fn file_name(&self) -> Option<&str>;
 }
 
 /// Document trait
 pub trait Document {
     fn get_parent(&self) -> Option<&str>;
     fn get_type(&self) -> &str;
+    fn get_file_name(&self) -> Option<&str>;
     fn sanitize(&mut self);
 }
 
@@ -28,6 +30,10 @@ macro_rules! impl_doc_trait {
                 $self_.doc_type.as_str()
             }
 
+            fn get_file_name(&$self_) -> Option<&str> {
+                $self_.media.file_name.as_deref()
+            }
+
             fn sanitize(&mut $self_) {
                 // Trim the white space on title and attributes
                 $self_.title = $self_.title.trim().to_owned();
@@ -46,6 +52,32 @@ macro_rules! impl_doc_trait {
                 }
 
                 $self_.names = names.into_iter().collect();
+
+                // Only keep known media types
+                let media_type = $self_.media.media_type.as_deref();
+                if !matches!(media_type, Some("video") | Some("image")) {
+                    $self_.media.media_type = None;
+                }
+
+                // Only keep renditions of the media's own file
+                let file_name = $self_.media.file_name.as_deref().unwrap_or("");
+                let is_image = $self_.media.media_type.as_deref() == Some("image");
+                let is_own_file = |file: &str| {
+                    file.starts_with(&format!("{}.", file_name)) && !file.contains('/')
+                };
+
+                $self_.media.renditions.retain(|rendition| {
+                    !is_image
+                        && !file_name.is_empty()
+                        && is_own_file(&rendition.file)
+                        && matches!(rendition.mime_type.as_str(), "video/webm" | "video/mp4")
+                });
+
+                for rendition in &mut $self_.media.renditions {
+                    if !rendition.poster.as_deref().map_or(false, is_own_file) {
+                        rendition.poster = None;
+                    }
+                }
             }
         }
     }
@@ -66,6 +98,35 @@ pub struct Media {
     pub file_name: Option<String>,
     #[serde(rename = "previewData")]
     pub preview_data: Option<String>,
+    /// Either `video` or `image`. Defaults to `video` if not present.
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub media_type: Option<String>,
+    /// The transcoded renditions of a video
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renditions: Vec<Rendition>,
+}
+
+/// A transcoded rendition of a video
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Rendition {
+    pub file: String,
+    /// The preview frame at the size of the rendition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poster: Option<String>,
+    #[serde(rename = "type")]
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(rename = "frameRate")]
+    pub frame_rate: u32,
+    #[serde(
+        rename = "frameCount",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub frame_count: Option<u32>,
+    #[serde(rename = "frameTimes", default, skip_serializing_if = "Vec::is_empty")]
+    pub frame_times: Vec<f64>,
 }
 
 /// A game document
@@ -123,8 +184,6 @@ where
     pub status: String,
     #[serde(rename = "authorId")]
     pub author_id: String,
-    #[serde(rename = "authorName")]
-    pub author_name: String,
     pub document: D,
 }
 
@@ -135,6 +194,10 @@ where
     fn target(&self) -> &str {
         self.target.as_str()
     }
+
+    fn file_name(&self) -> Option<&str> {
+        self.document.get_file_name()
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -2,4 +2,7 @@ mod document;
 mod proposal;
 
 pub use document::Document;
-pub use proposal::Proposal;
+pub use proposal::{
+    Notifiers,
+    Proposal,
+};
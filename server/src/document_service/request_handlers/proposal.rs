@@ -12,11 +12,14 @@ use crate::{
         couchbase as couchbase_adapters,
         AdapterError,
     },
+    event_service::Service as EventService,
     http_service::{
         util as http_util,
         HttpError,
         HttpResult,
     },
+    notification_service::Service as NotificationService,
+    webhook_service::Service as WebhookService,
 };
 use hyper::{
     body::{
@@ -41,11 +44,21 @@ use std::{
     },
 };
 
+/// The services that are told about changes to proposals and documents
+pub struct Notifiers {
+    pub webhooks: Arc<WebhookService>,
+    pub events: Arc<EventService>,
+    pub notifications: Arc<NotificationService>,
+}
+
 /// Handles proposal related requests via the doc-api
 pub struct Proposal {
     props_adapter: Arc<couchbase_adapters::Proposals>,
     docs_adapter: Arc<couchbase_adapters::Documents>,
     name_sets_adapter: Arc<couchbase_adapters::NameSets>,
+    media_adapter: Arc<couchbase_adapters::UploadMedia>,
+    users_adapter: Arc<couchbase_adapters::Users>,
+    notifiers: Notifiers,
 }
 
 impl Proposal {
@@ -53,11 +66,17 @@ impl Proposal {
         props_adapter: Arc<couchbase_adapters::Proposals>,
         docs_adapter: Arc<couchbase_adapters::Documents>,
         name_sets_adapter: Arc<couchbase_adapters::NameSets>,
+        media_adapter: Arc<couchbase_adapters::UploadMedia>,
+        users_adapter: Arc<couchbase_adapters::Users>,
+        notifiers: Notifiers,
     ) -> Self {
         Self {
             props_adapter,
             docs_adapter,
             name_sets_adapter,
+            media_adapter,
+            users_adapter,
+            notifiers,
         }
     }
 
@@ -157,26 +176,71 @@ impl Proposal {
         // Make sure the current user can change the status
         Self::authorize_proposal_close(&proposal, status, &claims)?;
 
+        let file_name = proposal["document"]["media"]["fileName"]
+            .as_str()
+            .map(|file_name| file_name.to_owned());
+
         // Publish
+        let proposal_data = proposal.clone();
         if status == "approved" {
             self.publish_proposal(proposal).await?;
         }
 
         // Close the proposal
-        self.props_adapter
+        let result = self
+            .props_adapter
             .close(target, &version, status)
             .await
             .map(|json| http_util::build_json_response(&json, StatusCode::OK))
             .map_err(|error| {
                 error!("Failed to close proposal: {:?}", error);
                 HttpError::InternalError(None)
-            })
+            })?;
+
+        // Closed proposals no longer reference their media
+        if let Some(file_name) = file_name {
+            let ref_id = format!("prop::{}::{}", target, version);
+            self.remove_media_ref(&file_name, &ref_id).await;
+        }
+
+        // Let the author know their proposal was reviewed, and the authors of
+        // other pending proposals that the document changed under them
+        let author_id = proposal_data["authorId"].as_str().unwrap_or("").to_owned();
+        if status != "cancelled" && author_id != claims.sub() {
+            self.notifiers.notifications.notify(
+                &author_id,
+                status,
+                &proposal_data,
+                version,
+                json!({ "closedBy": claims.sub() }),
+            );
+        }
+
+        if status == "approved" {
+            self.notify_superseded(&proposal_data, version).await;
+        }
+
+        self.notify(
+            "proposal.closed",
+            target,
+            &author_id,
+            json!({
+                "target": target,
+                "version": version,
+                "status": status,
+                "closedBy": claims.sub(),
+                "proposal": proposal_data,
+            }),
+        );
+
+        Ok(result)
     }
 
     /// Handles an id request given a document type
     async fn handle_id_request(&self, target: &str, version: &u64) -> HttpResult {
         let mut response = self.props_adapter.get(target, version).await;
-        if let Ok(proposal) = response {
+        if let Ok(mut proposal) = response {
+            proposal["authorName"] = self.get_author_name(&proposal).await.into();
             response = self.combine_with_prev(proposal).await;
         }
 
@@ -291,15 +355,16 @@ impl Proposal {
             return Err(HttpError::BadRequest("Invalid target name".into()));
         }
 
-        // Determine the author information
-        let (author_id, author_name) = if let Some(import_as) = request.import_as {
+        // Determine the author. Names are resolved from the author's profile
+        // when proposals are read.
+        let author_id = if let Some(import_as) = request.import_as {
             if !claims.is_admin() {
                 return Err(HttpError::Unauthorized(None));
             }
 
-            (format!("i:{}", import_as), import_as)
+            format!("i:{}", import_as)
         } else {
-            (claims.sub().to_owned(), claims.screen_name().to_owned())
+            claims.sub().to_owned()
         };
 
         // Sanitize the document
@@ -314,7 +379,6 @@ impl Proposal {
             last_updated: current_time,
             status: "pending".to_owned(),
             author_id,
-            author_name,
             document: request.document,
         };
 
@@ -337,20 +401,47 @@ impl Proposal {
                 HttpError::InternalError(None)
             })?;
 
+        // Reference the media before the proposal exists so that it is never
+        // collected while in use
+        let ref_id = format!("prop::{}::{}", target, count);
+        if let Some(file_name) = document.file_name() {
+            self.add_media_ref(file_name, &ref_id).await?;
+        }
+
         // Upsert it
-        self.props_adapter
-            .upsert(target, &count, &document)
-            .await
-            .map(|_| {
+        let result = match self.props_adapter.upsert(target, &count, &document).await {
+            Ok(_) => {
                 json!({
                     "proposal": target,
                     "version": count,
                 })
-            })
-            .map_err(|error| {
+            }
+            Err(error) => {
                 error!("Error committing document: {:?}", error);
-                HttpError::InternalError(None)
-            })
+
+                // The proposal does not exist so it must not keep its media
+                // from being collected
+                if let Some(file_name) = document.file_name() {
+                    self.remove_media_ref(file_name, &ref_id).await;
+                }
+
+                return Err(HttpError::InternalError(None));
+            }
+        };
+
+        let data = json!({
+            "target": target,
+            "version": count,
+            "proposal": document,
+        });
+
+        let author_id = data["proposal"]["authorId"]
+            .as_str()
+            .unwrap_or("")
+            .to_owned();
+        self.notify("proposal.created", target, &author_id, data);
+
+        Ok(result)
     }
 
     /// Combine a proposal document with its target
@@ -381,6 +472,29 @@ impl Proposal {
             })
     }
 
+    /// Gets the current display name of a proposal's author. Proposals of
+    /// authors without a profile fall back to the name they were created
+    /// with, or to the name imported authors are identified by.
+    async fn get_author_name(&self, proposal: &Value) -> String {
+        let author_id = proposal["authorId"].as_str().unwrap_or("");
+        match self.users_adapter.get(author_id).await {
+            Ok(profile) => {
+                if let Some(display_name) = profile["displayName"].as_str() {
+                    return display_name.to_owned();
+                }
+            }
+            Err(AdapterError::DocumentNotFound) => {}
+            Err(AdapterError::InternalError) => {
+                error!("Failed to get the profile of {}", author_id);
+            }
+        }
+
+        proposal["authorName"]
+            .as_str()
+            .map(|name| name.to_owned())
+            .unwrap_or_else(|| author_id.get(2..).unwrap_or("").to_owned())
+    }
+
     /// Authorizes the closing of the specified proposal by the current user
     fn authorize_proposal_close(
         proposal: &Value,
@@ -426,9 +540,9 @@ impl Proposal {
                     HttpError::InternalError(None)
                 })?;
 
-            // Make sure the proposal author is first on the list
+            // Make sure the proposal author is first on the list. Only ids
+            // are stored; names are resolved when documents are read.
             let author_id = proposal["authorId"].as_str().unwrap();
-            let author_name = proposal["authorName"].as_str().unwrap();
 
             let pos_result = authors
                 .iter()
@@ -438,10 +552,7 @@ impl Proposal {
                 authors.remove(pos);
             }
 
-            let latest_author = json!({
-                "id": author_id,
-                "name": author_name,
-            });
+            let latest_author = json!({ "id": author_id });
 
             authors.insert(0, latest_author);
             proposal["document"]["latestAuthors"] = Value::Array(authors);
@@ -452,6 +563,31 @@ impl Proposal {
         let doc_type = document["type"].as_str().unwrap();
         let target = proposal["target"].as_str().unwrap();
 
+        // Move the published document's media reference over to the new
+        // media
+        let ref_id = match doc_type {
+            "game" => format!("game::{}", target),
+            "character" => format!("char::{}", target),
+            _ => format!("move::{}", target),
+        };
+
+        let prev_file_name = match doc_type {
+            "game" => self.docs_adapter.get_game(target).await,
+            "character" => self.docs_adapter.get_char(target).await,
+            _ => self.docs_adapter.get_move(target).await,
+        }
+        .ok()
+        .and_then(|previous| {
+            previous["media"]["fileName"]
+                .as_str()
+                .map(|file_name| file_name.to_owned())
+        });
+
+        let file_name = document["media"]["fileName"].as_str();
+        if let Some(file_name) = file_name {
+            self.add_media_ref(file_name, &ref_id).await?;
+        }
+
         match doc_type {
             "game" => self.docs_adapter.upsert_game(&target, document).await,
             "character" => self.docs_adapter.upsert_char(&target, document).await,
@@ -462,6 +598,23 @@ impl Proposal {
             HttpError::InternalError(None)
         })?;
 
+        if let Some(prev_file_name) = prev_file_name {
+            if Some(prev_file_name.as_str()) != file_name {
+                self.remove_media_ref(&prev_file_name, &ref_id).await;
+            }
+        }
+
+        self.notify(
+            "document.published",
+            target,
+            proposal["authorId"].as_str().unwrap_or(""),
+            json!({
+                "id": target,
+                "type": doc_type,
+                "document": document,
+            }),
+        );
+
         // Update name_sets for full text searching
         match doc_type {
             "game" => self.name_sets_adapter.update_game(&target).await,
@@ -474,6 +627,81 @@ impl Proposal {
         })
     }
 
+    /// Notifies the authors of the pending proposals of a target that
+    /// another proposal of it was approved. Failures are only logged.
+    async fn notify_superseded(&self, approved: &Value, approved_version: u64) {
+        let target = approved["target"].as_str().unwrap_or("");
+        let pending = match self.props_adapter.get_pending_authors(target).await {
+            Ok(pending) => pending,
+            Err(error) => {
+                error!("Failed to get pending proposals: {:?}", error);
+                return;
+            }
+        };
+
+        for proposal in pending {
+            let author_id = proposal["authorId"].as_str().unwrap_or("");
+            let version = proposal["version"]
+                .as_str()
+                .and_then(|version| version.parse::<u64>().ok())
+                .unwrap_or(0);
+
+            // The approved proposal itself is not superseded
+            if version == approved_version {
+                continue;
+            }
+
+            self.notifiers.notifications.notify(
+                author_id,
+                "superseded",
+                approved,
+                version,
+                json!({ "approvedVersion": approved_version }),
+            );
+        }
+    }
+
+    /// Records a reference to an uploaded media file
+    async fn add_media_ref(&self, file_name: &str, ref_id: &str) -> Result<(), HttpError> {
+        self.media_adapter
+            .add_ref(file_name, ref_id, Self::get_now_timestamp())
+            .await
+            .map_err(|error| {
+                error!("Failed to reference media '{}': {:?}", file_name, error);
+                HttpError::InternalError(None)
+            })
+    }
+
+    /// Removes a reference to an uploaded media file. Failures are only
+    /// logged; the media is then kept rather than collected.
+    async fn remove_media_ref(&self, file_name: &str, ref_id: &str) {
+        let result = self
+            .media_adapter
+            .remove_ref(file_name, ref_id, Self::get_now_timestamp())
+            .await;
+
+        if let Err(error) = result {
+            error!("Failed to dereference media '{}': {:?}", file_name, error);
+        }
+    }
+
+    /// Sends an event about a target to the webhooks and the clients that
+    /// subscribe to it
+    fn notify(&self, event: &str, target: &str, author_id: &str, data: Value) {
+        self.notifiers
+            .webhooks
+            .dispatch(event, Self::get_game_id(target), data.clone());
+        self.notifiers
+            .events
+            .publish(event, target, author_id, data);
+    }
+
+    /// Gets the id of the game a document belongs to from its id, e.g. `sf5`
+    /// for `sf5.ryu.hadoken`
+    fn get_game_id(target: &str) -> &str {
+        target.split('.').next().unwrap_or(target)
+    }
+
     fn get_now_timestamp() -> u64 {
         let current_time = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -1,5 +1,8 @@
 use super::{
-    request_handlers,
+    request_handlers::{
+        self,
+        Notifiers,
+    },
     util,
 };
 use crate::{
@@ -26,6 +29,9 @@ impl Service {
         docs_adapter: Arc<couchbase_adapters::Documents>,
         props_adapter: Arc<couchbase_adapters::Proposals>,
         name_sets_adapter: Arc<couchbase_adapters::NameSets>,
+        media_adapter: Arc<couchbase_adapters::UploadMedia>,
+        users_adapter: Arc<couchbase_adapters::Users>,
+        notifiers: Notifiers,
     ) -> Self {
         Self {
             document_handler: request_handlers::Document::new(docs_adapter.clone()),
@@ -33,6 +39,9 @@ impl Service {
                 props_adapter,
                 docs_adapter,
                 name_sets_adapter,
+                media_adapter,
+                users_adapter,
+                notifiers,
             ),
         }
     }
@@ -0,0 +1,47 @@
+use serde_json::Value;
+
+/// A change to a proposal or document
+pub struct Event {
+    /// Increases with each event of a process. Clients resume from it when
+    /// they reconnect.
+    pub id: u64,
+    pub name: String,
+    /// The id of the proposal's or document's target, e.g. `sf5.ryu.hadoken`
+    pub target: String,
+    /// The author of the proposal the event is about
+    pub author_id: String,
+    pub data: Value,
+}
+
+/// The events a client is subscribed to. Events must match every scope that
+/// is set.
+#[derive(Default)]
+pub struct Subscription {
+    pub game: Option<String>,
+    pub character: Option<String>,
+    pub target: Option<String>,
+    pub author_id: Option<String>,
+}
+
+impl Subscription {
+    pub fn matches(&self, event: &Event) -> bool {
+        let within = |scope: &Option<String>| {
+            scope.as_ref().map_or(true, |scope| {
+                event.target == *scope
+                    || (event.target.starts_with(scope.as_str())
+                        && event.target[scope.len()..].starts_with('.'))
+            })
+        };
+
+        within(&self.game)
+            && within(&self.character)
+            && self
+                .target
+                .as_ref()
+                .map_or(true, |target| event.target == *target)
+            && self
+                .author_id
+                .as_ref()
+                .map_or(true, |author_id| event.author_id == *author_id)
+    }
+}
@@ -0,0 +1,9 @@
+mod event;
+mod service;
+mod service_config;
+
+pub use self::{
+    event::Event,
+    service::Service,
+    service_config::ServiceConfig,
+};
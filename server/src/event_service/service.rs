@@ -0,0 +1,291 @@
+use super::{
+    event::Subscription,
+    Event,
+    ServiceConfig,
+};
+use crate::{
+    auth_service::Session,
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResult,
+    },
+    util,
+};
+use bytes::Bytes;
+use futures::stream::{
+    self,
+    StreamExt,
+};
+use hyper::{
+    header::{
+        self,
+        HeaderMap,
+        HeaderValue,
+    },
+    Body,
+    Response,
+    StatusCode,
+};
+use log::warn;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    collections::VecDeque,
+    convert::Infallible,
+    sync::{
+        atomic::{
+            AtomicU64,
+            AtomicUsize,
+            Ordering,
+        },
+        Arc,
+        Mutex,
+    },
+    time::Duration,
+};
+use tokio::{
+    sync::broadcast::{
+        self,
+        error::RecvError,
+    },
+    time::{
+        self,
+        Instant,
+    },
+};
+
+/// The number of events buffered for each client before it misses events
+const CHANNEL_CAPACITY: usize = 256;
+
+/// The number of milliseconds clients wait before reconnecting
+const RETRY_DELAY: u32 = 5000;
+
+/// Decrements the number of connections when a client disconnects
+struct ConnectionGuard(Arc<AtomicUsize>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The state of an event stream of a client
+struct StreamState {
+    receiver: broadcast::Receiver<Arc<Event>>,
+    subscription: Subscription,
+    /// Missed events that are sent before new ones
+    backlog: VecDeque<Arc<Event>>,
+    last_id: u64,
+    keep_alive: Duration,
+    epoch: u64,
+    _guard: ConnectionGuard,
+}
+
+/// Pushes changes to proposals and documents to clients as server-sent
+/// events
+pub struct Service {
+    config: ServiceConfig,
+    sender: broadcast::Sender<Arc<Event>>,
+    /// Identifies the events of this process. Event ids restart with each
+    /// process, so ids of other processes are not resumed from.
+    epoch: u64,
+    next_id: AtomicU64,
+    history: Mutex<VecDeque<Arc<Event>>>,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Service {
+    pub fn new(config: ServiceConfig) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        Self {
+            config,
+            sender,
+            epoch: util::get_timestamp(),
+            next_id: AtomicU64::new(1),
+            history: Mutex::new(VecDeque::new()),
+            connections: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Sends an event about a target to the subscribed clients
+    pub fn publish(&self, name: &str, target: &str, author_id: &str, data: Value) {
+        // Events are numbered and sent while holding the lock so that they
+        // arrive in order
+        let mut history = self.history.lock().unwrap();
+        let event = Arc::new(Event {
+            id: self.next_id.fetch_add(1, Ordering::SeqCst),
+            name: name.to_owned(),
+            target: target.to_owned(),
+            author_id: author_id.to_owned(),
+            data,
+        });
+
+        history.push_back(event.clone());
+        while history.len() > self.config.history_size() as usize {
+            history.pop_front();
+        }
+
+        // Sending only fails when nobody is subscribed
+        let _ = self.sender.send(event);
+    }
+
+    /// Handles an event-api `GET` request, which opens an event stream.
+    /// Clients can subscribe to the events of a `game`, a character (`char`),
+    /// a `target` or their own proposals (`mine=true`).
+    pub async fn handle_get_request(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        header_map: &HeaderMap,
+        session: &Session,
+    ) -> HttpResult {
+        if path.trim_end_matches('/') != "v1/events" {
+            let message = format!(
+                "Invalid event-api path '{path}' for the specified method",
+                path = path
+            );
+
+            return Err(HttpError::BadRequest(message.into()));
+        }
+
+        let query_params = http_util::parse_query_string(query.unwrap_or(""));
+        let author_id = match query_params.get("mine") {
+            Some(&"true") => {
+                match session {
+                    Session::Valid(claims) | Session::Expired(claims) => {
+                        Some(claims.sub().to_owned())
+                    }
+                    _ => return Err(HttpError::Unauthorized(None)),
+                }
+            }
+            _ => None,
+        };
+
+        let subscription = Subscription {
+            game: query_params.get("game").map(|game| game.to_string()),
+            character: query_params
+                .get("char")
+                .map(|character| character.to_string()),
+            target: query_params.get("target").map(|target| target.to_string()),
+            author_id,
+        };
+
+        // Limit the number of open streams
+        let count = self.connections.fetch_add(1, Ordering::SeqCst);
+        let guard = ConnectionGuard(self.connections.clone());
+        if count >= self.config.max_connections() as usize {
+            warn!("Rejected event stream: {} clients are connected", count);
+            return Err(HttpError::ServiceUnavailable(None));
+        }
+
+        // Subscribe before reading the history so no event is missed. Events
+        // received twice are skipped by their id.
+        let receiver = self.sender.subscribe();
+        // Ids from before a restart are unknown
+        let last_id = header_map
+            .get("last-event-id")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| self.parse_event_id(value))
+            .unwrap_or(0);
+
+        let backlog = if last_id > 0 {
+            self.history
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|event| event.id > last_id && subscription.matches(event))
+                .cloned()
+                .collect()
+        } else {
+            VecDeque::new()
+        };
+
+        let state = StreamState {
+            receiver,
+            subscription,
+            last_id,
+            backlog,
+            keep_alive: Duration::from_secs(self.config.keep_alive() as u64),
+            epoch: self.epoch,
+            _guard: guard,
+        };
+
+        let greeting = Bytes::from(format!(": connected\nretry: {}\n\n", RETRY_DELAY));
+        let events =
+            stream::once(async { greeting }).chain(stream::unfold(state, Self::next_chunk));
+        let body = Body::wrap_stream(events.map(Ok::<Bytes, Infallible>));
+
+        let response = Response::builder()
+            .status(StatusCode::OK)
+            .header(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static("text/event-stream"),
+            )
+            .header(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"))
+            .header("x-accel-buffering", HeaderValue::from_static("no"))
+            .body(body)
+            .unwrap();
+
+        Ok(response)
+    }
+
+    /// Waits for the next chunk of an event stream. Sends a comment when no
+    /// event arrives within the keep alive interval.
+    async fn next_chunk(mut state: StreamState) -> Option<(Bytes, StreamState)> {
+        if let Some(event) = state.backlog.pop_front() {
+            state.last_id = event.id;
+            return Some((Self::format_event(state.epoch, &event), state));
+        }
+
+        let deadline = Instant::now() + state.keep_alive;
+        loop {
+            match time::timeout_at(deadline, state.receiver.recv()).await {
+                Ok(Ok(event)) => {
+                    if event.id <= state.last_id || !state.subscription.matches(&event) {
+                        continue;
+                    }
+
+                    state.last_id = event.id;
+                    return Some((Self::format_event(state.epoch, &event), state));
+                }
+                Ok(Err(RecvError::Lagged(count))) => {
+                    // Let the client know it should reload what it shows
+                    let chunk = format!("event: resync\ndata: {}\n\n", json!({ "missed": count }));
+                    return Some((Bytes::from(chunk), state));
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+                Err(_) => return Some((Bytes::from_static(b": keep-alive\n\n"), state)),
+            }
+        }
+    }
+
+    /// Parses the id of an event sent by this process. Ids are formatted as
+    /// `{epoch}-{number}`.
+    fn parse_event_id(&self, value: &str) -> Option<u64> {
+        let mut parts = value.splitn(2, '-');
+        let epoch = parts.next()?.parse::<u64>().ok()?;
+        let id = parts.next()?.parse::<u64>().ok()?;
+
+        if epoch == self.epoch && id < self.next_id.load(Ordering::SeqCst) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    fn format_event(epoch: u64, event: &Event) -> Bytes {
+        let data = json!({
+            "target": event.target,
+            "authorId": event.author_id,
+            "data": event.data,
+        });
+
+        Bytes::from(format!(
+            "id: {}-{}\nevent: {}\ndata: {}\n\n",
+            epoch, event.id, event.name, data
+        ))
+    }
+}
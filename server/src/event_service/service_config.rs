@@ -0,0 +1,35 @@
+pub struct ServiceConfig {
+    keep_alive: u32,
+    max_connections: u32,
+    history_size: u32,
+}
+
+impl ServiceConfig {
+    pub fn new(keep_alive: u32, max_connections: u32, history_size: u32) -> Result<Self, String> {
+        if keep_alive == 0 {
+            return Err("The keep alive interval must be at least one second".to_owned());
+        }
+
+        Ok(Self {
+            keep_alive,
+            max_connections,
+            history_size,
+        })
+    }
+
+    /// The number of seconds between comments sent to keep idle connections
+    /// open
+    pub fn keep_alive(&self) -> u32 {
+        self.keep_alive
+    }
+
+    /// The number of clients that can be subscribed at the same time
+    pub fn max_connections(&self) -> u32 {
+        self.max_connections
+    }
+
+    /// The number of recent events kept for clients that reconnect
+    pub fn history_size(&self) -> u32 {
+        self.history_size
+    }
+}
@@ -1,7 +1,10 @@
 mod service;
 mod service_config;
 
-#[derive(Debug)]
+use serde_derive::Deserialize;
+
+#[derive(Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum BaseDirectory {
     Content,
     Uploads,
@@ -9,5 +12,9 @@ pub enum BaseDirectory {
 
 pub use self::{
     service::Service,
-    service_config::ServiceConfig,
+    service_config::{
+        CachePolicy,
+        ServiceConfig,
+        SymlinkPolicy,
+    },
 };
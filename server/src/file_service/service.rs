@@ -1,22 +1,30 @@
 use super::{
     BaseDirectory,
     ServiceConfig,
+    SymlinkPolicy,
 };
 use crate::http_service::{
+    util as http_util,
     HttpError,
     HttpResponse,
     HttpResult,
 };
 use async_std::{
-    fs::File,
+    fs::{
+        self,
+        File,
+    },
     prelude::*,
 };
+use bytes::Bytes;
+use futures::stream;
 use hyper::{
     header::{
         self,
         HeaderMap,
         HeaderValue,
     },
+    http::response,
     Body,
     Response,
     StatusCode,
@@ -26,6 +34,7 @@ use log::{
     error,
     info,
     trace,
+    warn,
 };
 use regex::Regex;
 use std::{
@@ -35,57 +44,204 @@ use std::{
         ErrorKind,
         SeekFrom,
     },
-    path::PathBuf,
+    path::{
+        Path,
+        PathBuf,
+    },
+    time::{
+        Duration,
+        SystemTime,
+        UNIX_EPOCH,
+    },
 };
 
+/// The size of the chunks file bodies are streamed in
+const CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The content codings files can be precompressed with, in order of
+/// preference, and the extensions of the precompressed files
+const PRECOMPRESSED_EXTENSIONS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+/// A single range of bytes requested by a client. See
+/// https://tools.ietf.org/html/rfc7233#section-2.1
 #[derive(Debug)]
-struct RequestRange {
-    pub start: u64,
-    pub end: u64,
+enum RequestRange {
+    /// From the first byte position to the last (inclusive) or the end of the
+    /// file
+    FromTo(u64, Option<u64>),
+    /// The last 'n' bytes of the file
+    Suffix(u64),
+}
+
+impl RequestRange {
+    /// Resolves the first and last (inclusive) byte positions of the range
+    /// within a file of the specified length. Returns `None` if the range is
+    /// not satisfiable.
+    fn resolve(&self, length: u64) -> Option<(u64, u64)> {
+        match *self {
+            RequestRange::FromTo(start, _) if start >= length => None,
+            RequestRange::FromTo(start, end) => {
+                let last = length - 1;
+                Some((start, end.map_or(last, |end| end.min(last))))
+            }
+            RequestRange::Suffix(0) => None,
+            RequestRange::Suffix(_) if length == 0 => None,
+            RequestRange::Suffix(count) => Some((length.saturating_sub(count), length - 1)),
+        }
+    }
+}
+
+/// The range and conditional headers of a request. See
+/// https://tools.ietf.org/html/rfc7232
+#[derive(Default)]
+struct RequestConditions<'a> {
+    /// The content codings the client accepts, most preferred first
+    encodings: Vec<&'static str>,
+    range: Option<RequestRange>,
+    if_range: Option<&'a str>,
+    if_none_match: Option<&'a str>,
+    if_modified_since: Option<SystemTime>,
+}
+
+impl<'a> RequestConditions<'a> {
+    fn from_headers(header_map: &'a HeaderMap) -> Self {
+        let get_header = |name: header::HeaderName| {
+            header_map
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::trim)
+        };
+
+        let supported = PRECOMPRESSED_EXTENSIONS
+            .iter()
+            .map(|(coding, _)| *coding)
+            .collect::<Vec<&str>>();
+
+        Self {
+            encodings: http_util::get_accepted_encodings(header_map, &supported),
+            range: Service::get_request_range(header_map),
+            if_range: get_header(header::IF_RANGE),
+            if_none_match: get_header(header::IF_NONE_MATCH),
+            if_modified_since: get_header(header::IF_MODIFIED_SINCE)
+                .and_then(|value| httpdate::parse_http_date(value).ok()),
+        }
+    }
+
+    /// Whether the client's cached copy of a file is still valid
+    fn is_not_modified(&self, validators: &Validators) -> bool {
+        // Dates are only used by clients that do not support entity tags
+        match (self.if_none_match, self.if_modified_since) {
+            (Some(if_none_match), _) => {
+                if_none_match.split(',').any(|tag| {
+                    let tag = tag.trim();
+                    tag == "*" || tag.trim_start_matches("W/") == validators.etag
+                })
+            }
+            (None, Some(if_modified_since)) => validators.last_modified <= if_modified_since,
+            (None, None) => false,
+        }
+    }
+
+    /// Gets the range to serve. Ranges are ignored if the file changed since
+    /// the client's partial copy was fetched.
+    fn get_range(&self, validators: &Validators) -> Option<&RequestRange> {
+        let is_unchanged = match self.if_range {
+            None => true,
+            Some(if_range) if if_range.starts_with('"') => if_range == validators.etag,
+            Some(if_range) => {
+                httpdate::parse_http_date(if_range)
+                    .map_or(false, |date| date == validators.last_modified)
+            }
+        };
+
+        self.range.as_ref().filter(|_| is_unchanged)
+    }
+}
+
+/// Identifies the version of a file so clients can validate cached copies
+struct Validators {
+    etag: String,
+    last_modified: SystemTime,
+}
+
+impl Validators {
+    fn new(length: u64, modified: SystemTime, encoding: Option<&str>) -> Self {
+        // HTTP dates only have a precision of seconds
+        let seconds = modified
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs());
+
+        Self {
+            etag: format!(
+                "\"{:x}-{:x}{}\"",
+                seconds,
+                length,
+                encoding.map_or(String::new(), |encoding| format!("-{}", encoding))
+            ),
+            last_modified: UNIX_EPOCH + Duration::from_secs(seconds),
+        }
+    }
+
+    fn add_headers(&self, builder: response::Builder) -> response::Builder {
+        builder.header(header::ETAG, self.etag.as_str()).header(
+            header::LAST_MODIFIED,
+            httpdate::fmt_http_date(self.last_modified),
+        )
+    }
+}
+
+enum FetchResult {
+    Content(FetchResultPayload),
+    /// The client's cached copy of the file is still valid
+    NotModified(Validators),
+    /// The requested range is outside of the file. Holds the file's length.
+    RangeNotSatisfiable(u64),
 }
 
 struct FetchResultPayload {
-    pub mime_type: &'static str,
-    pub encoding: &'static str,
-    pub content: Vec<u8>,
-    pub range_start: u64,
-    pub range_end: u64,
+    pub mime_type: String,
+    pub encoding: Option<&'static str>,
+    pub validators: Validators,
+    pub content: Body,
+    pub content_length: u64,
+    /// The first and last byte positions of a partial response
+    pub range: Option<(u64, u64)>,
     pub total_length: u64,
 }
 
 impl FetchResultPayload {
-    pub fn to_http_response(self, status_code: StatusCode) -> HttpResponse {
-        let builder = Response::builder()
-            .status(status_code)
+    pub fn to_http_response(self, status_code: StatusCode, cache_control: &str) -> HttpResponse {
+        let mut builder = Response::builder()
             .header(
                 header::CONTENT_TYPE,
-                HeaderValue::from_static(self.mime_type),
+                HeaderValue::from_str(&self.mime_type).unwrap(),
             )
             .header(
-                header::CONTENT_ENCODING,
-                HeaderValue::from_static(self.encoding),
+                header::X_CONTENT_TYPE_OPTIONS,
+                HeaderValue::from_static("nosniff"),
             )
-            .header(
-                header::CACHE_CONTROL,
-                HeaderValue::from_static("public, max-age=86400"),
-            );
+            .header(header::CONTENT_LENGTH, self.content_length)
+            .header(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"))
+            .header(header::CACHE_CONTROL, cache_control)
+            .header(header::VARY, HeaderValue::from_static("accept-encoding"));
 
-        let builder = match status_code {
-            StatusCode::PARTIAL_CONTENT => {
-                let header_value = format!(
-                    "bytes {0}-{1}/{2}",
-                    self.range_start, self.range_end, self.total_length
-                );
+        builder = self.validators.add_headers(builder);
+        if let Some(encoding) = self.encoding {
+            builder = builder.header(header::CONTENT_ENCODING, HeaderValue::from_static(encoding));
+        }
 
-                builder.header(
+        let builder = match self.range {
+            Some((start, end)) => {
+                let header_value = format!("bytes {0}-{1}/{2}", start, end, self.total_length);
+                builder.status(StatusCode::PARTIAL_CONTENT).header(
                     header::CONTENT_RANGE,
                     HeaderValue::from_str(header_value.as_str()).unwrap(),
                 )
             }
-            _ => builder,
+            None => builder.status(status_code),
         };
 
-        builder.body(Body::from(self.content)).unwrap()
+        builder.body(self.content).unwrap()
     }
 }
 
@@ -105,16 +261,13 @@ impl Service {
         header_map: &HeaderMap,
         base_dir: BaseDirectory,
     ) -> HttpResult {
-        let request_range = Self::get_request_range(header_map);
-        let mut status_code = if request_range.is_some() {
-            StatusCode::PARTIAL_CONTENT
-        } else {
-            StatusCode::OK
-        };
+        let conditions = RequestConditions::from_headers(header_map);
+        let mut status_code = StatusCode::OK;
+        let mut cache_control = self.config.get_cache_control(&base_dir, path);
 
         // Fetch the file
         let fetch_result =
-            Self::fetch_file_content(&path, &base_dir, &request_range, &self.config).await;
+            Self::fetch_file_content(&path, &base_dir, &conditions, &self.config).await;
 
         // If the file was not found fetch the 'not found' file instead
         let fetch_result = match fetch_result {
@@ -124,11 +277,13 @@ impl Service {
                     path, base_dir
                 );
 
+                // The file may be created later on
                 status_code = StatusCode::NOT_FOUND;
+                cache_control = "no-cache";
                 Self::fetch_file_content(
                     self.config.not_found_file(),
                     &BaseDirectory::Content,
-                    &None,
+                    &RequestConditions::default(),
                     &self.config,
                 )
                 .await
@@ -137,89 +292,354 @@ impl Service {
         };
 
         fetch_result
-            .map(|payload| payload.to_http_response(status_code))
+            .map(|result| {
+                match result {
+                    FetchResult::Content(payload) => {
+                        payload.to_http_response(status_code, cache_control)
+                    }
+                    FetchResult::NotModified(validators) => {
+                        let builder = Response::builder()
+                            .status(StatusCode::NOT_MODIFIED)
+                            .header(header::CACHE_CONTROL, cache_control)
+                            .header(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+                        validators.add_headers(builder).body(Body::empty()).unwrap()
+                    }
+                    FetchResult::RangeNotSatisfiable(total_length) => {
+                        Self::build_range_not_satisfiable_response(total_length)
+                    }
+                }
+            })
             .map_err(|error| {
-                error!("Encountered an error while retrieving content: {:?}", error);
-                HttpError::InternalError(None)
+                match error.kind() {
+                    ErrorKind::InvalidInput => HttpError::BadRequest("Invalid file path".into()),
+                    _ => {
+                        error!("Encountered an error while retrieving content: {:?}", error);
+                        HttpError::InternalError(None)
+                    }
+                }
             })
     }
 
+    /// Gets the range requested by a client. Only single byte ranges are
+    /// supported; other and invalid ranges are ignored and the whole file is
+    /// served instead.
     fn get_request_range(header_map: &HeaderMap) -> Option<RequestRange> {
         lazy_static! {
             static ref RANGE_REGEX: Regex =
-                Regex::new(r"^bytes=(?P<start>\d+)-(?P<end>\d+)$").unwrap();
+                Regex::new(r"^\s*(?i:bytes)\s*=\s*(?P<start>\d*)\s*-\s*(?P<end>\d*)\s*$").unwrap();
         }
 
-        header_map
+        let captures = header_map
             .get(header::RANGE)
             .and_then(|value| value.to_str().ok())
-            .and_then(|value| RANGE_REGEX.captures(value))
-            .and_then(|captures| {
-                let start = captures["start"].parse::<u64>();
-                let end = captures["end"].parse::<u64>();
-                match (start, end) {
-                    (Ok(start), Ok(end)) => Some(RequestRange { start, end }),
-                    _ => None,
-                }
-            })
+            .and_then(|value| RANGE_REGEX.captures(value))?;
+
+        let parse = |name: &str| {
+            match &captures[name] {
+                "" => Ok(None),
+                value => value.parse::<u64>().map(Some),
+            }
+        };
+
+        match (parse("start"), parse("end")) {
+            (Ok(Some(start)), Ok(end)) if end.map_or(true, |end| start <= end) => {
+                Some(RequestRange::FromTo(start, end))
+            }
+            (Ok(None), Ok(Some(count))) => Some(RequestRange::Suffix(count)),
+            _ => None,
+        }
     }
 
-    /// Asynchronously retrieves file content from disk
+    /// Builds the response to a range that is outside of the requested file
+    fn build_range_not_satisfiable_response(total_length: u64) -> HttpResponse {
+        Response::builder()
+            .status(StatusCode::RANGE_NOT_SATISFIABLE)
+            .header(header::CONTENT_RANGE, format!("bytes */{}", total_length))
+            .header(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    /// Asynchronously opens a file on disk and prepares its content to be
+    /// streamed
     async fn fetch_file_content(
         path: &str,
         base_dir: &BaseDirectory,
-        request_range: &Option<RequestRange>,
+        conditions: &RequestConditions<'_>,
         config: &ServiceConfig,
-    ) -> Result<FetchResultPayload, Error> {
+    ) -> Result<FetchResult, Error> {
         let base_dir = match base_dir {
             BaseDirectory::Content => config.content_dir(),
             BaseDirectory::Uploads => config.uploads_dir(),
         };
 
-        let mut absolute_path = PathBuf::new();
-        absolute_path.push(base_dir);
-        absolute_path.push(path.trim_start_matches('/'));
-        trace!("Request: {:?}", absolute_path);
+        // Reject invalid paths before trying any of the files they may refer
+        // to
+        Self::get_path_segments(path)?;
+        trace!("Request: {:?} in {:?}", path, base_dir);
 
-        // Attempt to read the gzipped version first if that fails read the original
-        let gzipped_path = {
-            let path_str = absolute_path.to_string_lossy();
-            let path_str: &str = path_str.borrow();
-            PathBuf::from(&format!("{}.gz", path_str))
-        };
+        // Ranges always refer to the original as byte positions within a
+        // compressed body are meaningless to clients, so the original is
+        // read first when a range is requested. If it does not exist a
+        // precompressed version is served whole instead.
+        let policy = config.symlink_policy();
+        let mut original = None;
+        if conditions.range.is_some() {
+            match Self::open_file(base_dir, path, policy).await {
+                Ok(file) => original = Some(file),
+                Err(error) if error.kind() == ErrorKind::NotFound => {}
+                Err(error) => return Err(error),
+            }
+        }
+
+        // Otherwise attempt to read the precompressed versions the client
+        // accepts first, if that fails read the original. The original does
+        // not need to exist when a precompressed version does.
+        let mut precompressed = None;
+        if original.is_none() {
+            for encoding in &conditions.encodings {
+                let extension = PRECOMPRESSED_EXTENSIONS
+                    .iter()
+                    .find(|(coding, _)| coding == encoding)
+                    .map(|(_, extension)| extension)
+                    .unwrap();
+
+                let path = format!("{}.{}", path, extension);
+                if let Ok(file) = Self::open_file(base_dir, &path, policy).await {
+                    precompressed = Some((file, *encoding));
+                    break;
+                }
+            }
+        }
 
-        let (mut file, encoding) = match File::open(gzipped_path).await {
-            Ok(file) => (file, "gzip"),
-            _ => (File::open(&absolute_path).await?, "identity"),
+        let (mut file, encoding) = match (original, precompressed) {
+            (Some(file), _) => (file, None),
+            (None, Some((file, encoding))) => (file, Some(encoding)),
+            (None, None) => (Self::open_file(base_dir, path, policy).await?, None),
         };
 
-        // Get the file length
+        // Directories are not served
         let metadata = file.metadata().await?;
-        let (range_start, range_end) = request_range
-            .as_ref()
-            .map_or((0, metadata.len()), |v| (v.start as u64, v.end as u64));
+        if !metadata.is_file() {
+            return Err(Error::new(ErrorKind::NotFound, "Not a file"));
+        }
 
-        // Seek to the reading position
-        file.seek(SeekFrom::Start(range_start)).await?;
+        let total_length = metadata.len();
+        let validators = Validators::new(total_length, metadata.modified()?, encoding);
+        if conditions.is_not_modified(&validators) {
+            return Ok(FetchResult::NotModified(validators));
+        }
+
+        // Work out the part of the file to send
+        let request_range = conditions
+            .get_range(&validators)
+            .filter(|_| encoding.is_none());
+        let range = match request_range {
+            Some(request_range) => {
+                match request_range.resolve(total_length) {
+                    Some(range) => Some(range),
+                    None => return Ok(FetchResult::RangeNotSatisfiable(total_length)),
+                }
+            }
+            None => None,
+        };
+
+        let (start, length) =
+            range.map_or((0, total_length), |(start, end)| (start, end - start + 1));
 
-        // Read the content
-        let mut content = vec![0; range_end as usize];
-        file.read(&mut content).await?;
+        file.seek(SeekFrom::Start(start)).await?;
 
-        let mime_type = if let Some(os_str) = absolute_path.extension() {
+        let mime_type = if let Some(os_str) = Path::new(path).extension() {
             let value = os_str.to_string_lossy();
             config.get_mime_type(value.borrow())
         } else {
             config.get_mime_type("txt")
-        };
+        }
+        .to_owned();
 
-        Ok(FetchResultPayload {
-            content,
+        Ok(FetchResult::Content(FetchResultPayload {
+            content: Self::stream_file(file, length),
+            content_length: length,
             mime_type,
             encoding,
-            total_length: metadata.len(),
-            range_start,
-            range_end,
+            validators,
+            range,
+            total_length,
+        }))
+    }
+
+    /// Splits the path of a requested file into its segments. Fails with
+    /// `InvalidInput` for paths with dot-segments or NUL bytes.
+    fn get_path_segments(path: &str) -> Result<Vec<&str>, Error> {
+        let segments = path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .collect::<Vec<&str>>();
+
+        if path.contains('\0')
+            || segments
+                .iter()
+                .any(|segment| *segment == "." || *segment == "..")
+        {
+            warn!(
+                "Rejected request for invalid path '{}'",
+                path.escape_debug()
+            );
+            return Err(Error::new(ErrorKind::InvalidInput, "Invalid path"));
+        }
+
+        Ok(segments)
+    }
+
+    /// Resolves the path of a requested file within its base directory. Fails
+    /// with `InvalidInput` for invalid paths and with `NotFound` for paths
+    /// that lead outside of the base directory through symbolic links.
+    async fn resolve_path(
+        base_dir: &str,
+        path: &str,
+        policy: SymlinkPolicy,
+    ) -> Result<PathBuf, Error> {
+        let segments = Self::get_path_segments(path)?;
+        let base_path = PathBuf::from(base_dir);
+        let mut absolute_path = base_path.clone();
+        absolute_path.extend(&segments);
+
+        let leads_outside = match policy {
+            SymlinkPolicy::Follow => false,
+            SymlinkPolicy::WithinBase => {
+                let base_path = fs::canonicalize(&base_path).await?;
+                let canonical_path = fs::canonicalize(&absolute_path).await?;
+                !canonical_path.starts_with(&base_path)
+            }
+            SymlinkPolicy::Deny => {
+                let mut current_path = base_path;
+                let mut has_symlink = false;
+                for segment in &segments {
+                    current_path.push(segment);
+                    if fs::symlink_metadata(&current_path)
+                        .await?
+                        .file_type()
+                        .is_symlink()
+                    {
+                        has_symlink = true;
+                        break;
+                    }
+                }
+
+                has_symlink
+            }
+        };
+
+        if leads_outside {
+            warn!(
+                "Rejected request for path '{}' that leads outside of '{}'",
+                path, base_dir
+            );
+
+            return Err(Error::new(ErrorKind::NotFound, "Path outside of base"));
+        }
+
+        Ok(absolute_path)
+    }
+
+    /// Opens a requested file within its base directory
+    async fn open_file(base_dir: &str, path: &str, policy: SymlinkPolicy) -> Result<File, Error> {
+        let absolute_path = Self::resolve_path(base_dir, path, policy).await?;
+        File::open(&absolute_path).await
+    }
+
+    /// Streams the specified number of bytes of a file from its current
+    /// position in chunks as they are read
+    fn stream_file(file: File, length: u64) -> Body {
+        let chunks = stream::unfold((file, length), |(mut file, remaining)| {
+            async move {
+                if remaining == 0 {
+                    return None;
+                }
+
+                let mut chunk = vec![0; remaining.min(CHUNK_SIZE) as usize];
+                match file.read(&mut chunk).await {
+                    Ok(0) => {
+                        let error = Error::new(ErrorKind::UnexpectedEof, "The file was truncated");
+                        Some((Err(error), (file, 0)))
+                    }
+                    Ok(count) => {
+                        chunk.truncate(count);
+                        Some((Ok(Bytes::from(chunk)), (file, remaining - count as u64)))
+                    }
+                    Err(error) => Some((Err(error), (file, 0))),
+                }
+            }
+        });
+
+        Body::wrap_stream(chunks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        RequestRange,
+        Service,
+    };
+    use hyper::header::{
+        self,
+        HeaderMap,
+        HeaderValue,
+    };
+
+    fn get_range(value: &'static str) -> Option<(u64, Option<u64>, bool)> {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(header::RANGE, HeaderValue::from_static(value));
+        Service::get_request_range(&header_map).map(|range| {
+            match range {
+                RequestRange::FromTo(start, end) => (start, end, false),
+                RequestRange::Suffix(count) => (count, None, true),
+            }
         })
     }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(get_range("bytes=0-499"), Some((0, Some(499), false)));
+        assert_eq!(get_range(" Bytes = 500 - "), Some((500, None, false)));
+        assert_eq!(get_range("bytes=-500"), Some((500, None, true)));
+    }
+
+    #[test]
+    fn ignores_unsupported_ranges() {
+        assert_eq!(get_range("bytes=500-499"), None);
+        assert_eq!(get_range("bytes=0-1,5-6"), None);
+        assert_eq!(get_range("bytes=-"), None);
+        assert_eq!(get_range("items=0-1"), None);
+        assert_eq!(get_range("bytes=99999999999999999999-"), None);
+        assert!(Service::get_request_range(&HeaderMap::new()).is_none());
+    }
+
+    #[test]
+    fn resolves_ranges_within_the_file() {
+        assert_eq!(
+            RequestRange::FromTo(0, Some(499)).resolve(1000),
+            Some((0, 499))
+        );
+        assert_eq!(
+            RequestRange::FromTo(500, None).resolve(1000),
+            Some((500, 999))
+        );
+        assert_eq!(
+            RequestRange::FromTo(500, Some(5000)).resolve(1000),
+            Some((500, 999))
+        );
+        assert_eq!(RequestRange::Suffix(100).resolve(1000), Some((900, 999)));
+        assert_eq!(RequestRange::Suffix(5000).resolve(1000), Some((0, 999)));
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(RequestRange::FromTo(1000, None).resolve(1000), None);
+        assert_eq!(RequestRange::FromTo(0, None).resolve(0), None);
+        assert_eq!(RequestRange::Suffix(0).resolve(1000), None);
+        assert_eq!(RequestRange::Suffix(100).resolve(0), None);
+    }
 }
@@ -1,32 +1,126 @@
-use std::collections::HashMap;
+use super::BaseDirectory;
+use serde_derive::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+};
+
+/// The cache policy of files that match no configured policy
+const DEFAULT_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// The MIME types of the file extensions that are served by default
+const DEFAULT_MIME_TYPES: [(&str, &str); 22] = [
+    ("html", "text/html; charset=utf-8"),
+    ("js", "application/javascript; charset=utf-8"),
+    ("mjs", "application/javascript; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("xml", "application/xml"),
+    ("png", "image/png"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("svg", "image/svg+xml"),
+    ("webp", "image/webp"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("wasm", "application/wasm"),
+    ("webm", "video/webm"),
+    ("mp4", "video/mp4"),
+    ("pdf", "application/pdf"),
+];
+
+/// The `Cache-Control` header of the files in a directory
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CachePolicy {
+    pub directory: BaseDirectory,
+    /// The prefix of the paths of the files, relative to the directory. The
+    /// policy applies to all files of the directory if not set.
+    #[serde(default)]
+    pub path: Option<String>,
+    pub cache_control: String,
+}
+
+/// Whether files are served through symbolic links
+#[derive(Clone, Copy)]
+pub enum SymlinkPolicy {
+    /// Never serve files through symbolic links
+    Deny,
+    /// Only serve files that symbolic links resolve to within the base
+    /// directory
+    WithinBase,
+    /// Serve files wherever symbolic links lead
+    Follow,
+}
 
 pub struct ServiceConfig {
     content_dir: String,
     uploads_dir: String,
     not_found_file: String,
-    mime_type_map: HashMap<&'static str, &'static str>,
+    cache_policies: Vec<CachePolicy>,
+    symlink_policy: SymlinkPolicy,
+    mime_type_map: HashMap<String, String>,
 }
 
 impl ServiceConfig {
-    pub fn new(content_dir: String, uploads_dir: String, not_found_file: String) -> Self {
-        // TODO: This should be read from a file or set for every file in an index
-        let mut mime_type_map = HashMap::<&'static str, &'static str>::new();
-        mime_type_map.insert("html", mime::TEXT_HTML_UTF_8.as_ref());
-        mime_type_map.insert("js", mime::APPLICATION_JAVASCRIPT_UTF_8.as_ref());
-        mime_type_map.insert("png", mime::IMAGE_PNG.as_ref());
-        mime_type_map.insert("jpeg", mime::IMAGE_JPEG.as_ref());
-        mime_type_map.insert("jpg", mime::IMAGE_JPEG.as_ref());
-        mime_type_map.insert("gif", mime::IMAGE_GIF.as_ref());
-        mime_type_map.insert("svg", mime::IMAGE_SVG.as_ref());
-        mime_type_map.insert("webm", "video/webm");
-        mime_type_map.insert("mp4", "video/mp4");
-
-        Self {
+    /// Creates the configuration of the file service. The MIME types of the
+    /// `mime_types_file`, in the format of `mime.types`, and then those of
+    /// `mime_types` take precedence over the default ones.
+    pub fn new(
+        content_dir: String,
+        uploads_dir: String,
+        not_found_file: String,
+        cache_policies: Vec<CachePolicy>,
+        symlink_policy: SymlinkPolicy,
+        mime_types_file: Option<&str>,
+        mime_types: HashMap<String, String>,
+    ) -> Result<Self, String> {
+        let mut mime_type_map = DEFAULT_MIME_TYPES
+            .iter()
+            .map(|(extension, mime_type)| (extension.to_string(), mime_type.to_string()))
+            .collect::<HashMap<String, String>>();
+
+        if let Some(mime_types_file) = mime_types_file {
+            let contents = fs::read_to_string(mime_types_file)
+                .map_err(|error| format!("Could not read '{}': {}", mime_types_file, error))?;
+
+            for line in contents.lines() {
+                let line = line.split('#').next().unwrap_or("");
+                let mut fields = line.split_whitespace();
+                if let Some(mime_type) = fields.next() {
+                    for extension in fields {
+                        mime_type_map.insert(extension.to_lowercase(), mime_type.to_owned());
+                    }
+                }
+            }
+        }
+
+        for (extension, mime_type) in mime_types {
+            mime_type_map.insert(extension.to_lowercase(), mime_type);
+        }
+
+        for (extension, mime_type) in &mime_type_map {
+            if mime_type.parse::<mime::Mime>().is_err() {
+                return Err(format!(
+                    "Invalid MIME type '{}' for extension '{}'",
+                    mime_type, extension
+                ));
+            }
+        }
+
+        Ok(Self {
             content_dir,
             uploads_dir,
             not_found_file,
+            cache_policies,
+            symlink_policy,
             mime_type_map,
-        }
+        })
     }
 
     pub fn content_dir(&self) -> &str {
@@ -41,10 +135,32 @@ impl ServiceConfig {
         &self.not_found_file
     }
 
-    pub fn get_mime_type(&self, file_extension: &str) -> &'static str {
-        match self.mime_type_map.get(file_extension) {
+    pub fn symlink_policy(&self) -> SymlinkPolicy {
+        self.symlink_policy
+    }
+
+    /// Gets the `Cache-Control` header of a file. The first matching policy
+    /// applies.
+    pub fn get_cache_control(&self, base_dir: &BaseDirectory, path: &str) -> &str {
+        let path = path.trim_start_matches('/');
+        self.cache_policies
+            .iter()
+            .find(|policy| {
+                policy.directory == *base_dir
+                    && policy
+                        .path
+                        .as_ref()
+                        .map_or(true, |prefix| path.starts_with(prefix.as_str()))
+            })
+            .map_or(DEFAULT_CACHE_CONTROL, |policy| &policy.cache_control)
+    }
+
+    /// Gets the MIME type of a file extension. Files of unknown types are
+    /// served as arbitrary binary data.
+    pub fn get_mime_type(&self, file_extension: &str) -> &str {
+        match self.mime_type_map.get(&file_extension.to_lowercase()) {
             Some(mime_type) => mime_type,
-            None => mime::TEXT_PLAIN_UTF_8.as_ref(),
+            None => mime::APPLICATION_OCTET_STREAM.as_ref(),
         }
     }
 }
@@ -0,0 +1,78 @@
+use super::{
+    util as http_util,
+    HttpError,
+    HttpResponse,
+};
+use flate2::{
+    write::GzEncoder,
+    Compression,
+};
+use hyper::{
+    body,
+    header::{
+        self,
+        HeaderMap,
+        HeaderValue,
+    },
+    Body,
+};
+use log::error;
+use std::io::Write;
+
+/// Bodies smaller than this are not worth compressing
+const MIN_COMPRESSED_LENGTH: usize = 1024;
+
+/// Compresses the body of a JSON response with gzip if the client accepts
+/// it. Other responses are returned as is.
+pub async fn compress_json_response(
+    response: HttpResponse,
+    header_map: &HeaderMap,
+) -> HttpResponse {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .map_or(false, |value| {
+            value.as_bytes() == mime::APPLICATION_JSON.as_ref().as_bytes()
+        });
+
+    if !is_json || response.headers().contains_key(header::CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert(header::VARY, HeaderValue::from_static("accept-encoding"));
+
+    if http_util::get_accepted_encodings(header_map, &["gzip"]).is_empty() {
+        return HttpResponse::from_parts(parts, body);
+    }
+
+    let content = match body::to_bytes(body).await {
+        Ok(content) => content,
+        Err(error) => {
+            error!("Unable to read response body: {}", error);
+            return HttpError::InternalError(None).to_response();
+        }
+    };
+
+    if content.len() < MIN_COMPRESSED_LENGTH {
+        return HttpResponse::from_parts(parts, Body::from(content));
+    }
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::fast());
+    match encoder.write_all(&content).and_then(|_| encoder.finish()) {
+        Ok(compressed) => {
+            parts.headers.remove(header::CONTENT_LENGTH);
+            parts
+                .headers
+                .insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+
+            HttpResponse::from_parts(parts, Body::from(compressed))
+        }
+        Err(error) => {
+            error!("Unable to compress response body: {}", error);
+            HttpResponse::from_parts(parts, Body::from(content))
+        }
+    }
+}
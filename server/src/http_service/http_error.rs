@@ -14,10 +14,21 @@ pub enum HttpError {
     InternalError(Option<Vec<u8>>),
     Unauthorized(Option<Vec<u8>>),
     NotFound(Option<Vec<u8>>),
+    Conflict(Option<Vec<u8>>),
+    PayloadTooLarge(Option<Vec<u8>>),
+    ServiceUnavailable(Option<Vec<u8>>),
+    /// A rate limit was reached. Holds the number of seconds to wait before
+    /// retrying.
+    TooManyRequests(Option<Vec<u8>>, u64),
 }
 
 impl HttpError {
     pub fn to_response(self) -> HttpResponse {
+        let retry_after = match &self {
+            HttpError::TooManyRequests(_, seconds) => Some(*seconds),
+            _ => None,
+        };
+
         let (message, status) = match self {
             HttpError::BadRequest(msg) => (msg, StatusCode::BAD_REQUEST),
             HttpError::InternalError(msg) => {
@@ -38,15 +49,41 @@ impl HttpError {
                     StatusCode::NOT_FOUND,
                 )
             }
+            HttpError::Conflict(msg) => {
+                (
+                    msg.unwrap_or("The request conflicts with the resource's state".into()),
+                    StatusCode::CONFLICT,
+                )
+            }
+            HttpError::PayloadTooLarge(msg) => {
+                (
+                    msg.unwrap_or("The request payload is too large".into()),
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                )
+            }
+            HttpError::ServiceUnavailable(msg) => {
+                (
+                    msg.unwrap_or("The service is temporarily unavailable".into()),
+                    StatusCode::SERVICE_UNAVAILABLE,
+                )
+            }
+            HttpError::TooManyRequests(msg, _) => {
+                (
+                    msg.unwrap_or("Too many requests".into()),
+                    StatusCode::TOO_MANY_REQUESTS,
+                )
+            }
         };
 
-        Response::builder()
-            .status(status)
-            .header(
-                header::CONTENT_TYPE,
-                HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
-            )
-            .body(Body::from(message))
-            .unwrap()
+        let mut builder = Response::builder().status(status).header(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(mime::TEXT_PLAIN_UTF_8.as_ref()),
+        );
+
+        if let Some(seconds) = retry_after {
+            builder = builder.header(header::RETRY_AFTER, seconds);
+        }
+
+        builder.body(Body::from(message)).unwrap()
     }
 }
@@ -1,5 +1,7 @@
+mod compression;
 mod http_error;
 mod service;
+mod service_config;
 mod service_container;
 mod service_router;
 
@@ -7,7 +9,11 @@ pub mod util;
 
 pub use self::{
     service::Service,
-    service_container::ServiceContainer,
+    service_config::ServiceConfig,
+    service_container::{
+        ActivityServices,
+        ServiceContainer,
+    },
 };
 pub use http_error::HttpError;
 
@@ -31,14 +37,15 @@ pub type HttpResponse = Response<Body>;
 /// Represents a full http result
 pub type HttpResult = Result<HttpResponse, HttpError>;
 
-pub async fn run(http_port: &str, service_container: Arc<ServiceContainer>) {
+pub async fn run(config: ServiceConfig, service_container: Arc<ServiceContainer>) {
+    let config = Arc::new(config);
+    let address_string = format!("0.0.0.0:{0}", config.port());
     let make_service = make_service_fn(move |_| {
+        let config = config.clone();
         let service_container = service_container.clone();
-        async { Ok::<_, Error>(Service::new(service_container)) }
+        async { Ok::<_, Error>(Service::new(config, service_container)) }
     });
 
-    let address_string = format!("0.0.0.0:{0}", http_port);
-
     info!("Binding to address {}...", address_string);
     let address = address_string.parse().unwrap();
     let server = Server::bind(&address).serve(make_service);
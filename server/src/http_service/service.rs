@@ -1,5 +1,6 @@
 use super::{
     HttpResponse,
+    ServiceConfig,
     ServiceContainer,
     ServiceRouter,
 };
@@ -21,12 +22,16 @@ use std::{
 
 /// Main http service structure for the host
 pub struct Service {
+    config: Arc<ServiceConfig>,
     service_container: Arc<ServiceContainer>,
 }
 
 impl Service {
-    pub fn new(service_container: Arc<ServiceContainer>) -> Self {
-        Self { service_container }
+    pub fn new(config: Arc<ServiceConfig>, service_container: Arc<ServiceContainer>) -> Self {
+        Self {
+            config,
+            service_container,
+        }
     }
 }
 
@@ -40,7 +45,7 @@ impl HyperService<Request<Body>> for Service {
     }
 
     fn call(&mut self, req: Request<Body>) -> Self::Future {
-        let router = ServiceRouter::new(self.service_container.clone());
+        let router = ServiceRouter::new(self.config.clone(), self.service_container.clone());
         Box::pin(router.route_request(req))
     }
 }
@@ -0,0 +1,23 @@
+pub struct ServiceConfig {
+    port: String,
+    compress_json: bool,
+}
+
+impl ServiceConfig {
+    pub fn new(port: String, compress_json: bool) -> Self {
+        Self {
+            port,
+            compress_json,
+        }
+    }
+
+    pub fn port(&self) -> &str {
+        &self.port
+    }
+
+    /// Whether JSON responses of the doc-api and search-api are compressed
+    /// for clients that accept it
+    pub fn compress_json(&self) -> bool {
+        self.compress_json
+    }
+}
@@ -1,10 +1,25 @@
 use crate::{
     auth_service::Service as AuthService,
     document_service::Service as DocumentService,
+    event_service::Service as EventService,
     file_service::Service as FileService,
+    notification_service::Service as NotificationService,
+    page_service::Service as PageService,
     search_service::Service as SearchService,
     upload_service::Service as UploadService,
+    user_service::Service as UserService,
+    webhook_service::Service as WebhookService,
 };
+use std::sync::Arc;
+
+/// The services that follow the activity of users: webhooks, event streams,
+/// notifications and user profiles
+pub struct ActivityServices {
+    pub webhook_service: Arc<WebhookService>,
+    pub event_service: Arc<EventService>,
+    pub notification_service: Arc<NotificationService>,
+    pub user_service: UserService,
+}
 
 /// Contains services available to fulfill requests
 pub struct ServiceContainer {
@@ -13,6 +28,11 @@ pub struct ServiceContainer {
     document_service: DocumentService,
     search_service: SearchService,
     upload_service: UploadService,
+    page_service: PageService,
+    webhook_service: Arc<WebhookService>,
+    event_service: Arc<EventService>,
+    notification_service: Arc<NotificationService>,
+    user_service: UserService,
 }
 
 impl ServiceContainer {
@@ -23,13 +43,27 @@ impl ServiceContainer {
         document_service: DocumentService,
         search_service: SearchService,
         upload_service: UploadService,
+        page_service: PageService,
+        activity_services: ActivityServices,
     ) -> Self {
+        let ActivityServices {
+            webhook_service,
+            event_service,
+            notification_service,
+            user_service,
+        } = activity_services;
+
         Self {
             auth_service,
             file_service,
             document_service,
             search_service,
             upload_service,
+            page_service,
+            webhook_service,
+            event_service,
+            notification_service,
+            user_service,
         }
     }
 
@@ -52,4 +86,24 @@ impl ServiceContainer {
     pub fn upload_service(&self) -> &UploadService {
         &self.upload_service
     }
+
+    pub fn page_service(&self) -> &PageService {
+        &self.page_service
+    }
+
+    pub fn webhook_service(&self) -> &WebhookService {
+        &self.webhook_service
+    }
+
+    pub fn event_service(&self) -> &EventService {
+        &self.event_service
+    }
+
+    pub fn notification_service(&self) -> &NotificationService {
+        &self.notification_service
+    }
+
+    pub fn user_service(&self) -> &UserService {
+        &self.user_service
+    }
 }
@@ -1,7 +1,9 @@
 use super::{
+    compression,
     HttpError,
     HttpResponse,
     HttpResult,
+    ServiceConfig,
     ServiceContainer,
 };
 use crate::{
@@ -29,12 +31,16 @@ use std::{
 
 /// Main http service router structure
 pub struct ServiceRouter {
+    config: Arc<ServiceConfig>,
     service_container: Arc<ServiceContainer>,
 }
 
 impl ServiceRouter {
-    pub fn new(service_container: Arc<ServiceContainer>) -> Self {
-        Self { service_container }
+    pub fn new(config: Arc<ServiceConfig>, service_container: Arc<ServiceContainer>) -> Self {
+        Self {
+            config,
+            service_container,
+        }
     }
 
     /// Routes an http request to it's servicing destination
@@ -61,15 +67,33 @@ impl ServiceRouter {
         // Route the request
         let path: &str = path.borrow();
         let result = match parts.method {
-            Method::GET => self.handle_get_request(path, headers, query).await,
+            Method::GET => {
+                self.handle_get_request(path, headers, query, &session)
+                    .await
+            }
             Method::POST => {
-                self.handle_post_request(path, headers, body, &session)
+                self.handle_post_request(path, headers, query, body, &session)
                     .await
             }
-            Method::PATCH => self.handle_patch_request(path, &session).await,
+            Method::PATCH => {
+                self.handle_patch_request(path, headers, body, &session)
+                    .await
+            }
+            Method::HEAD => self.handle_head_request(path, &session).await,
+            Method::DELETE => self.handle_delete_request(path, &session).await,
             _ => Err(HttpError::BadRequest("Bad or unsupported method".into())),
         };
 
+        // Compress api responses if enabled
+        let result = match (result, Self::extract_paths(path).0) {
+            (Ok(response), "doc-api") | (Ok(response), "search-api")
+                if self.config.compress_json() =>
+            {
+                Ok(compression::compress_json_response(response, headers).await)
+            }
+            (result, _) => result,
+        };
+
         // Modify auth related headers if necessary
         result
             .map(|mut response| {
@@ -87,6 +111,7 @@ impl ServiceRouter {
         path: &str,
         header_map: &HeaderMap,
         query: Option<&str>,
+        session: &Session,
     ) -> HttpResult {
         let (root_path, relative_path) = Self::extract_paths(path);
         match root_path {
@@ -111,7 +136,7 @@ impl ServiceRouter {
             "search-api" => {
                 self.service_container
                     .search_service()
-                    .handle_get_request(relative_path, query)
+                    .handle_get_request(relative_path, query, session)
                     .await
             }
             "uploads" => {
@@ -120,13 +145,79 @@ impl ServiceRouter {
                     .handle_get_request(relative_path, header_map, BaseDirectory::Uploads)
                     .await
             }
-            _ => {
-                // Treat all other gets as gets against the main app.
+            "upload-api" => {
+                self.service_container
+                    .upload_service()
+                    .handle_get_request(relative_path, session)
+                    .await
+            }
+            "webhook-api" => {
+                self.service_container
+                    .webhook_service()
+                    .handle_get_request(relative_path, query, session)
+                    .await
+            }
+            "event-api" => {
+                self.service_container
+                    .event_service()
+                    .handle_get_request(relative_path, query, header_map, session)
+                    .await
+            }
+            "notification-api" => {
+                self.service_container
+                    .notification_service()
+                    .handle_get_request(relative_path, query, session)
+                    .await
+            }
+            "user-api" => {
+                self.service_container
+                    .user_service()
+                    .handle_get_request(relative_path, session)
+                    .await
+            }
+            "docs" => {
+                self.service_container
+                    .page_service()
+                    .handle_document_request(relative_path)
+                    .await
+            }
+            "sitemap.xml" => {
+                self.service_container
+                    .page_service()
+                    .handle_sitemap_request()
+                    .await
+            }
+            "sitemaps" => {
+                self.service_container
+                    .page_service()
+                    .handle_sitemaps_request(relative_path)
+                    .await
+            }
+            "feeds" => {
+                self.service_container
+                    .page_service()
+                    .handle_feed_request(relative_path)
+                    .await
+            }
+            "robots.txt" => {
+                self.service_container
+                    .page_service()
+                    .handle_robots_request()
+                    .await
+            }
+            "" | "tos" | "search" | "props" => {
+                // Treat all other pages as gets against the main app.
                 self.service_container
                     .file_service()
                     .handle_get_request("app.html", header_map, BaseDirectory::Content)
                     .await
             }
+            _ => {
+                self.service_container
+                    .page_service()
+                    .handle_not_found_request()
+                    .await
+            }
         }
     }
 
@@ -134,6 +225,7 @@ impl ServiceRouter {
         &self,
         path: &str,
         header_map: &HeaderMap,
+        query: Option<&str>,
         body: Body,
         session: &Session,
     ) -> HttpResult {
@@ -145,17 +237,47 @@ impl ServiceRouter {
                     .handle_post_request(relative_path, body, session)
                     .await
             }
+            "search-api" => {
+                self.service_container
+                    .search_service()
+                    .handle_post_request(relative_path, body, session)
+                    .await
+            }
             "upload-api" => {
                 self.service_container
                     .upload_service()
-                    .handle_post_request(relative_path, header_map, body, session)
+                    .handle_post_request(relative_path, query, header_map, body, session)
+                    .await
+            }
+            "webhook-api" => {
+                self.service_container
+                    .webhook_service()
+                    .handle_post_request(relative_path, session)
+                    .await
+            }
+            "notification-api" => {
+                self.service_container
+                    .notification_service()
+                    .handle_post_request(relative_path, body, session)
+                    .await
+            }
+            "user-api" => {
+                self.service_container
+                    .user_service()
+                    .handle_post_request(relative_path, body, session)
                     .await
             }
             _ => Err(Self::build_invalid_path_error(root_path)),
         }
     }
 
-    async fn handle_patch_request(&self, path: &str, session: &Session) -> HttpResult {
+    async fn handle_patch_request(
+        &self,
+        path: &str,
+        header_map: &HeaderMap,
+        body: Body,
+        session: &Session,
+    ) -> HttpResult {
         let (root_path, relative_path) = Self::extract_paths(path);
         match root_path {
             "doc-api" => {
@@ -164,6 +286,44 @@ impl ServiceRouter {
                     .handle_patch_request(relative_path, session)
                     .await
             }
+            "upload-api" => {
+                self.service_container
+                    .upload_service()
+                    .handle_patch_request(relative_path, header_map, body, session)
+                    .await
+            }
+            "notification-api" => {
+                self.service_container
+                    .notification_service()
+                    .handle_patch_request(relative_path, session)
+                    .await
+            }
+            _ => Err(Self::build_invalid_path_error(root_path)),
+        }
+    }
+
+    async fn handle_head_request(&self, path: &str, session: &Session) -> HttpResult {
+        let (root_path, relative_path) = Self::extract_paths(path);
+        match root_path {
+            "upload-api" => {
+                self.service_container
+                    .upload_service()
+                    .handle_head_request(relative_path, session)
+                    .await
+            }
+            _ => Err(Self::build_invalid_path_error(root_path)),
+        }
+    }
+
+    async fn handle_delete_request(&self, path: &str, session: &Session) -> HttpResult {
+        let (root_path, relative_path) = Self::extract_paths(path);
+        match root_path {
+            "upload-api" => {
+                self.service_container
+                    .upload_service()
+                    .handle_delete_request(relative_path, session)
+                    .await
+            }
             _ => Err(Self::build_invalid_path_error(root_path)),
         }
     }
@@ -191,7 +351,7 @@ impl ServiceRouter {
     fn extract_paths(path: &str) -> (&str, &str) {
         lazy_static! {
             static ref ROOT_PATH_REGEX: Regex =
-                Regex::new(r"^/+(?P<root_path>[\w-]+)(?:/+(?P<relative_path>.+)?)?$").unwrap();
+                Regex::new(r"^/+(?P<root_path>[\w.-]+)(?:/+(?P<relative_path>.+)?)?$").unwrap();
         }
 
         ROOT_PATH_REGEX.captures(path).map_or(("", ""), |captures| {
@@ -2,6 +2,7 @@ use super::HttpResponse;
 use hyper::{
     header::{
         self,
+        HeaderMap,
         HeaderValue,
     },
     Body,
@@ -60,6 +61,49 @@ pub fn parse_query_string<'a>(query: &'a str) -> HashMap<&'a str, &'a str> {
     query_params
 }
 
+/// Gets the content codings a client accepts from the `Accept-Encoding`
+/// header, out of the specified codings. The most preferred coding comes
+/// first; codings the client values equally keep their specified order.
+/// Identity is always acceptable and is never returned.
+pub fn get_accepted_encodings<'a>(header_map: &HeaderMap, supported: &[&'a str]) -> Vec<&'a str> {
+    // Parse the codings and their quality values, e.g. 'br;q=1.0, gzip;q=0.8'
+    let mut qualities: HashMap<String, f32> = HashMap::new();
+    for value in header_map.get_all(header::ACCEPT_ENCODING) {
+        let value = value.to_str().unwrap_or("");
+        for item in value.split(',') {
+            let mut params = item.split(';').map(str::trim);
+            let coding = match params.next() {
+                Some("") | None => continue,
+                Some(coding) => coding.to_lowercase(),
+            };
+
+            let quality = params
+                .find_map(|param| param.strip_prefix("q="))
+                .map_or(Some(1.0), |quality| quality.parse::<f32>().ok());
+
+            if let Some(quality) = quality {
+                let coding = match coding.as_str() {
+                    "x-gzip" => "gzip".to_owned(),
+                    _ => coding,
+                };
+
+                qualities.insert(coding, quality);
+            }
+        }
+    }
+
+    // Codings that are not listed take the quality of '*' if present
+    let wildcard = qualities.get("*").copied().unwrap_or(0.0);
+    let mut accepted = supported
+        .iter()
+        .map(|coding| (*coding, qualities.get(*coding).copied().unwrap_or(wildcard)))
+        .filter(|(_, quality)| *quality > 0.0)
+        .collect::<Vec<(&str, f32)>>();
+
+    accepted.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(cmp::Ordering::Equal));
+    accepted.into_iter().map(|(coding, _)| coding).collect()
+}
+
 /// Gets paging options from params in a hashmap
 pub fn get_paging_options(query_params: &HashMap<&str, &str>) -> Result<(u16, u16), String> {
     const MAX_LIMIT: u16 = 50;
@@ -81,3 +125,41 @@ pub fn get_paging_options(query_params: &HashMap<&str, &str>) -> Result<(u16, u1
 
     Ok((offset, limit))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::get_accepted_encodings;
+    use hyper::header::{
+        self,
+        HeaderMap,
+        HeaderValue,
+    };
+
+    fn accepted(value: &'static str) -> Vec<&'static str> {
+        let mut header_map = HeaderMap::new();
+        header_map.insert(header::ACCEPT_ENCODING, HeaderValue::from_static(value));
+        get_accepted_encodings(&header_map, &["br", "gzip"])
+    }
+
+    #[test]
+    fn orders_codings_by_quality() {
+        assert_eq!(accepted("gzip, deflate, br"), vec!["br", "gzip"]);
+        assert_eq!(accepted("br;q=0.5, gzip;q=0.8"), vec!["gzip", "br"]);
+        assert_eq!(accepted("x-gzip"), vec!["gzip"]);
+    }
+
+    #[test]
+    fn applies_the_wildcard_to_unlisted_codings() {
+        assert_eq!(accepted("*"), vec!["br", "gzip"]);
+        assert_eq!(accepted("gzip;q=0, *;q=0.5"), vec!["br"]);
+    }
+
+    #[test]
+    fn excludes_refused_and_missing_codings() {
+        assert_eq!(accepted("br;q=0, identity"), Vec::<&str>::new());
+        assert_eq!(
+            get_accepted_encodings(&HeaderMap::new(), &["br", "gzip"]),
+            Vec::<&str>::new()
+        );
+    }
+}
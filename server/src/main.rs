@@ -1,17 +1,31 @@
 mod auth_service;
 mod database_adapters;
 mod document_service;
+mod event_service;
 mod file_service;
 mod http_service;
 mod logging;
+mod notification_service;
+mod page_service;
+mod search_index;
 mod search_service;
 mod upload_service;
+mod user_service;
 mod util;
+mod webhook_service;
 
-use database_adapters::couchbase::QueryStore;
-use http_service::ServiceContainer;
+use database_adapters::couchbase::{
+    Adapters,
+    QueryStore,
+};
+use http_service::{
+    ActivityServices,
+    ServiceContainer,
+};
+use search_index::SearchIndex;
 use serde_json::Value;
 use std::{
+    collections::HashMap,
     env::args,
     fs::File,
     io::Read,
@@ -77,32 +91,40 @@ async fn main() {
         serde_json::from_slice(&content).unwrap()
     };
 
-    // The auth sub-service
-    let auth_service = {
-        let oauth_config = &json_config["auth-service"]["twitter-oauth"];
-        let oauth_config = auth_service::TwitterOauthConfig::new(
-            value_as_str(&oauth_config, "consumer-key").to_owned(),
-            value_as_str(&oauth_config, "consumer-secret").to_owned(),
-            value_as_str(&oauth_config, "callback-url").to_owned(),
-        );
-
-        let jwt_config = &json_config["auth-service"]["jwt"];
-        let options = auth_service::ServiceConfig::new(
-            oauth_config,
-            value_as_str(&jwt_config, "signing-key").to_owned(),
-        );
-
-        auth_service::Service::new(options)
-    };
-
     // Create the file sub-service
     let file_service = {
         let config = &json_config["file-service"];
+        let cache_policies: Vec<file_service::CachePolicy> = match config.get("cache-policies") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'cache-policies'")
+            }
+            None => Vec::new(),
+        };
+
+        let symlink_policy = match config["symlinks"].as_str() {
+            Some("deny") => file_service::SymlinkPolicy::Deny,
+            Some("within-base") | None => file_service::SymlinkPolicy::WithinBase,
+            Some("follow") => file_service::SymlinkPolicy::Follow,
+            Some(policy) => panic!("Invalid symlink policy: {}", policy),
+        };
+
+        let mime_types: HashMap<String, String> = match config.get("mime-types") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'mime-types'")
+            }
+            None => HashMap::new(),
+        };
+
         let config = file_service::ServiceConfig::new(
             value_as_str(&config, "content-dir").to_string(),
             value_as_str(&config, "uploads-dir").to_string(),
             value_as_str(&config, "not-found-file").to_string(),
-        );
+            cache_policies,
+            symlink_policy,
+            config["mime-types-file"].as_str(),
+            mime_types,
+        )
+        .expect("Invalid file service config");
 
         file_service::Service::new(config)
     };
@@ -111,8 +133,27 @@ async fn main() {
     let query_store = QueryStore::create_from_dir(std::path::Path::new("./n1ql"))
         .expect("Unable to load queries into query store");
 
+    // Use the embedded search index instead of couchbase FTS if configured
+    let search_index = match json_config["search-service"]["engine"].as_str() {
+        Some("embedded") => Some(Arc::new(SearchIndex::new())),
+        Some("couchbase") | None => None,
+        Some(engine) => panic!("Invalid search engine: {}", engine),
+    };
+
     let config = &json_config["couchbase"];
-    let (docs_adapter, props_adapter, name_sets_adapter, search_adapter) = {
+    let Adapters {
+        documents: docs_adapter,
+        proposals: props_adapter,
+        name_sets: name_sets_adapter,
+        search: search_adapter,
+        search_stats: search_stats_adapter,
+        upload_jobs: upload_jobs_adapter,
+        upload_media: upload_media_adapter,
+        upload_usage: upload_usage_adapter,
+        webhook_deliveries: webhook_deliveries_adapter,
+        notifications: notifications_adapter,
+        users: users_adapter,
+    } = {
         let cluster = couchbase_cluster
             .as_ref()
             .map(|s| s.as_str())
@@ -123,28 +164,229 @@ async fn main() {
             value_as_str(&config, "user"),
             value_as_str(&config, "password"),
             query_store,
+            search_index,
+        )
+    };
+
+    let users_adapter = Arc::new(users_adapter);
+
+    // The auth sub-service
+    let auth_service = {
+        let oauth_config = &json_config["auth-service"]["twitter-oauth"];
+        let oauth_config = auth_service::TwitterOauthConfig::new(
+            value_as_str(&oauth_config, "consumer-key").to_owned(),
+            value_as_str(&oauth_config, "consumer-secret").to_owned(),
+            value_as_str(&oauth_config, "callback-url").to_owned(),
+        );
+
+        let jwt_config = &json_config["auth-service"]["jwt"];
+        let options = auth_service::ServiceConfig::new(
+            oauth_config,
+            value_as_str(&jwt_config, "signing-key").to_owned(),
+        );
+
+        auth_service::Service::new(options, users_adapter.clone())
+    };
+
+    name_sets_adapter
+        .rebuild_index()
+        .await
+        .expect("Unable to build the search index");
+
+    // The webhook sub-service. No webhooks are sent unless configured.
+    let webhook_service = {
+        let config = &json_config["webhook-service"];
+        let webhooks: Vec<webhook_service::Webhook> = match config.get("webhooks") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'webhooks'")
+            }
+            None => Vec::new(),
+        };
+
+        let config = webhook_service::ServiceConfig::new(
+            webhooks,
+            config["max-attempts"].as_u64().unwrap_or(5) as u32,
+            config["retry-delay"].as_u64().unwrap_or(30) as u32,
+            config["timeout"].as_u64().unwrap_or(10) as u32,
+            config["log-retention"].as_u64().unwrap_or(604800) as u32,
+        )
+        .expect("Invalid webhook configuration");
+
+        Arc::new(webhook_service::Service::new(
+            config,
+            Arc::new(webhook_deliveries_adapter),
+        ))
+    };
+
+    // The event sub-service
+    let event_service = {
+        let config = &json_config["event-service"];
+        let config = event_service::ServiceConfig::new(
+            config["keep-alive"].as_u64().unwrap_or(15) as u32,
+            config["max-connections"].as_u64().unwrap_or(1000) as u32,
+            config["history-size"].as_u64().unwrap_or(100) as u32,
         )
+        .expect("Invalid event configuration");
+
+        Arc::new(event_service::Service::new(config))
     };
 
+    // The notification sub-service. Users are notified of all events unless
+    // configured otherwise.
+    let notification_service = {
+        let config = &json_config["notification-service"];
+        let default_events: Vec<String> = match config.get("default-events") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'default-events'")
+            }
+            None => {
+                notification_service::NOTIFICATION_EVENTS
+                    .iter()
+                    .map(|event| event.to_string())
+                    .collect()
+            }
+        };
+
+        let config = notification_service::ServiceConfig::new(
+            config["retention"].as_u64().unwrap_or(7776000) as u32,
+            default_events,
+        )
+        .expect("Invalid notification configuration");
+
+        Arc::new(notification_service::Service::new(
+            config,
+            Arc::new(notifications_adapter),
+        ))
+    };
+
+    let docs_adapter = Arc::new(docs_adapter);
+    let props_adapter = Arc::new(props_adapter);
+    let upload_media_adapter = Arc::new(upload_media_adapter);
     let document_service = document_service::Service::new(
-        Arc::new(docs_adapter),
-        Arc::new(props_adapter),
+        docs_adapter.clone(),
+        props_adapter.clone(),
         Arc::new(name_sets_adapter),
+        upload_media_adapter.clone(),
+        users_adapter.clone(),
+        document_service::Notifiers {
+            webhooks: webhook_service.clone(),
+            events: event_service.clone(),
+            notifications: notification_service.clone(),
+        },
     );
 
+    // The page sub-service. Pages are rendered into the app file of the
+    // content directory unless configured otherwise.
+    let page_service = {
+        let config = &json_config["page-service"];
+        let app_file = match config["app-file"].as_str() {
+            Some(app_file) => app_file.to_owned(),
+            None => {
+                let content_dir = value_as_str(&json_config["file-service"], "content-dir");
+                format!("{}/app.html", content_dir.trim_end_matches('/'))
+            }
+        };
+
+        let robots: Vec<String> = match config.get("robots") {
+            Some(value) => serde_json::from_value(value.clone()).expect("Could not read 'robots'"),
+            None => Vec::new(),
+        };
+
+        let config = page_service::ServiceConfig::new(
+            app_file,
+            config["site-url"].as_str().unwrap_or("").to_owned(),
+            config["site-name"]
+                .as_str()
+                .unwrap_or("framebastard")
+                .to_owned(),
+            config["default-image"]
+                .as_str()
+                .unwrap_or("/static/images/crowd.jpg")
+                .to_owned(),
+            robots,
+            config["sitemap-max-urls"].as_u64().unwrap_or(50000) as u32,
+            config["feed-size"].as_u64().unwrap_or(20) as u16,
+        );
+
+        page_service::Service::new(config, docs_adapter.clone(), props_adapter.clone())
+    };
+
+    // The user sub-service
+    let user_service = user_service::Service::new(users_adapter, props_adapter);
+
     // The search sub-service
-    let search_service = search_service::Service::new(search_adapter);
+    let search_service =
+        search_service::Service::new(search_adapter, Arc::new(search_stats_adapter), docs_adapter);
 
     // The upload sub-server
     let upload_service = {
         let config = &json_config["upload-service"];
+        let profiles = match config.get("transcode-profiles") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'transcode-profiles'")
+            }
+            None => vec![upload_service::TranscodeProfile::default()],
+        };
+
+        let transcode_config = upload_service::TranscodeConfig::new(
+            config["transcode-workers"].as_u64().unwrap_or(1) as u32,
+            config["transcode-queue-size"].as_u64().unwrap_or(8) as u32,
+            profiles,
+            config["preview-position"].as_f64().unwrap_or(0.5),
+        )
+        .expect("Invalid transcode configuration");
+
+        // Uploads are not limited by quotas unless configured
+        let quotas: Vec<upload_service::UploadQuota> = match config.get("upload-quotas") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'upload-quotas'")
+            }
+            None => Vec::new(),
+        };
+
+        // Images are limited to the size of videos unless configured
+        let video_size_limit = value_as_int(&config, "video-size-limit");
+        let limits = upload_service::UploadLimits::new(
+            video_size_limit as u32,
+            config["image-size-limit"]
+                .as_u64()
+                .unwrap_or(video_size_limit) as u32,
+            quotas,
+        )
+        .expect("Invalid upload limits");
+
+        let processing_limits = match config.get("processing-limits") {
+            Some(value) => {
+                serde_json::from_value(value.clone()).expect("Could not read 'processing-limits'")
+            }
+            None => upload_service::ProcessingLimits::default(),
+        };
+
         let config = upload_service::ServiceConfig::new(
             value_as_str(&config, "publish-dir").to_owned(),
             value_as_str(&config, "video-tmp-dir").to_owned(),
-            value_as_int(&config, "video-size-limit") as u32,
+            limits,
+            transcode_config,
+            processing_limits,
+            config["media-gc-interval"].as_u64().unwrap_or(3600) as u32,
+            config["media-gc-grace-period"].as_u64().unwrap_or(86400) as u32,
         );
 
-        upload_service::Service::new(config)
+        // Jobs that were still being processed were interrupted by a restart
+        upload_jobs_adapter
+            .fail_unfinished(
+                util::get_timestamp(),
+                upload_service::FINISHED_JOB_RETENTION,
+            )
+            .await
+            .expect("Unable to update unfinished upload jobs");
+
+        upload_service::Service::new(
+            config,
+            Arc::new(upload_jobs_adapter),
+            upload_media_adapter,
+            Arc::new(upload_usage_adapter),
+        )
     };
 
     // Create the service container
@@ -154,9 +396,21 @@ async fn main() {
         document_service,
         search_service,
         upload_service,
+        page_service,
+        ActivityServices {
+            webhook_service,
+            event_service,
+            notification_service,
+            user_service,
+        },
     ));
 
     // Start hosting
     let http_config = &json_config["http-service"];
-    http_service::run(value_as_str(&http_config, "port"), service_container).await;
+    let http_config = http_service::ServiceConfig::new(
+        value_as_str(&http_config, "port").to_owned(),
+        http_config["compress-json"].as_bool().unwrap_or(false),
+    );
+
+    http_service::run(http_config, service_container).await;
 }
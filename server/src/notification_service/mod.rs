@@ -0,0 +1,10 @@
+mod service;
+mod service_config;
+
+pub use self::{
+    service::Service,
+    service_config::{
+        ServiceConfig,
+        NOTIFICATION_EVENTS,
+    },
+};
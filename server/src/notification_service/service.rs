@@ -0,0 +1,273 @@
+use super::{
+    service_config::NOTIFICATION_EVENTS,
+    ServiceConfig,
+};
+use crate::{
+    auth_service::Session,
+    database_adapters::{
+        self,
+        couchbase::Notifications,
+    },
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResponse,
+        HttpResult,
+    },
+    util,
+};
+use hyper::{
+    body::{
+        Body,
+        Buf,
+    },
+    StatusCode,
+};
+use lazy_static::lazy_static;
+use log::{
+    error,
+    warn,
+};
+use rand::{
+    distributions::Alphanumeric,
+    thread_rng,
+    Rng,
+};
+use regex::Regex;
+use serde_derive::Deserialize;
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+
+/// A request to change the events a user is notified of
+#[derive(Deserialize)]
+struct SettingsRequest {
+    events: Vec<String>,
+}
+
+/// Notifies authors when their proposals are reviewed
+pub struct Service {
+    config: Arc<ServiceConfig>,
+    adapter: Arc<Notifications>,
+}
+
+impl Service {
+    pub fn new(config: ServiceConfig, adapter: Arc<Notifications>) -> Self {
+        Self {
+            config: Arc::new(config),
+            adapter,
+        }
+    }
+
+    /// Notifies a user of an event of one of their proposals unless they
+    /// turned the event off. Notifications are stored in the background.
+    pub fn notify(&self, user_id: &str, event: &str, proposal: &Value, version: u64, data: Value) {
+        let document = &proposal["document"];
+        let notification = json!({
+            "type": "notification",
+            "id": Self::build_notification_id(),
+            "userId": user_id,
+            "event": event,
+            "target": proposal["target"],
+            "version": version,
+            "title": document["title"],
+            "docType": document["type"],
+            "data": data,
+            "created": util::get_timestamp(),
+            "read": false,
+        });
+
+        let config = self.config.clone();
+        let adapter = self.adapter.clone();
+        let user_id = user_id.to_owned();
+        let event = event.to_owned();
+        tokio::spawn(async move {
+            let settings = match adapter.get_settings(&user_id).await {
+                Ok(settings) => settings,
+                Err(_) => {
+                    warn!("Unable to get the notification settings of {}", user_id);
+                    return;
+                }
+            };
+
+            if !Self::get_events(&config, settings.as_ref()).contains(&event) {
+                return;
+            }
+
+            let id = notification["id"].as_str().unwrap_or("");
+            if adapter
+                .insert(&user_id, id, &notification, config.retention())
+                .await
+                .is_err()
+            {
+                warn!("Unable to notify {} of {} {}", user_id, event, id);
+            }
+        });
+    }
+
+    /// Handles a notification-api `GET` request
+    pub async fn handle_get_request(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        session: &Session,
+    ) -> HttpResult {
+        let user_id = Self::get_user_id(session)?;
+        match path.trim_end_matches('/') {
+            "v1/notifications" => {
+                let query_params = http_util::parse_query_string(query.unwrap_or(""));
+                let (offset, limit) = http_util::get_paging_options(&query_params)
+                    .map_err(|error| HttpError::BadRequest(error.into()))?;
+
+                let unread_only = query_params.get("unread") == Some(&"true");
+                let result = self
+                    .adapter
+                    .get_list(user_id, unread_only, offset, limit)
+                    .await;
+
+                database_adapters::build_http_result(result)
+            }
+            "v1/settings" => {
+                let settings = self
+                    .adapter
+                    .get_settings(user_id)
+                    .await
+                    .map_err(|_| HttpError::InternalError(None))?;
+
+                let events = Self::get_events(&self.config, settings.as_ref());
+                Ok(Self::build_settings_response(&events))
+            }
+            _ => Err(Self::build_invalid_path_error(path)),
+        }
+    }
+
+    /// Handles a notification-api `POST` request, which changes the events
+    /// the user is notified of
+    pub async fn handle_post_request(
+        &self,
+        path: &str,
+        body: Body,
+        session: &Session,
+    ) -> HttpResult {
+        let user_id = Self::get_user_id(session)?;
+        if path.trim_end_matches('/') != "v1/settings" {
+            return Err(Self::build_invalid_path_error(path));
+        }
+
+        let bytes = hyper::body::aggregate(body)
+            .await
+            .map(|mut buf| buf.copy_to_bytes(buf.remaining()))
+            .map_err(|error| {
+                error!("Unexpected error while collecting body: {}", error);
+                HttpError::InternalError(None)
+            })?;
+
+        let mut request: SettingsRequest = serde_json::from_slice(&bytes)
+            .map_err(|error| HttpError::BadRequest(error.to_string().into()))?;
+
+        if let Some(event) = request
+            .events
+            .iter()
+            .find(|event| !NOTIFICATION_EVENTS.contains(&event.as_str()))
+        {
+            let message = format!("Invalid notification event '{}'", event);
+            return Err(HttpError::BadRequest(message.into()));
+        }
+
+        request.events.sort();
+        request.events.dedup();
+
+        let settings = json!({
+            "type": "notification_settings",
+            "userId": user_id,
+            "events": request.events,
+            "updated": util::get_timestamp(),
+        });
+
+        self.adapter
+            .upsert_settings(user_id, &settings)
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        Ok(Self::build_settings_response(&request.events))
+    }
+
+    /// Handles a notification-api `PATCH` request, which marks a
+    /// notification or all of them as read
+    pub async fn handle_patch_request(&self, path: &str, session: &Session) -> HttpResult {
+        lazy_static! {
+            static ref PATH_REGEX: Regex =
+                Regex::new(r"^v1/+notifications(?:/+(?P<id>\w+))?/+read/*$").unwrap();
+        }
+
+        let user_id = Self::get_user_id(session)?;
+        let captures = PATH_REGEX
+            .captures(path)
+            .ok_or_else(|| Self::build_invalid_path_error(path))?;
+
+        let id = captures.name("id").map_or("", |id| id.as_str());
+        let updated = self
+            .adapter
+            .mark_read(user_id, id)
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        if !id.is_empty() && updated.is_empty() {
+            return Err(HttpError::NotFound(None));
+        }
+
+        Ok(http_util::build_json_response(
+            &json!({ "updated": updated }),
+            StatusCode::OK,
+        ))
+    }
+
+    /// Gets the events a user is notified of from their settings
+    fn get_events(config: &ServiceConfig, settings: Option<&Value>) -> Vec<String> {
+        match settings.and_then(|settings| settings["events"].as_array()) {
+            Some(events) => {
+                events
+                    .iter()
+                    .filter_map(|event| event.as_str().map(|event| event.to_owned()))
+                    .collect()
+            }
+            None => config.default_events().to_vec(),
+        }
+    }
+
+    fn get_user_id(session: &Session) -> Result<&str, HttpError> {
+        match session {
+            Session::Valid(claims) | Session::Expired(claims) => Ok(claims.sub()),
+            _ => Err(HttpError::Unauthorized(None)),
+        }
+    }
+
+    fn build_settings_response(events: &[String]) -> HttpResponse {
+        http_util::build_json_response(
+            &json!({
+                "events": events,
+                "available": NOTIFICATION_EVENTS,
+            }),
+            StatusCode::OK,
+        )
+    }
+
+    fn build_notification_id() -> String {
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .map(char::from)
+            .take(24)
+            .collect::<String>()
+    }
+
+    fn build_invalid_path_error(path: &str) -> HttpError {
+        let message = format!(
+            "Invalid notification-api path '{path}' for the specified method",
+            path = path
+        );
+
+        HttpError::BadRequest(message.into())
+    }
+}
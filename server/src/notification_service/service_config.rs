@@ -0,0 +1,37 @@
+/// The events users can be notified of
+pub const NOTIFICATION_EVENTS: [&str; 3] = ["approved", "rejected", "superseded"];
+
+pub struct ServiceConfig {
+    retention: u32,
+    default_events: Vec<String>,
+}
+
+impl ServiceConfig {
+    pub fn new(retention: u32, default_events: Vec<String>) -> Result<Self, String> {
+        if retention == 0 {
+            return Err("Notifications must be kept for at least one second".to_owned());
+        }
+
+        if let Some(event) = default_events
+            .iter()
+            .find(|event| !NOTIFICATION_EVENTS.contains(&event.as_str()))
+        {
+            return Err(format!("Invalid notification event '{}'", event));
+        }
+
+        Ok(Self {
+            retention,
+            default_events,
+        })
+    }
+
+    /// The number of seconds notifications are kept
+    pub fn retention(&self) -> u32 {
+        self.retention
+    }
+
+    /// The events users are notified of until they change their settings
+    pub fn default_events(&self) -> &[String] {
+        &self.default_events
+    }
+}
@@ -0,0 +1,164 @@
+use super::ServiceConfig;
+use crate::util;
+use serde_json::Value;
+
+/// An approved proposal along with the document it replaced, if any
+pub struct FeedEntry {
+    pub proposal: Value,
+    pub previous: Option<Value>,
+}
+
+/// Builds an Atom feed of approved proposals. The feed's path is relative to
+/// the site, e.g. `/feeds/games/sf5.atom`.
+pub fn build_feed(
+    config: &ServiceConfig,
+    path: &str,
+    title: &str,
+    entries: &[FeedEntry],
+) -> String {
+    let feed_url = format!("{}{}", config.site_url(), path);
+    let updated = entries
+        .iter()
+        .filter_map(|entry| entry.proposal["closed"].as_u64())
+        .max()
+        .unwrap_or(0);
+
+    let mut xml = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <feed xmlns=\"http://www.w3.org/2005/Atom\">\n  \
+         <id>{url}</id>\n  \
+         <title>{title}</title>\n  \
+         <updated>{updated}</updated>\n  \
+         <link rel=\"self\" href=\"{url}\"/>\n  \
+         <link rel=\"alternate\" href=\"{site_url}/\"/>\n",
+        url = util::escape_html(&feed_url),
+        title = util::escape_html(&format!("{}: {}", config.site_name(), title)),
+        updated = util::format_rfc3339(updated),
+        site_url = util::escape_html(config.site_url()),
+    );
+
+    for entry in entries {
+        let proposal = &entry.proposal;
+        let document = &proposal["document"];
+        let target = proposal["target"].as_str().unwrap_or("");
+        let (doc_path, doc_type) = match document["type"].as_str() {
+            Some("game") => ("games", "game"),
+            Some("character") => ("chars", "character"),
+            _ => ("moves", "move"),
+        };
+
+        let action = if entry.previous.is_some() {
+            "Updated"
+        } else {
+            "Added"
+        };
+
+        let entry_title = format!(
+            "{} {} {}",
+            action,
+            doc_type,
+            document["title"].as_str().unwrap_or(target)
+        );
+
+        let proposal_url = format!(
+            "{}/props/view/any/{}/{}",
+            config.site_url(),
+            proposal["id"].as_str().unwrap_or(""),
+            proposal["version"].as_str().unwrap_or("")
+        );
+
+        let doc_url = format!("{}/docs/{}/{}", config.site_url(), doc_path, target);
+        let summary = summarize_changes(document, entry.previous.as_ref());
+        xml.push_str(&format!(
+            "  <entry>\n    \
+             <id>{id}</id>\n    \
+             <title>{title}</title>\n    \
+             <updated>{updated}</updated>\n    \
+             <author><name>{author}</name></author>\n    \
+             <link rel=\"alternate\" href=\"{doc_url}\"/>\n    \
+             <link rel=\"related\" href=\"{id}\"/>\n    \
+             <summary type=\"text\">{summary}</summary>\n  \
+             </entry>\n",
+            id = util::escape_html(&proposal_url),
+            title = util::escape_html(&entry_title),
+            updated = util::format_rfc3339(proposal["closed"].as_u64().unwrap_or(0)),
+            author = util::escape_html(proposal["authorName"].as_str().unwrap_or("")),
+            doc_url = util::escape_html(&doc_url),
+            summary = util::escape_html(&summary),
+        ));
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// Describes how a proposed document differs from the one it replaced, e.g.
+/// `Start Up: 25 → 23. Added On Hit: +5.`
+pub fn summarize_changes(document: &Value, previous: Option<&Value>) -> String {
+    let attributes = get_attributes(document);
+    let previous = match previous {
+        Some(previous) => previous,
+        None => {
+            return attributes
+                .iter()
+                .map(|(title, value)| format!("{}: {}.", title, value))
+                .collect::<Vec<String>>()
+                .join(" ");
+        }
+    };
+
+    let mut changes = Vec::new();
+    let previous_title = previous["title"].as_str().unwrap_or("");
+    let title = document["title"].as_str().unwrap_or("");
+    if previous_title != title {
+        changes.push(format!("Renamed from {} to {}.", previous_title, title));
+    }
+
+    let previous_attributes = get_attributes(previous);
+    for (title, value) in &attributes {
+        match previous_attributes
+            .iter()
+            .find(|(previous, _)| previous == title)
+        {
+            Some((_, previous_value)) if previous_value != value => {
+                changes.push(format!("{}: {} → {}.", title, previous_value, value));
+            }
+            Some(_) => {}
+            None => changes.push(format!("Added {}: {}.", title, value)),
+        }
+    }
+
+    for (title, _) in &previous_attributes {
+        if !attributes.iter().any(|(current, _)| current == title) {
+            changes.push(format!("Removed {}.", title));
+        }
+    }
+
+    if previous["media"]["fileName"] != document["media"]["fileName"] {
+        changes.push("Updated media.".to_owned());
+    }
+
+    if previous["names"] != document["names"] {
+        changes.push("Updated names.".to_owned());
+    }
+
+    if changes.is_empty() {
+        "No changes to attributes.".to_owned()
+    } else {
+        changes.join(" ")
+    }
+}
+
+fn get_attributes(document: &Value) -> Vec<(&str, &str)> {
+    document["attributes"]
+        .as_array()
+        .map_or(&[][..], |attributes| attributes)
+        .iter()
+        .map(|attribute| {
+            (
+                attribute["title"].as_str().unwrap_or(""),
+                attribute["value"].as_str().unwrap_or(""),
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,10 @@
+mod feed;
+mod service;
+mod service_config;
+mod sitemap;
+mod snapshot;
+
+pub use self::{
+    service::Service,
+    service_config::ServiceConfig,
+};
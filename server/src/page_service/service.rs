@@ -0,0 +1,283 @@
+use super::{
+    feed::{
+        self,
+        FeedEntry,
+    },
+    sitemap,
+    snapshot::Snapshot,
+    ServiceConfig,
+};
+use crate::{
+    database_adapters::{
+        couchbase as couchbase_adapters,
+        AdapterError,
+    },
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResponse,
+        HttpResult,
+    },
+};
+use async_std::fs;
+use hyper::{
+    header::{
+        self,
+        HeaderValue,
+    },
+    StatusCode,
+};
+use lazy_static::lazy_static;
+use log::error;
+use regex::Regex;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// The cache policy of sitemaps and `robots.txt`
+const INDEX_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// The cache policy of feeds
+const FEED_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// Renders the pages of the client app on the server so crawlers and link
+/// previews see the content of documents
+pub struct Service {
+    config: ServiceConfig,
+    docs_adapter: Arc<couchbase_adapters::Documents>,
+    props_adapter: Arc<couchbase_adapters::Proposals>,
+}
+
+impl Service {
+    pub fn new(
+        config: ServiceConfig,
+        docs_adapter: Arc<couchbase_adapters::Documents>,
+        props_adapter: Arc<couchbase_adapters::Proposals>,
+    ) -> Self {
+        Self {
+            config,
+            docs_adapter,
+            props_adapter,
+        }
+    }
+
+    /// Handles a request for a document page, e.g. `moves/sf5.ryu.hadoken`.
+    /// Pages of unknown documents are served with a 404 status.
+    pub async fn handle_document_request(&self, path: &str) -> HttpResult {
+        lazy_static! {
+            static ref PATH_REGEX: Regex =
+                Regex::new(r"^(?P<type>games|chars|moves)/+(?P<id>[\w\-.]+)/*$").unwrap();
+        }
+
+        let captures = match PATH_REGEX.captures(path) {
+            Some(captures) => captures,
+            None => return self.handle_not_found_request().await,
+        };
+
+        let doc_type = &captures["type"];
+        let doc_id = &captures["id"];
+        let result = match doc_type {
+            "games" => self.docs_adapter.get_game(doc_id).await,
+            "chars" => self.docs_adapter.get_char(doc_id).await,
+            _ => self.docs_adapter.get_move(doc_id).await,
+        };
+
+        let doc = match result {
+            Ok(doc) => doc,
+            Err(AdapterError::DocumentNotFound) => return self.handle_not_found_request().await,
+            Err(AdapterError::InternalError) => return Err(HttpError::InternalError(None)),
+        };
+
+        let app_html = self.read_app_html().await?;
+        let snapshot = Snapshot::new(&self.config, doc_type, &doc);
+        Ok(Self::build_page_response(
+            snapshot.render(&self.config, &app_html),
+            StatusCode::OK,
+        ))
+    }
+
+    /// Serves the client app with a 404 status for pages that do not exist.
+    /// The app shows its own not found message.
+    pub async fn handle_not_found_request(&self) -> HttpResult {
+        let app_html = self.read_app_html().await?;
+        Ok(Self::build_page_response(app_html, StatusCode::NOT_FOUND))
+    }
+
+    /// Handles a request for `sitemap.xml`. Large sites get a sitemap index
+    /// instead.
+    pub async fn handle_sitemap_request(&self) -> HttpResult {
+        let count = self
+            .docs_adapter
+            .get_sitemap_count()
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        let xml = if count <= self.config.sitemap_max_urls() as u64 {
+            let docs = self
+                .docs_adapter
+                .get_sitemap_entries()
+                .await
+                .map_err(|_| HttpError::InternalError(None))?;
+
+            sitemap::build_url_set(&self.config, &docs)
+        } else {
+            let games = self
+                .docs_adapter
+                .get_sitemap_games()
+                .await
+                .map_err(|_| HttpError::InternalError(None))?;
+
+            sitemap::build_index(&self.config, &games)
+        };
+
+        Ok(Self::build_sitemap_response(xml))
+    }
+
+    /// Handles a request for one of the sitemaps of a sitemap index, either
+    /// `games.xml` or `<game id>.xml`
+    pub async fn handle_sitemaps_request(&self, path: &str) -> HttpResult {
+        lazy_static! {
+            static ref PATH_REGEX: Regex = Regex::new(r"^(?P<id>[\w\-.]+)\.xml$").unwrap();
+        }
+
+        let captures = PATH_REGEX.captures(path).ok_or(HttpError::NotFound(None))?;
+
+        let result = match &captures["id"] {
+            "games" => {
+                self.docs_adapter.get_sitemap_games().await.map(|games| {
+                    games
+                        .into_iter()
+                        .map(|mut game| {
+                            game["type"] = "game".into();
+                            game
+                        })
+                        .collect::<Vec<Value>>()
+                })
+            }
+            game_id => self.docs_adapter.get_sitemap_game_entries(game_id).await,
+        };
+
+        let docs = result.map_err(|_| HttpError::InternalError(None))?;
+        if docs.is_empty() {
+            return Err(HttpError::NotFound(None));
+        }
+
+        Ok(Self::build_sitemap_response(sitemap::build_url_set(
+            &self.config,
+            &docs,
+        )))
+    }
+
+    /// Handles a request for `robots.txt`
+    pub async fn handle_robots_request(&self) -> HttpResult {
+        let mut robots = self.config.robots().join("\n");
+        if !robots.is_empty() {
+            robots.push_str("\n\n");
+        }
+
+        robots.push_str(&format!(
+            "Sitemap: {}/sitemap.xml\n",
+            self.config.site_url()
+        ));
+
+        let mut response =
+            http_util::build_response(robots, mime::TEXT_PLAIN_UTF_8.as_ref(), StatusCode::OK);
+
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(INDEX_CACHE_CONTROL),
+        );
+
+        Ok(response)
+    }
+
+    /// Handles a request for an Atom feed of recently approved proposals,
+    /// either `all.atom` or the feed of a game or character and its children,
+    /// e.g. `chars/sf5.ryu.atom`
+    pub async fn handle_feed_request(&self, path: &str) -> HttpResult {
+        lazy_static! {
+            static ref PATH_REGEX: Regex =
+                Regex::new(r"^(?:(?P<type>games|chars)/+)?(?P<id>[\w\-.]+)\.atom$").unwrap();
+        }
+
+        let captures = PATH_REGEX.captures(path).ok_or(HttpError::NotFound(None))?;
+
+        let id = &captures["id"];
+        let (scope, title) = match captures.name("type").map(|m| m.as_str()) {
+            Some(doc_type) => {
+                let result = match doc_type {
+                    "games" => self.docs_adapter.get_game(id).await,
+                    _ => self.docs_adapter.get_char(id).await,
+                };
+
+                let doc = match result {
+                    Ok(doc) => doc,
+                    Err(AdapterError::DocumentNotFound) => return Err(HttpError::NotFound(None)),
+                    Err(AdapterError::InternalError) => return Err(HttpError::InternalError(None)),
+                };
+
+                let title = format!("Changes to {}", doc["title"].as_str().unwrap_or(id));
+                (id, title)
+            }
+            None if id == "all" => ("", "Recent changes".to_owned()),
+            None => return Err(HttpError::NotFound(None)),
+        };
+
+        let proposals = self
+            .props_adapter
+            .get_approved_feed(scope, self.config.feed_size())
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        // Compare each proposal with the one approved before it
+        let entries: Vec<FeedEntry> = proposals
+            .into_iter()
+            .map(|mut proposal| {
+                let previous = match proposal["previousDocument"].take() {
+                    Value::Null => None,
+                    previous => Some(previous),
+                };
+
+                FeedEntry { proposal, previous }
+            })
+            .collect();
+
+        let xml = feed::build_feed(&self.config, &format!("/feeds/{}", path), &title, &entries);
+        let mut response =
+            http_util::build_response(xml, "application/atom+xml; charset=utf-8", StatusCode::OK);
+
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(FEED_CACHE_CONTROL),
+        );
+
+        Ok(response)
+    }
+
+    async fn read_app_html(&self) -> Result<String, HttpError> {
+        fs::read_to_string(self.config.app_file())
+            .await
+            .map_err(|error| {
+                error!("Unable to read the app html: {}", error);
+                HttpError::InternalError(None)
+            })
+    }
+
+    fn build_sitemap_response(xml: String) -> HttpResponse {
+        let mut response = http_util::build_response(xml, "application/xml", StatusCode::OK);
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(INDEX_CACHE_CONTROL),
+        );
+
+        response
+    }
+
+    fn build_page_response(html: String, status: StatusCode) -> HttpResponse {
+        let mut response = http_util::build_response(html, mime::TEXT_HTML_UTF_8.as_ref(), status);
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+
+        response
+    }
+}
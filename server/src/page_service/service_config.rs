@@ -0,0 +1,69 @@
+pub struct ServiceConfig {
+    app_file: String,
+    site_url: String,
+    site_name: String,
+    default_image: String,
+    robots: Vec<String>,
+    sitemap_max_urls: u32,
+    feed_size: u16,
+}
+
+impl ServiceConfig {
+    pub fn new(
+        app_file: String,
+        site_url: String,
+        site_name: String,
+        default_image: String,
+        robots: Vec<String>,
+        sitemap_max_urls: u32,
+        feed_size: u16,
+    ) -> Self {
+        Self {
+            app_file,
+            site_url: site_url.trim_end_matches('/').to_owned(),
+            site_name,
+            default_image,
+            robots,
+            sitemap_max_urls,
+            feed_size,
+        }
+    }
+
+    /// The html file of the client app that pages are rendered into
+    pub fn app_file(&self) -> &str {
+        &self.app_file
+    }
+
+    /// The public url of the site without a trailing slash. Link previews
+    /// and sitemaps require absolute urls.
+    pub fn site_url(&self) -> &str {
+        &self.site_url
+    }
+
+    pub fn site_name(&self) -> &str {
+        &self.site_name
+    }
+
+    /// The path of the image shown in link previews of documents without an
+    /// image of their own
+    pub fn default_image(&self) -> &str {
+        &self.default_image
+    }
+
+    /// The lines of `robots.txt`. The location of the sitemap is added to
+    /// them.
+    pub fn robots(&self) -> &[String] {
+        &self.robots
+    }
+
+    /// The number of urls a sitemap can hold. The sitemap is split into one
+    /// per game when there are more documents.
+    pub fn sitemap_max_urls(&self) -> u32 {
+        self.sitemap_max_urls
+    }
+
+    /// The number of approved proposals listed in a feed
+    pub fn feed_size(&self) -> u16 {
+        self.feed_size
+    }
+}
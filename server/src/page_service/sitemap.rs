@@ -0,0 +1,86 @@
+use super::ServiceConfig;
+use crate::util;
+use log::warn;
+use serde_json::Value;
+
+/// Builds a sitemap of the pages of the specified documents. Each document
+/// has a type, an id and the time its latest proposal was approved.
+pub fn build_url_set(config: &ServiceConfig, docs: &[Value]) -> String {
+    if docs.len() > config.sitemap_max_urls() as usize {
+        warn!(
+            "Sitemap truncated to {} of {} documents",
+            config.sitemap_max_urls(),
+            docs.len()
+        );
+    }
+
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    for doc in docs.iter().take(config.sitemap_max_urls() as usize) {
+        let path = match doc["type"].as_str() {
+            Some("game") => "games",
+            Some("character") => "chars",
+            _ => "moves",
+        };
+
+        let url = format!(
+            "{}/docs/{}/{}",
+            config.site_url(),
+            path,
+            doc["id"].as_str().unwrap_or("")
+        );
+
+        add_entry(&mut xml, "url", &url, doc["lastModified"].as_u64());
+    }
+
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+/// Builds a sitemap index of a sitemap listing the games and a sitemap per
+/// game listing its characters and moves
+pub fn build_index(config: &ServiceConfig, games: &[Value]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+
+    let games_url = format!("{}/sitemaps/games.xml", config.site_url());
+    let last_modified = games
+        .iter()
+        .filter_map(|game| game["lastModified"].as_u64())
+        .max();
+
+    add_entry(&mut xml, "sitemap", &games_url, last_modified);
+    for game in games {
+        let url = format!(
+            "{}/sitemaps/{}.xml",
+            config.site_url(),
+            game["id"].as_str().unwrap_or("")
+        );
+
+        add_entry(&mut xml, "sitemap", &url, None);
+    }
+
+    xml.push_str("</sitemapindex>\n");
+    xml
+}
+
+fn add_entry(xml: &mut String, tag: &str, url: &str, last_modified: Option<u64>) {
+    xml.push_str(&format!(
+        "  <{}>\n    <loc>{}</loc>\n",
+        tag,
+        util::escape_html(url)
+    ));
+    if let Some(last_modified) = last_modified {
+        xml.push_str(&format!(
+            "    <lastmod>{}</lastmod>\n",
+            util::format_rfc3339(last_modified)
+        ));
+    }
+
+    xml.push_str(&format!("  </{}>\n", tag));
+}
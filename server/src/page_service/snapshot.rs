@@ -0,0 +1,230 @@
+use super::ServiceConfig;
+use crate::util::escape_html;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::Value;
+
+/// The width of the image variant shown in link previews
+const PREVIEW_IMAGE_WIDTH: u32 = 960;
+
+/// The number of attributes listed in the description of a document
+const DESCRIPTION_ATTRIBUTES: usize = 6;
+
+/// A video shown in link previews
+struct PreviewVideo {
+    url: String,
+    mime_type: String,
+    width: u64,
+    height: u64,
+}
+
+/// A static rendering of a document page for crawlers and link previews
+pub struct Snapshot {
+    title: String,
+    description: String,
+    url: String,
+    image: String,
+    video: Option<PreviewVideo>,
+    content: String,
+}
+
+impl Snapshot {
+    /// Creates the snapshot of a game, character or move document as
+    /// returned by the documents adapter
+    pub fn new(config: &ServiceConfig, doc_type: &str, doc: &Value) -> Self {
+        let doc_title = doc["title"].as_str().unwrap_or("");
+        let doc_id = doc["id"].as_str().unwrap_or("");
+
+        // Parents come first in the breadcrumbs and last in the title
+        let mut parents = Vec::new();
+        if let Some(game) = doc["game"]["id"].as_str() {
+            parents.push(("games", game, doc["game"]["title"].as_str().unwrap_or(game)));
+        }
+
+        if let Some(character) = doc["character"]["id"].as_str() {
+            parents.push((
+                "chars",
+                character,
+                doc["character"]["title"].as_str().unwrap_or(character),
+            ));
+        }
+
+        let mut title = doc_title.to_owned();
+        for (_, _, parent_title) in parents.iter().rev() {
+            title.push_str(" - ");
+            title.push_str(parent_title);
+        }
+
+        let attributes = doc["attributes"]
+            .as_array()
+            .map_or(&[][..], |attributes| attributes)
+            .iter()
+            .map(|attribute| {
+                (
+                    attribute["title"].as_str().unwrap_or(""),
+                    attribute["value"].as_str().unwrap_or(""),
+                )
+            })
+            .collect::<Vec<(&str, &str)>>();
+
+        let description = if attributes.is_empty() {
+            format!("Community frame data for {}", title)
+        } else {
+            attributes
+                .iter()
+                .take(DESCRIPTION_ATTRIBUTES)
+                .map(|(title, value)| format!("{}: {}", title, value))
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        // Render the breadcrumbs, attributes and names
+        let mut content = String::from("<nav>");
+        for (parent_type, parent_id, parent_title) in &parents {
+            content.push_str(&format!(
+                "<a href=\"/docs/{}/{}\">{}</a> / ",
+                parent_type,
+                escape_html(parent_id),
+                escape_html(parent_title)
+            ));
+        }
+
+        content.push_str(&format!("</nav><h1>{}</h1>", escape_html(doc_title)));
+        if !attributes.is_empty() {
+            content.push_str("<table>");
+            for (title, value) in &attributes {
+                content.push_str(&format!(
+                    "<tr><th>{}</th><td>{}</td></tr>",
+                    escape_html(title),
+                    escape_html(value)
+                ));
+            }
+
+            content.push_str("</table>");
+        }
+
+        let names = doc["names"]
+            .as_array()
+            .map_or(&[][..], |names| names)
+            .iter()
+            .filter_map(Value::as_str)
+            .map(escape_html)
+            .collect::<Vec<String>>();
+
+        if !names.is_empty() {
+            content.push_str(&format!("<p>Also known as: {}</p>", names.join(", ")));
+        }
+
+        let media = &doc["media"];
+        let file_name = media["fileName"].as_str();
+        let is_image = media["type"].as_str() == Some("image");
+
+        // Prefer mp4 renditions since they play on the most platforms
+        let renditions = media["renditions"]
+            .as_array()
+            .map_or(&[][..], |renditions| renditions);
+
+        let rendition = if is_image {
+            None
+        } else {
+            renditions
+                .iter()
+                .find(|rendition| rendition["type"].as_str() == Some("video/mp4"))
+                .or_else(|| renditions.first())
+        };
+
+        // Videos are previewed with the poster frame of their rendition
+        let poster = rendition.and_then(|rendition| rendition["poster"].as_str());
+        let image = match (file_name, poster) {
+            (Some(file_name), _) if is_image => {
+                format!(
+                    "{}/uploads/{}.{}.jpg",
+                    config.site_url(),
+                    file_name,
+                    PREVIEW_IMAGE_WIDTH
+                )
+            }
+            (_, Some(poster)) => format!("{}/uploads/{}", config.site_url(), poster),
+            _ => format!("{}{}", config.site_url(), config.default_image()),
+        };
+
+        let video = rendition.and_then(|rendition| {
+            Some(PreviewVideo {
+                url: format!(
+                    "{}/uploads/{}",
+                    config.site_url(),
+                    rendition["file"].as_str()?
+                ),
+                mime_type: rendition["type"].as_str()?.to_owned(),
+                width: rendition["width"].as_u64().unwrap_or(0),
+                height: rendition["height"].as_u64().unwrap_or(0),
+            })
+        });
+
+        Self {
+            title,
+            description,
+            url: format!("{}/docs/{}/{}", config.site_url(), doc_type, doc_id),
+            image,
+            video,
+            content,
+        }
+    }
+
+    /// Renders the snapshot into the html of the client app. The title and
+    /// link preview tags go in the head and the content is only shown to
+    /// clients that do not run the app.
+    pub fn render(&self, config: &ServiceConfig, app_html: &str) -> String {
+        let title = format!("{} | {}", self.title, config.site_name());
+        let mut head = format!("<title>{}</title>", escape_html(&title));
+        let mut add_meta = |attribute: &str, name: &str, content: &str| {
+            head.push_str(&format!(
+                "\n<meta {}=\"{}\" content=\"{}\">",
+                attribute,
+                name,
+                escape_html(content)
+            ));
+        };
+
+        add_meta("name", "description", &self.description);
+        add_meta("property", "og:type", "website");
+        add_meta("property", "og:site_name", config.site_name());
+        add_meta("property", "og:title", &self.title);
+        add_meta("property", "og:description", &self.description);
+        add_meta("property", "og:url", &self.url);
+        add_meta("property", "og:image", &self.image);
+        if let Some(video) = &self.video {
+            add_meta("property", "og:video", &video.url);
+            add_meta("property", "og:video:type", &video.mime_type);
+            add_meta("property", "og:video:width", &video.width.to_string());
+            add_meta("property", "og:video:height", &video.height.to_string());
+        }
+
+        add_meta("name", "twitter:card", "summary_large_image");
+        add_meta("name", "twitter:title", &self.title);
+        add_meta("name", "twitter:description", &self.description);
+        add_meta("name", "twitter:image", &self.image);
+        head.push_str(&format!(
+            "\n<link rel=\"canonical\" href=\"{}\">",
+            escape_html(&self.url)
+        ));
+
+        lazy_static! {
+            static ref TITLE_REGEX: Regex = Regex::new(r"(?is)<title>.*?</title>").unwrap();
+        }
+
+        let html = if TITLE_REGEX.is_match(app_html) {
+            TITLE_REGEX
+                .replace(app_html, regex::NoExpand(&head))
+                .into_owned()
+        } else {
+            app_html.replacen("</head>", &format!("{}\n</head>", head), 1)
+        };
+
+        let content = format!("<noscript><main>{}</main></noscript>\n", self.content);
+        match html.find("<fb-app") {
+            Some(index) => format!("{}{}{}", &html[..index], content, &html[index..]),
+            None => html.replacen("</body>", &format!("{}</body>", content), 1),
+        }
+    }
+}
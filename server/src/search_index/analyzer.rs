@@ -0,0 +1,114 @@
+use super::porter_stemmer;
+use lazy_static::lazy_static;
+use std::collections::HashSet;
+
+/// An analyzed token of a text field
+#[derive(Debug, Clone)]
+pub struct Token {
+    /// The analyzed term
+    pub term: String,
+    /// The 1 based position of the token within its text
+    pub position: usize,
+    /// The byte offset the token starts at
+    pub start: usize,
+    /// The byte offset the token ends at
+    pub end: usize,
+}
+
+/// Analyzes text the same way the `name_set-analyzer` of the couchbase FTS
+/// index does (see couchbase/n1ql/fts_index.json): a whitespace tokenizer
+/// followed by the `stop_en`, `to_lower` and `stemmer_porter` token filters.
+pub fn analyze(text: &str) -> Vec<Token> {
+    lazy_static! {
+        static ref STOP_WORDS: HashSet<&'static str> = STOP_WORDS_EN.split_whitespace().collect();
+    }
+
+    let mut tokens = Vec::new();
+    for (index, (start, end)) in split_whitespace(text).into_iter().enumerate() {
+        // Stop words are removed before lower casing so only lower case stop
+        // words are dropped.
+        let word = &text[start..end];
+        if STOP_WORDS.contains(word) {
+            continue;
+        }
+
+        tokens.push(Token {
+            term: porter_stemmer::stem(&word.to_lowercase()),
+            position: index + 1,
+            start,
+            end,
+        });
+    }
+
+    tokens
+}
+
+/// Splits text on white space returning the byte ranges of each word
+fn split_whitespace(text: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut start: Option<usize> = None;
+    for (index, character) in text.char_indices() {
+        match (character.is_whitespace(), start) {
+            (true, Some(word_start)) => {
+                ranges.push((word_start, index));
+                start = None;
+            }
+            (false, None) => start = Some(index),
+            _ => {}
+        }
+    }
+
+    if let Some(word_start) = start {
+        ranges.push((word_start, text.len()));
+    }
+
+    ranges
+}
+
+/// English stop words. See http://snowball.tartarus.org/algorithms/english/stop.txt
+const STOP_WORDS_EN: &str = "\
+    i me my myself we our ours ourselves you your yours yourself yourselves he him his himself \
+    she her hers herself it its itself they them their theirs themselves what which who whom \
+    this that these those am is are was were be been being have has had having do does did doing \
+    would should could ought i'm you're he's she's it's we're they're i've you've we've they've \
+    i'd you'd he'd she'd we'd they'd i'll you'll he'll she'll we'll they'll isn't aren't wasn't \
+    weren't hasn't haven't hadn't doesn't don't didn't won't wouldn't shan't shouldn't can't \
+    cannot couldn't mustn't let's that's who's what's here's there's when's where's why's how's \
+    a an the and but if or because as until while of at by for with about against between into \
+    through during before after above below to from up down in out on off over under again \
+    further then once here there when where why how all any both each few more most other some \
+    such no nor not only own same so than too very";
+
+#[cfg(test)]
+mod tests {
+    use super::analyze;
+
+    #[test]
+    fn drops_stop_words_but_keeps_positions() {
+        let tokens = analyze("the Spinning  Bird kick");
+        let terms: Vec<(&str, usize)> = tokens
+            .iter()
+            .map(|token| (token.term.as_str(), token.position))
+            .collect();
+
+        assert_eq!(terms, vec![("spin", 2), ("bird", 3), ("kick", 4)]);
+    }
+
+    #[test]
+    fn keeps_upper_case_stop_words() {
+        let terms: Vec<String> = analyze("The End")
+            .into_iter()
+            .map(|token| token.term)
+            .collect();
+
+        assert_eq!(terms, vec!["the", "end"]);
+    }
+
+    #[test]
+    fn records_byte_offsets() {
+        let tokens = analyze(" Hadōken\tfireball ");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!((tokens[0].start, tokens[0].end), (1, 9));
+        assert_eq!((tokens[1].start, tokens[1].end), (10, 18));
+    }
+}
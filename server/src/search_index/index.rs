@@ -0,0 +1,511 @@
+use super::analyzer;
+use serde_derive::Deserialize;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    cmp,
+    collections::{
+        BTreeMap,
+        BTreeSet,
+        HashMap,
+    },
+    sync::RwLock,
+};
+
+/// Mirrors the boosts, fuzziness and prefix length used by the
+/// search/search_all.n1ql query
+const NAMES_BOOST: f64 = 1000.0;
+const PARENT_NAMES_BOOST: f64 = 100.0;
+const FUZZY_NAMES_BOOST: f64 = 10.0;
+const FUZZY_PARENT_NAMES_BOOST: f64 = 1.0;
+const FUZZINESS: usize = 2;
+const PREFIX_LENGTH: usize = 2;
+
+/// A name set as stored in the `published` bucket
+#[derive(Deserialize, Debug)]
+pub struct NameSet {
+    /// The id of the document the name set belongs to. e.g.
+    /// `move::sf5.ryu.hadoken`
+    pub id: String,
+    pub game: Option<String>,
+    pub character: Option<String>,
+    #[serde(default)]
+    pub names: Vec<String>,
+    #[serde(rename = "parentNames", default)]
+    pub parent_names: Vec<String>,
+}
+
+/// Limits a search to the descendants of a game or character
+pub enum Scope<'a> {
+    All,
+    Game(&'a str),
+    Character(&'a str),
+}
+
+/// A document matching a search
+#[derive(Debug)]
+pub struct SearchHit {
+    /// The id of the matched document. e.g. `move::sf5.ryu.hadoken`
+    pub id: String,
+    pub score: f64,
+    /// The term locations of the match in the same format as couchbase's
+    /// `search_meta().locations`
+    pub locations: Value,
+    pub names: Vec<String>,
+    pub parent_names: Vec<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Field {
+    Names,
+    ParentNames,
+}
+
+impl Field {
+    fn name(self) -> &'static str {
+        match self {
+            Field::Names => "names",
+            Field::ParentNames => "parentNames",
+        }
+    }
+}
+
+/// An occurrence of a term within a field
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Occurrence {
+    array_position: usize,
+    position: usize,
+    start: usize,
+    end: usize,
+}
+
+/// The inverted index of a single field
+#[derive(Default)]
+struct FieldIndex {
+    /// term -> document id -> occurrences
+    postings: BTreeMap<String, HashMap<String, Vec<Occurrence>>>,
+    /// document id -> number of tokens in the field
+    lengths: HashMap<String, usize>,
+}
+
+impl FieldIndex {
+    fn insert(&mut self, id: &str, values: &[String]) {
+        let mut length = 0;
+        for (array_position, value) in values.iter().enumerate() {
+            for token in analyzer::analyze(value) {
+                length += 1;
+                self.postings
+                    .entry(token.term)
+                    .or_default()
+                    .entry(id.to_owned())
+                    .or_default()
+                    .push(Occurrence {
+                        array_position,
+                        position: token.position,
+                        start: token.start,
+                        end: token.end,
+                    });
+            }
+        }
+
+        self.lengths.insert(id.to_owned(), length);
+    }
+
+    fn remove(&mut self, id: &str, values: &[String]) {
+        for value in values {
+            for token in analyzer::analyze(value) {
+                let now_empty = self
+                    .postings
+                    .get_mut(&token.term)
+                    .map(|documents| {
+                        documents.remove(id);
+                        documents.is_empty()
+                    })
+                    .unwrap_or(false);
+
+                if now_empty {
+                    self.postings.remove(&token.term);
+                }
+            }
+        }
+
+        self.lengths.remove(id);
+    }
+
+    /// Gets the indexed terms within the maximum edit distance of a term that
+    /// share its prefix
+    fn fuzzy_terms(&self, term: &str, max_distance: usize) -> Vec<(&str, usize)> {
+        let prefix: String = term.chars().take(PREFIX_LENGTH).collect();
+        self.postings
+            .range(prefix.clone()..)
+            .map(|(indexed, _)| indexed.as_str())
+            .take_while(|indexed| indexed.starts_with(&prefix))
+            .filter_map(|indexed| {
+                edit_distance(term, indexed, max_distance).map(|distance| (indexed, distance))
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct IndexData {
+    name_sets: HashMap<String, NameSet>,
+    names: FieldIndex,
+    parent_names: FieldIndex,
+}
+
+impl IndexData {
+    fn insert(&mut self, name_set: NameSet) {
+        self.remove(&name_set.id);
+        self.names.insert(&name_set.id, &name_set.names);
+        self.parent_names
+            .insert(&name_set.id, &name_set.parent_names);
+
+        self.name_sets.insert(name_set.id.clone(), name_set);
+    }
+
+    fn remove(&mut self, id: &str) {
+        if let Some(name_set) = self.name_sets.remove(id) {
+            self.names.remove(id, &name_set.names);
+            self.parent_names.remove(id, &name_set.parent_names);
+        }
+    }
+
+    fn field(&self, field: Field) -> &FieldIndex {
+        match field {
+            Field::Names => &self.names,
+            Field::ParentNames => &self.parent_names,
+        }
+    }
+}
+
+/// An in-process full text index over name sets
+pub struct SearchIndex {
+    data: RwLock<IndexData>,
+}
+
+impl SearchIndex {
+    /// Creates a new empty index
+    pub fn new() -> Self {
+        Self {
+            data: RwLock::new(IndexData::default()),
+        }
+    }
+
+    /// Replaces the content of the index with the specified name sets
+    pub fn rebuild(&self, name_sets: Vec<NameSet>) {
+        let mut data = IndexData::default();
+        for name_set in name_sets {
+            data.insert(name_set);
+        }
+
+        *self.data.write().unwrap() = data;
+    }
+
+    /// Adds or replaces the specified name sets
+    pub fn upsert(&self, name_sets: Vec<NameSet>) {
+        let mut data = self.data.write().unwrap();
+        for name_set in name_sets {
+            data.insert(name_set);
+        }
+    }
+
+    /// Gets the number of indexed name sets
+    pub fn document_count(&self) -> usize {
+        self.data.read().unwrap().name_sets.len()
+    }
+
+    /// Gets the distinct names of all indexed name sets
+    pub fn names(&self) -> Vec<String> {
+        let data = self.data.read().unwrap();
+        let names: BTreeSet<&String> = data
+            .name_sets
+            .values()
+            .flat_map(|name_set| name_set.names.iter())
+            .collect();
+
+        names.into_iter().cloned().collect()
+    }
+
+    /// Searches the index returning up to `limit` hits ordered by score
+    pub fn search(&self, search_term: &str, scope: Scope, limit: usize) -> Vec<SearchHit> {
+        let data = self.data.read().unwrap();
+        let query_tokens = analyzer::analyze(search_term);
+        let document_count = cmp::max(data.name_sets.len(), 1) as f64;
+
+        // Clauses mirror the disjuncts of the couchbase FTS query
+        let clauses = [
+            (Field::Names, NAMES_BOOST, 0),
+            (Field::ParentNames, PARENT_NAMES_BOOST, 0),
+            (Field::Names, FUZZY_NAMES_BOOST, FUZZINESS),
+            (Field::ParentNames, FUZZY_PARENT_NAMES_BOOST, FUZZINESS),
+        ];
+
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        let mut locations: HashMap<&str, BTreeMap<(Field, &str), BTreeSet<Occurrence>>> =
+            HashMap::new();
+
+        for (field, boost, fuzziness) in clauses.iter() {
+            let field_index = data.field(*field);
+            for query_token in &query_tokens {
+                let terms = if *fuzziness > 0 {
+                    field_index.fuzzy_terms(&query_token.term, *fuzziness)
+                } else if field_index.postings.contains_key(&query_token.term) {
+                    vec![(query_token.term.as_str(), 0)]
+                } else {
+                    Vec::new()
+                };
+
+                for (term, distance) in terms {
+                    let (indexed_term, documents) =
+                        field_index.postings.get_key_value(term).unwrap();
+
+                    let idf = 1.0 + (document_count / (documents.len() as f64 + 1.0)).ln();
+                    for (id, occurrences) in documents {
+                        let name_set = &data.name_sets[id];
+                        let in_scope = match scope {
+                            Scope::All => true,
+                            Scope::Game(game) => name_set.game.as_deref() == Some(game),
+                            Scope::Character(character) => {
+                                name_set.character.as_deref() == Some(character)
+                            }
+                        };
+
+                        if !in_scope {
+                            continue;
+                        }
+
+                        let length = cmp::max(field_index.lengths[id], 1) as f64;
+                        let score = boost * (occurrences.len() as f64).sqrt() * idf
+                            / length.sqrt()
+                            / (1 + distance) as f64;
+
+                        *scores.entry(id.as_str()).or_insert(0.0) += score;
+                        locations
+                            .entry(id.as_str())
+                            .or_default()
+                            .entry((*field, indexed_term.as_str()))
+                            .or_default()
+                            .extend(occurrences.iter().cloned());
+                    }
+                }
+            }
+        }
+
+        let mut hits: Vec<(&str, f64)> = scores.into_iter().collect();
+        hits.sort_by(|(_, left), (_, right)| {
+            right.partial_cmp(left).unwrap_or(cmp::Ordering::Equal)
+        });
+        hits.truncate(limit);
+
+        hits.into_iter()
+            .map(|(id, score)| {
+                let name_set = &data.name_sets[id];
+                SearchHit {
+                    id: id.to_owned(),
+                    score,
+                    locations: Self::build_locations(locations.remove(id).unwrap_or_default()),
+                    names: name_set.names.clone(),
+                    parent_names: name_set.parent_names.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Builds term locations in the format returned by couchbase FTS
+    fn build_locations(matches: BTreeMap<(Field, &str), BTreeSet<Occurrence>>) -> Value {
+        let mut locations = json!({});
+        for ((field, term), occurrences) in matches {
+            let occurrences: Vec<Value> = occurrences
+                .into_iter()
+                .map(|occurrence| {
+                    json!({
+                        "pos": occurrence.position,
+                        "start": occurrence.start,
+                        "end": occurrence.end,
+                        "array_positions": [occurrence.array_position],
+                    })
+                })
+                .collect();
+
+            locations[field.name()][term] = Value::Array(occurrences);
+        }
+
+        locations
+    }
+}
+
+/// Computes the Levenshtein distance between two terms returning `None` if
+/// it exceeds the maximum distance
+pub fn edit_distance(left: &str, right: &str, max_distance: usize) -> Option<usize> {
+    let left: Vec<char> = left.chars().collect();
+    let right: Vec<char> = right.chars().collect();
+    let length_difference = cmp::max(left.len(), right.len()) - cmp::min(left.len(), right.len());
+    if length_difference > max_distance {
+        return None;
+    }
+
+    let mut previous: Vec<usize> = (0..=right.len()).collect();
+    let mut current = vec![0; right.len() + 1];
+    for (i, left_char) in left.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, right_char) in right.iter().enumerate() {
+            let substitution = previous[j] + if left_char == right_char { 0 } else { 1 };
+            current[j + 1] = cmp::min(substitution, cmp::min(previous[j + 1], current[j]) + 1);
+        }
+
+        // The distance can only grow from here
+        if current.iter().all(|distance| *distance > max_distance) {
+            return None;
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    let distance = previous[right.len()];
+    if distance <= max_distance {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name_set(id: &str, game: &str, character: Option<&str>, names: &[&str]) -> NameSet {
+        NameSet {
+            id: id.to_owned(),
+            game: Some(game.to_owned()),
+            character: character.map(|character| character.to_owned()),
+            names: names.iter().map(|name| name.to_string()).collect(),
+            parent_names: Vec::new(),
+        }
+    }
+
+    fn build_index() -> SearchIndex {
+        let index = SearchIndex::new();
+        index.rebuild(vec![
+            name_set(
+                "move::sf5.ryu.hadoken",
+                "sf5",
+                Some("sf5.ryu"),
+                &["Hadoken"],
+            ),
+            name_set(
+                "move::sf5.ken.hadoken",
+                "sf5",
+                Some("sf5.ken"),
+                &["Hadoken"],
+            ),
+            name_set(
+                "move::sf4.ryu.hadoken",
+                "sf4",
+                Some("sf4.ryu"),
+                &["Hadoken"],
+            ),
+            name_set(
+                "move::sf5.ryu.shoryuken",
+                "sf5",
+                Some("sf5.ryu"),
+                &["Shoryuken", "DP"],
+            ),
+        ]);
+
+        index
+    }
+
+    fn hit_ids(hits: &[SearchHit]) -> Vec<&str> {
+        let mut ids: Vec<&str> = hits.iter().map(|hit| hit.id.as_str()).collect();
+        ids.sort_unstable();
+        ids
+    }
+
+    #[test]
+    fn computes_edit_distances() {
+        assert_eq!(edit_distance("hadoken", "hadoken", 2), Some(0));
+        assert_eq!(edit_distance("hadoken", "hadokn", 2), Some(1));
+        assert_eq!(edit_distance("hadoken", "hadukan", 2), Some(2));
+        assert_eq!(edit_distance("kitten", "sitting", 3), Some(3));
+        assert_eq!(edit_distance("", "abc", 3), Some(3));
+    }
+
+    #[test]
+    fn limits_edit_distances() {
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+        assert_eq!(edit_distance("hadoken", "hado", 2), None);
+        assert_eq!(edit_distance("abcdef", "uvwxyz", 2), None);
+    }
+
+    #[test]
+    fn matches_fuzzy_terms_sharing_the_prefix() {
+        let mut field_index = FieldIndex::default();
+        field_index.insert(
+            "move::sf5.ryu.hadoken",
+            &["hadoken hadouken xadoken".to_owned()],
+        );
+
+        let mut terms = field_index.fuzzy_terms("hadokn", FUZZINESS);
+        terms.sort_unstable();
+
+        // `xadoken` is within the edit distance but does not share the prefix
+        assert_eq!(terms, vec![("hadoken", 1), ("hadouken", 2)]);
+    }
+
+    #[test]
+    fn filters_hits_by_scope() {
+        let index = build_index();
+
+        let hits = index.search("hadoken", Scope::All, 10);
+        assert_eq!(
+            hit_ids(&hits),
+            vec![
+                "move::sf4.ryu.hadoken",
+                "move::sf5.ken.hadoken",
+                "move::sf5.ryu.hadoken",
+            ]
+        );
+
+        let hits = index.search("hadoken", Scope::Game("sf5"), 10);
+        assert_eq!(
+            hit_ids(&hits),
+            vec!["move::sf5.ken.hadoken", "move::sf5.ryu.hadoken"]
+        );
+
+        let hits = index.search("hadoken", Scope::Character("sf5.ryu"), 10);
+        assert_eq!(hit_ids(&hits), vec!["move::sf5.ryu.hadoken"]);
+    }
+
+    #[test]
+    fn ranks_exact_matches_before_fuzzy_ones() {
+        let index = build_index();
+        let hits = index.search("shoryuken", Scope::Game("sf5"), 10);
+        assert_eq!(hit_ids(&hits), vec!["move::sf5.ryu.shoryuken"]);
+
+        let exact = hits[0].score;
+        let fuzzy = index.search("shoryukan", Scope::Game("sf5"), 10);
+        assert_eq!(hit_ids(&fuzzy), vec!["move::sf5.ryu.shoryuken"]);
+        assert!(fuzzy[0].score < exact);
+    }
+
+    #[test]
+    fn removes_replaced_name_sets() {
+        let index = build_index();
+        index.upsert(vec![name_set(
+            "move::sf5.ryu.shoryuken",
+            "sf5",
+            Some("sf5.ryu"),
+            &["Rising Uppercut"],
+        )]);
+
+        assert!(index.search("shoryuken", Scope::All, 10).is_empty());
+        assert_eq!(
+            hit_ids(&index.search("uppercut", Scope::All, 10)),
+            vec!["move::sf5.ryu.shoryuken"]
+        );
+        assert_eq!(index.document_count(), 4);
+    }
+}
@@ -0,0 +1,13 @@
+mod analyzer;
+mod index;
+mod porter_stemmer;
+
+pub use self::{
+    analyzer::analyze,
+    index::{
+        edit_distance,
+        NameSet,
+        Scope,
+        SearchIndex,
+    },
+};
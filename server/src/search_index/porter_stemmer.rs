@@ -0,0 +1,390 @@
+/// Stems a lower case word using the Porter stemming algorithm.
+/// See https://tartarus.org/martin/PorterStemmer/def.txt for reference.
+pub fn stem(word: &str) -> String {
+    let b: Vec<char> = word.chars().collect();
+
+    // Words of one or two letters are left alone
+    if b.len() <= 2 {
+        return word.to_owned();
+    }
+
+    let mut stemmer = Stemmer {
+        k: b.len() as isize - 1,
+        j: 0,
+        b,
+    };
+
+    stemmer.step1ab();
+    if stemmer.k > 0 {
+        stemmer.step1c();
+        stemmer.step2();
+        stemmer.step3();
+        stemmer.step4();
+        stemmer.step5();
+    }
+
+    stemmer.b[..=stemmer.k as usize].iter().collect()
+}
+
+/// The working state of the stemmer. `b[0..=k]` is the word being stemmed
+/// and `j` marks the end of the stem once a suffix has been matched.
+struct Stemmer {
+    b: Vec<char>,
+    k: isize,
+    j: isize,
+}
+
+impl Stemmer {
+    fn at(&self, i: isize) -> char {
+        self.b[i as usize]
+    }
+
+    /// Is `b[i]` a consonant
+    fn cons(&self, i: isize) -> bool {
+        match self.at(i) {
+            'a' | 'e' | 'i' | 'o' | 'u' => false,
+            'y' => i == 0 || !self.cons(i - 1),
+            _ => true,
+        }
+    }
+
+    /// Measures the number of vowel-consonant sequences in `b[0..=j]`
+    fn m(&self) -> usize {
+        let mut n = 0;
+        let mut i = 0;
+
+        // Skip the leading consonants
+        loop {
+            if i > self.j {
+                return n;
+            }
+
+            if !self.cons(i) {
+                break;
+            }
+
+            i += 1;
+        }
+
+        i += 1;
+        loop {
+            // Skip vowels
+            loop {
+                if i > self.j {
+                    return n;
+                }
+
+                if self.cons(i) {
+                    break;
+                }
+
+                i += 1;
+            }
+
+            i += 1;
+            n += 1;
+
+            // Skip consonants
+            loop {
+                if i > self.j {
+                    return n;
+                }
+
+                if !self.cons(i) {
+                    break;
+                }
+
+                i += 1;
+            }
+
+            i += 1;
+        }
+    }
+
+    /// Does `b[0..=j]` contain a vowel
+    fn vowel_in_stem(&self) -> bool {
+        (0..=self.j).any(|i| !self.cons(i))
+    }
+
+    /// Is `b[i - 1..=i]` a double consonant
+    fn double_c(&self, i: isize) -> bool {
+        i >= 1 && self.at(i) == self.at(i - 1) && self.cons(i)
+    }
+
+    /// Is `b[i - 2..=i]` a consonant-vowel-consonant sequence where the last
+    /// consonant is not w, x or y
+    fn cvc(&self, i: isize) -> bool {
+        if i < 2 || !self.cons(i) || self.cons(i - 1) || !self.cons(i - 2) {
+            return false;
+        }
+
+        !matches!(self.at(i), 'w' | 'x' | 'y')
+    }
+
+    /// Does `b[0..=k]` end with the specified suffix. Sets `j` to the end of
+    /// the remaining stem if so.
+    fn ends(&mut self, suffix: &str) -> bool {
+        let suffix: Vec<char> = suffix.chars().collect();
+        let length = suffix.len() as isize;
+        if length > self.k + 1 {
+            return false;
+        }
+
+        let start = (self.k + 1 - length) as usize;
+        if self.b[start..=self.k as usize] != suffix[..] {
+            return false;
+        }
+
+        self.j = self.k - length;
+        true
+    }
+
+    /// Sets `b[j + 1..=k]` to the specified string
+    fn set_to(&mut self, value: &str) {
+        self.b.truncate((self.j + 1) as usize);
+        self.b.extend(value.chars());
+        self.k = self.b.len() as isize - 1;
+    }
+
+    /// Sets `b[j + 1..=k]` to the specified string if the stem measure is
+    /// positive
+    fn r(&mut self, value: &str) {
+        if self.m() > 0 {
+            self.set_to(value);
+        }
+    }
+
+    /// Truncates the word to `b[0..=k]`
+    fn truncate_to(&mut self, k: isize) {
+        self.k = k;
+        self.b.truncate((k + 1) as usize);
+    }
+
+    /// Removes plurals and -ed or -ing endings
+    fn step1ab(&mut self) {
+        if self.at(self.k) == 's' {
+            if self.ends("sses") {
+                self.truncate_to(self.k - 2);
+            } else if self.ends("ies") {
+                self.set_to("i");
+            } else if self.at(self.k - 1) != 's' {
+                self.truncate_to(self.k - 1);
+            }
+        }
+
+        if self.ends("eed") {
+            if self.m() > 0 {
+                self.truncate_to(self.k - 1);
+            }
+        } else if (self.ends("ed") || self.ends("ing")) && self.vowel_in_stem() {
+            self.truncate_to(self.j);
+            if self.ends("at") {
+                self.set_to("ate");
+            } else if self.ends("bl") {
+                self.set_to("ble");
+            } else if self.ends("iz") {
+                self.set_to("ize");
+            } else if self.double_c(self.k) {
+                if !matches!(self.at(self.k), 'l' | 's' | 'z') {
+                    self.truncate_to(self.k - 1);
+                }
+            } else {
+                self.j = self.k;
+                if self.m() == 1 && self.cvc(self.k) {
+                    self.set_to("e");
+                }
+            }
+        }
+    }
+
+    /// Turns a terminal y to i when there is another vowel in the stem
+    fn step1c(&mut self) {
+        if self.ends("y") && self.vowel_in_stem() {
+            self.b[self.k as usize] = 'i';
+        }
+    }
+
+    /// Maps double suffixes to single ones
+    fn step2(&mut self) {
+        self.replace_suffix(&[
+            ("ational", "ate"),
+            ("tional", "tion"),
+            ("enci", "ence"),
+            ("anci", "ance"),
+            ("izer", "ize"),
+            ("abli", "able"),
+            ("alli", "al"),
+            ("entli", "ent"),
+            ("eli", "e"),
+            ("ousli", "ous"),
+            ("ization", "ize"),
+            ("ation", "ate"),
+            ("ator", "ate"),
+            ("alism", "al"),
+            ("iveness", "ive"),
+            ("fulness", "ful"),
+            ("ousness", "ous"),
+            ("aliti", "al"),
+            ("iviti", "ive"),
+            ("biliti", "ble"),
+        ]);
+    }
+
+    /// Deals with -ic-, -full, -ness etc.
+    fn step3(&mut self) {
+        self.replace_suffix(&[
+            ("icate", "ic"),
+            ("ative", ""),
+            ("alize", "al"),
+            ("iciti", "ic"),
+            ("ical", "ic"),
+            ("ful", ""),
+            ("ness", ""),
+        ]);
+    }
+
+    /// Removes -ant, -ence etc. in the context of <c>vcvc<v>
+    fn step4(&mut self) {
+        // Suffixes sharing an ending are listed longest first
+        const SUFFIXES: [&str; 19] = [
+            "al", "ance", "ence", "er", "ic", "able", "ible", "ant", "ement", "ment", "ent", "ion",
+            "ou", "ism", "ate", "iti", "ous", "ive", "ize",
+        ];
+
+        for suffix in SUFFIXES.iter() {
+            if !self.ends(suffix) {
+                continue;
+            }
+
+            // -ion is only removed after an s or t
+            if *suffix == "ion" && (self.j < 0 || !matches!(self.at(self.j), 's' | 't')) {
+                continue;
+            }
+
+            if self.m() > 1 {
+                self.truncate_to(self.j);
+            }
+
+            return;
+        }
+    }
+
+    /// Removes a final -e and changes -ll to -l if the measure is large enough
+    fn step5(&mut self) {
+        self.j = self.k;
+        if self.at(self.k) == 'e' {
+            let measure = self.m();
+            if measure > 1 || (measure == 1 && !self.cvc(self.k - 1)) {
+                self.truncate_to(self.k - 1);
+            }
+        }
+
+        if self.at(self.k) == 'l' && self.double_c(self.k) && self.m() > 1 {
+            self.truncate_to(self.k - 1);
+        }
+    }
+
+    /// Replaces the first matching suffix. Suffixes sharing an ending must be
+    /// listed longest first.
+    fn replace_suffix(&mut self, suffixes: &[(&str, &str)]) {
+        for (suffix, replacement) in suffixes {
+            if self.ends(suffix) {
+                self.r(replacement);
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::stem;
+
+    #[test]
+    fn stems_reference_vocabulary() {
+        // Taken from the sample vocabulary and output of the reference
+        // implementation
+        let vectors = [
+            ("caresses", "caress"),
+            ("ponies", "poni"),
+            ("ties", "ti"),
+            ("caress", "caress"),
+            ("cats", "cat"),
+            ("feed", "feed"),
+            ("agreed", "agre"),
+            ("plastered", "plaster"),
+            ("bled", "bled"),
+            ("motoring", "motor"),
+            ("sing", "sing"),
+            ("conflated", "conflat"),
+            ("troubled", "troubl"),
+            ("sized", "size"),
+            ("hopping", "hop"),
+            ("tanned", "tan"),
+            ("falling", "fall"),
+            ("hissing", "hiss"),
+            ("fizzed", "fizz"),
+            ("failing", "fail"),
+            ("filing", "file"),
+            ("happy", "happi"),
+            ("sky", "sky"),
+            ("relational", "relat"),
+            ("conditional", "condit"),
+            ("rational", "ration"),
+            ("digitizer", "digit"),
+            ("vietnamization", "vietnam"),
+            ("predication", "predic"),
+            ("operator", "oper"),
+            ("feudalism", "feudal"),
+            ("decisiveness", "decis"),
+            ("hopefulness", "hope"),
+            ("callousness", "callous"),
+            ("triplicate", "triplic"),
+            ("formative", "form"),
+            ("formalize", "formal"),
+            ("electrical", "electr"),
+            ("goodness", "good"),
+            ("revival", "reviv"),
+            ("allowance", "allow"),
+            ("inference", "infer"),
+            ("airliner", "airlin"),
+            ("gyroscopic", "gyroscop"),
+            ("adjustable", "adjust"),
+            ("defensible", "defens"),
+            ("irritant", "irrit"),
+            ("replacement", "replac"),
+            ("dependent", "depend"),
+            ("adoption", "adopt"),
+            ("communism", "commun"),
+            ("activate", "activ"),
+            ("homologous", "homolog"),
+            ("effective", "effect"),
+            ("bowdlerize", "bowdler"),
+            ("probate", "probat"),
+            ("rate", "rate"),
+            ("cease", "ceas"),
+            ("controlling", "control"),
+            ("roll", "roll"),
+            ("generalizations", "gener"),
+            ("oscillators", "oscil"),
+        ];
+
+        for (word, expected) in vectors.iter() {
+            assert_eq!(stem(word), *expected, "stem of '{}'", word);
+        }
+    }
+
+    #[test]
+    fn leaves_short_words_alone() {
+        assert_eq!(stem("is"), "is");
+        assert_eq!(stem("a"), "a");
+        assert_eq!(stem(""), "");
+    }
+
+    #[test]
+    fn stems_fighting_game_terms() {
+        assert_eq!(stem("kicks"), "kick");
+        assert_eq!(stem("punches"), "punch");
+        assert_eq!(stem("uppercut"), "uppercut");
+    }
+}
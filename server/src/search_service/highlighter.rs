@@ -0,0 +1,65 @@
+use crate::util::escape_html;
+use serde_json::Value;
+
+/// Builds html fragments for the names matched by a search result. Matched
+/// terms are wrapped in `<mark>` tags and all other text is escaped.
+/// `locations` is in the format of couchbase's `search_meta().locations` for
+/// a single field.
+pub fn highlight_names(names: &[String], locations: &Value) -> Vec<String> {
+    // Collect the matched byte ranges of each name
+    let mut ranges: Vec<Vec<(usize, usize)>> = vec![Vec::new(); names.len()];
+    let occurrences = locations
+        .as_object()
+        .into_iter()
+        .flat_map(|terms| terms.values())
+        .filter_map(|occurrences| occurrences.as_array())
+        .flatten();
+
+    for occurrence in occurrences {
+        let start = occurrence["start"].as_u64();
+        let end = occurrence["end"].as_u64();
+        let positions = occurrence["array_positions"].as_array();
+        if let (Some(start), Some(end), Some(positions)) = (start, end, positions) {
+            for position in positions.iter().filter_map(|position| position.as_u64()) {
+                if let Some(name_ranges) = ranges.get_mut(position as usize) {
+                    name_ranges.push((start as usize, end as usize));
+                }
+            }
+        }
+    }
+
+    names
+        .iter()
+        .zip(ranges.into_iter())
+        .filter(|(_, name_ranges)| !name_ranges.is_empty())
+        .map(|(name, name_ranges)| mark_name(name, name_ranges))
+        .collect()
+}
+
+/// Marks the specified byte ranges of a name
+fn mark_name(name: &str, mut ranges: Vec<(usize, usize)>) -> String {
+    ranges.sort();
+
+    let mut result = String::with_capacity(name.len() + ranges.len() * 13);
+    let mut current = 0;
+    for (start, end) in ranges {
+        // Skip overlapping or invalid ranges
+        let start = start.max(current);
+        if start >= end
+            || end > name.len()
+            || !name.is_char_boundary(start)
+            || !name.is_char_boundary(end)
+        {
+            continue;
+        }
+
+        result.push_str(&escape_html(&name[current..start]));
+        result.push_str("<mark>");
+        result.push_str(&escape_html(&name[start..end]));
+        result.push_str("</mark>");
+        current = end;
+    }
+
+    result.push_str(&escape_html(&name[current..]));
+    result
+}
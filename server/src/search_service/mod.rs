@@ -1,3 +1,5 @@
+mod highlighter;
 mod service;
+mod suggester;
 
 pub use self::service::Service;
@@ -1,43 +1,117 @@
+use super::{
+    highlighter,
+    suggester::Suggester,
+};
 use crate::{
+    auth_service::Session,
     database_adapters,
-    database_adapters::couchbase as couchbase_adapters,
+    database_adapters::{
+        couchbase::{
+            self as couchbase_adapters,
+            AttributeFilter,
+            SearchQuery,
+            SearchScope,
+        },
+        AdapterError,
+    },
     http_service::{
         util as http_util,
         HttpError,
         HttpResult,
     },
+    search_index,
+    util,
+};
+use hyper::{
+    body::Buf,
+    Body,
 };
 use lazy_static::lazy_static;
+use log::{
+    error,
+    warn,
+};
 use regex::Regex;
-use std::str;
+use serde_derive::Deserialize;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    cmp,
+    str,
+    sync::Arc,
+};
+
+/// The number of seconds in a day
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// The maximum length of a document id in a reported search click
+const MAX_ID_LENGTH: usize = 64;
+
+/// A clicked search result reported by the client
+#[derive(Deserialize)]
+struct SearchClick {
+    query: String,
+    game: Option<String>,
+    #[serde(rename = "char")]
+    character: Option<String>,
+    #[serde(rename = "type")]
+    doc_type: String,
+    id: String,
+}
 
 pub struct Service {
     adapter: couchbase_adapters::Search,
+    stats_adapter: Arc<couchbase_adapters::SearchStats>,
+    docs_adapter: Arc<couchbase_adapters::Documents>,
+    suggester: Suggester,
 }
 
 impl Service {
     /// Creates a new instance of the search service
-    pub fn new(adapter: couchbase_adapters::Search) -> Self {
-        Self { adapter }
+    pub fn new(
+        adapter: couchbase_adapters::Search,
+        stats_adapter: Arc<couchbase_adapters::SearchStats>,
+        docs_adapter: Arc<couchbase_adapters::Documents>,
+    ) -> Self {
+        Self {
+            adapter,
+            stats_adapter,
+            docs_adapter,
+            suggester: Suggester::new(),
+        }
     }
 
     /// Handles any get requests routed to the search service
-    pub async fn handle_get_request(&self, path: &str, query: Option<&str>) -> HttpResult {
-        // Pull out the 'version' from the path.
-        lazy_static! {
-            static ref PATH_REGEX: Regex = Regex::new(r"^v(?P<ver>\d+)$").unwrap();
+    pub async fn handle_get_request(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        session: &Session,
+    ) -> HttpResult {
+        match Self::extract_resource(path)? {
+            None => self.handle_search(query).await,
+            Some("stats") => self.handle_get_stats(query, session).await,
+            Some(resource) => Err(Self::build_invalid_resource_error(resource)),
         }
+    }
 
-        let captures = PATH_REGEX
-            .captures(path)
-            .ok_or_else(|| HttpError::BadRequest("Invalid search-api path format".into()))?;
-
-        // Validate the version (currently only version #1)
-        if !captures["ver"].eq("1") {
-            let message = "Unsupported search-api version".into();
-            return Err(HttpError::BadRequest(message));
+    /// Handles any post requests routed to the search service
+    pub async fn handle_post_request(
+        &self,
+        path: &str,
+        body: Body,
+        session: &Session,
+    ) -> HttpResult {
+        match Self::extract_resource(path)? {
+            Some("clicks") => self.handle_post_click(body, session).await,
+            resource => Err(Self::build_invalid_resource_error(resource.unwrap_or(""))),
         }
+    }
 
+    /// Handles a search request
+    async fn handle_search(&self, query: Option<&str>) -> HttpResult {
         // Get the query params
         let query_params = http_util::parse_query_string(query.unwrap_or(""));
 
@@ -52,20 +126,316 @@ impl Service {
         let (offset, limit) = http_util::get_paging_options(&query_params)
             .map_err(|message| HttpError::BadRequest(message.into()))?;
 
-        let result = match (game, character) {
-            (Some(target), None) => {
-                self.adapter
-                    .search_game(target, search_term, offset, limit)
-                    .await
+        let doc_type = match query_params.get("type").map(|v| *v) {
+            Some(value @ "game") | Some(value @ "character") | Some(value @ "move") => Some(value),
+            None => None,
+            Some(value) => {
+                let message = format!("Invalid document type '{value}'", value = value);
+                return Err(HttpError::BadRequest(message.into()));
             }
-            (None, Some(target)) => {
-                self.adapter
-                    .search_character(target, search_term, offset, limit)
-                    .await
+        };
+
+        let filters = query_params
+            .get("filter")
+            .map_or(Ok(Vec::new()), |value| Self::parse_attribute_filters(value))
+            .map_err(|message| HttpError::BadRequest(message.into()))?;
+
+        let search_query = SearchQuery {
+            search_term,
+            doc_type,
+            filters: &filters,
+            offset,
+            limit,
+        };
+
+        let result = match (game, character) {
+            (Some(target), None) => self.adapter.search_game(target, &search_query).await,
+            (None, Some(target)) => self.adapter.search_character(target, &search_query).await,
+            _ => self.adapter.search_all(&search_query).await,
+        };
+
+        let result = match result {
+            Ok(content) => {
+                // Only the first page of results counts as a search
+                if offset == 0 {
+                    let result_count = content["totalCount"].as_u64().unwrap_or(0);
+                    self.record_search(search_term, game, character, result_count);
+                }
+
+                Ok(self
+                    .add_highlights_and_suggestions(content, search_term)
+                    .await)
             }
-            _ => self.adapter.search_all(search_term, offset, limit).await,
+            Err(error) => Err(error),
         };
 
         database_adapters::build_http_result(result)
     }
+
+    /// Handles a request for search statistics. Only available to admins.
+    async fn handle_get_stats(&self, query: Option<&str>, session: &Session) -> HttpResult {
+        const DEFAULT_DAYS: u64 = 7;
+        const MAX_DAYS: u64 = 365;
+        const DEFAULT_LIMIT: u16 = 20;
+        const MAX_LIMIT: u16 = 100;
+
+        match session {
+            Session::Valid(claims) | Session::Expired(claims) if claims.is_admin() => {}
+            _ => return Err(HttpError::Unauthorized(None)),
+        }
+
+        let query_params = http_util::parse_query_string(query.unwrap_or(""));
+        let days = query_params
+            .get("days")
+            .map_or(Ok(DEFAULT_DAYS), |value| value.parse::<u64>())
+            .map_err(|_| HttpError::BadRequest("Invalid integer for parameter 'days'".into()))?;
+
+        let limit = query_params
+            .get("limit")
+            .map_or(Ok(DEFAULT_LIMIT), |value| value.parse::<u16>())
+            .map_err(|_| HttpError::BadRequest("Invalid integer for parameter 'limit'".into()))?;
+
+        let days = days.clamp(1, MAX_DAYS);
+        let limit = cmp::min(limit, MAX_LIMIT);
+
+        // Include the current day
+        let since = util::get_timestamp().saturating_sub((days - 1) * SECONDS_PER_DAY);
+        let result = self.stats_adapter.get_summary(since, limit).await;
+        database_adapters::build_http_result(result)
+    }
+
+    /// Handles a report of a clicked search result. Only signed in users
+    /// can report clicks, and only on documents that exist.
+    async fn handle_post_click(&self, body: Body, session: &Session) -> HttpResult {
+        match session {
+            Session::Valid(_) | Session::Expired(_) => {}
+            _ => return Err(HttpError::Unauthorized(None)),
+        }
+
+        let bytes = hyper::body::aggregate(body)
+            .await
+            .map(|mut buf| buf.copy_to_bytes(buf.remaining()))
+            .map_err(|error| {
+                error!("Unexpected error while collecting body: {}", error);
+                HttpError::InternalError(None)
+            })?;
+
+        let click: SearchClick = serde_json::from_slice(&bytes)
+            .map_err(|error| HttpError::BadRequest(error.to_string().into()))?;
+
+        let valid = Self::is_valid_id(&click.id)
+            && click.game.as_deref().map_or(true, Self::is_valid_id)
+            && click.character.as_deref().map_or(true, Self::is_valid_id)
+            && !click.query.trim().is_empty();
+
+        if !valid {
+            return Err(HttpError::BadRequest("Invalid search click".into()));
+        }
+
+        let (type_prefix, exists) = match click.doc_type.as_str() {
+            "game" => ("game", self.docs_adapter.game_exists(&click.id).await),
+            "character" => ("char", self.docs_adapter.char_exists(&click.id).await),
+            "move" => ("move", self.docs_adapter.move_exists(&click.id).await),
+            _ => return Err(HttpError::BadRequest("Invalid document type".into())),
+        };
+
+        if !exists.map_err(|_| HttpError::InternalError(None))? {
+            return Err(HttpError::NotFound(None));
+        }
+
+        let scope = SearchScope {
+            game: click.game.as_deref(),
+            character: click.character.as_deref(),
+        };
+
+        let doc_id = format!("{}::{}", type_prefix, click.id);
+        let result = self
+            .stats_adapter
+            .record_click(
+                &click.query,
+                &scope,
+                &click.doc_type,
+                &doc_id,
+                util::get_timestamp(),
+            )
+            .await;
+
+        database_adapters::build_http_result(result.map(|_| json!({})))
+    }
+
+    /// Records a search in the background so it doesn't delay the response
+    fn record_search(
+        &self,
+        search_term: &str,
+        game: Option<&str>,
+        character: Option<&str>,
+        result_count: u64,
+    ) {
+        // Scopes that are not document ids cannot match anything and are not
+        // recorded
+        if search_term.trim().is_empty()
+            || !game.map_or(true, Self::is_valid_id)
+            || !character.map_or(true, Self::is_valid_id)
+        {
+            return;
+        }
+
+        let stats_adapter = self.stats_adapter.clone();
+        let search_term = search_term.to_owned();
+        let game = game.map(|value| value.to_owned());
+        let character = character.map(|value| value.to_owned());
+        let time_stamp = util::get_timestamp();
+
+        tokio::spawn(async move {
+            let scope = SearchScope {
+                game: game.as_deref(),
+                character: character.as_deref(),
+            };
+
+            let result = stats_adapter
+                .record_search(&search_term, &scope, result_count, time_stamp)
+                .await;
+
+            if let Err(error) = result {
+                warn!("Unable to record search: {:?}", error);
+            }
+        });
+    }
+
+    /// Adds highlighted name fragments to each search result and spelling
+    /// suggestions when no result matches the search term exactly
+    async fn add_highlights_and_suggestions(&self, mut content: Value, search_term: &str) -> Value {
+        let query_terms: Vec<String> = search_index::analyze(search_term)
+            .into_iter()
+            .map(|token| token.term)
+            .collect();
+
+        let mut exact_match = false;
+        if let Some(page) = content["page"].as_array_mut() {
+            for result in page {
+                let mut highlights = json!({});
+                for field in &["names", "parentNames"] {
+                    let names: Vec<String> =
+                        serde_json::from_value(result[*field].clone()).unwrap_or_default();
+
+                    let locations = &result["matches"][*field];
+                    exact_match |= locations.as_object().map_or(false, |terms| {
+                        terms.keys().any(|term| query_terms.contains(term))
+                    });
+
+                    highlights[*field] = json!(highlighter::highlight_names(&names, locations));
+                }
+
+                result["highlights"] = highlights;
+            }
+        }
+
+        let suggestions = if exact_match {
+            Vec::new()
+        } else {
+            self.get_suggestions(search_term).await
+        };
+
+        content["suggestions"] = json!(suggestions);
+        content
+    }
+
+    /// Gets spelling suggestions for a search term
+    async fn get_suggestions(&self, search_term: &str) -> Vec<String> {
+        if self.suggester.needs_names() {
+            match self.adapter.get_names().await {
+                Ok(names) => self.suggester.set_names(names),
+                Err(AdapterError::DocumentNotFound) => self.suggester.set_names(Vec::new()),
+                Err(error) => warn!("Unable to load names for suggestions: {:?}", error),
+            }
+        }
+
+        self.suggester.suggest(search_term)
+    }
+
+    /// Extracts the optional resource following the version from a
+    /// search-api path. e.g. `v1/stats`
+    fn extract_resource(path: &str) -> Result<Option<&str>, HttpError> {
+        // Pull out the 'version' from the path.
+        lazy_static! {
+            static ref PATH_REGEX: Regex =
+                Regex::new(r"^v(?P<ver>\d+)(?:/+(?P<resource>\w+))?$").unwrap();
+        }
+
+        let captures = PATH_REGEX
+            .captures(path)
+            .ok_or_else(|| HttpError::BadRequest("Invalid search-api path format".into()))?;
+
+        // Validate the version (currently only version #1)
+        if !captures["ver"].eq("1") {
+            let message = "Unsupported search-api version".into();
+            return Err(HttpError::BadRequest(message));
+        }
+
+        Ok(captures.name("resource").map(|m| m.as_str()))
+    }
+
+    /// Checks whether a value is a valid document id, which keeps the keys
+    /// of recorded statistics short
+    fn is_valid_id(id: &str) -> bool {
+        lazy_static! {
+            static ref ID_REGEX: Regex = Regex::new(r"^[\w\-.]+$").unwrap();
+        }
+
+        id.len() <= MAX_ID_LENGTH && ID_REGEX.is_match(id)
+    }
+
+    fn build_invalid_resource_error(resource: &str) -> HttpError {
+        let message = format!(
+            "Invalid search-api resource '{resource}' for the specified method",
+            resource = resource
+        );
+
+        HttpError::BadRequest(message.into())
+    }
+
+    /// Parses a comma separated list of attribute filters in the format
+    /// `title:op:value`. e.g. `startup:lt:5,on block:sentiment:positive`
+    fn parse_attribute_filters(value: &str) -> Result<Vec<AttributeFilter>, String> {
+        lazy_static! {
+            static ref FILTER_REGEX: Regex =
+                Regex::new(r"^(?P<title>[^:]+):(?P<op>\w+):(?P<value>.+)$").unwrap();
+            static ref TITLE_REGEX: Regex = Regex::new(r"[^A-Za-z0-9]").unwrap();
+        }
+
+        value
+            .split(',')
+            .map(|filter| {
+                let captures = FILTER_REGEX
+                    .captures(filter.trim())
+                    .ok_or_else(|| format!("Invalid attribute filter '{}'", filter))?;
+
+                let title = TITLE_REGEX
+                    .replace_all(&captures["title"], "")
+                    .to_lowercase();
+
+                let value = captures["value"].trim().to_lowercase();
+                let op = &captures["op"];
+                let number = match op {
+                    "eq" | "contains" | "sentiment" => None,
+                    "lt" | "lte" | "gt" | "gte" => {
+                        let number =
+                            value.trim_start_matches('+').parse::<f64>().map_err(|_| {
+                                format!("Invalid number '{}' in attribute filter", value)
+                            })?;
+
+                        Some(number)
+                    }
+                    _ => return Err(format!("Invalid attribute filter operation '{}'", op)),
+                };
+
+                Ok(AttributeFilter {
+                    title,
+                    op: op.to_owned(),
+                    value,
+                    number,
+                })
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,147 @@
+use crate::search_index::edit_distance;
+use std::{
+    collections::BTreeMap,
+    sync::RwLock,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// How long known names are used before being reloaded
+const NAMES_LIFETIME: Duration = Duration::from_secs(600);
+
+/// The maximum number of suggestions returned
+const MAX_SUGGESTIONS: usize = 3;
+
+struct KnownNames {
+    loaded: Instant,
+    /// lower case name -> name
+    names: BTreeMap<String, String>,
+    /// lower case word -> word
+    words: BTreeMap<String, String>,
+}
+
+/// Suggests spelling corrections for search terms from the set of all known
+/// document names
+pub struct Suggester {
+    known_names: RwLock<Option<KnownNames>>,
+}
+
+impl Suggester {
+    pub fn new() -> Self {
+        Self {
+            known_names: RwLock::new(None),
+        }
+    }
+
+    /// Whether the known names have not been loaded or are out of date
+    pub fn needs_names(&self) -> bool {
+        self.known_names
+            .read()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |known| known.loaded.elapsed() > NAMES_LIFETIME)
+    }
+
+    /// Sets the known names suggestions are derived from
+    pub fn set_names(&self, names: Vec<String>) {
+        let mut known = KnownNames {
+            loaded: Instant::now(),
+            names: BTreeMap::new(),
+            words: BTreeMap::new(),
+        };
+
+        for name in names {
+            for word in name.split_whitespace() {
+                known
+                    .words
+                    .entry(word.to_lowercase())
+                    .or_insert_with(|| word.to_owned());
+            }
+
+            known.names.entry(name.to_lowercase()).or_insert(name);
+        }
+
+        *self.known_names.write().unwrap() = Some(known);
+    }
+
+    /// Gets spelling suggestions for a search term. Whole names close to the
+    /// term are suggested first followed by the term with each misspelled
+    /// word corrected.
+    pub fn suggest(&self, search_term: &str) -> Vec<String> {
+        let known_names = self.known_names.read().unwrap();
+        let known = match known_names.as_ref() {
+            Some(known) => known,
+            None => return Vec::new(),
+        };
+
+        let search_term = search_term.trim().to_lowercase();
+        let mut suggestions: Vec<(usize, &str)> = known
+            .names
+            .iter()
+            .filter(|(lower_name, _)| **lower_name != search_term)
+            .filter_map(|(lower_name, name)| {
+                edit_distance(&search_term, lower_name, Self::max_distance(&search_term))
+                    .map(|distance| (distance, name.as_str()))
+            })
+            .collect();
+
+        suggestions.sort();
+        let mut suggestions: Vec<String> = suggestions
+            .into_iter()
+            .map(|(_, name)| name.to_owned())
+            .collect();
+
+        // Correct each word of the term individually
+        let mut corrected = false;
+        let words: Vec<String> = search_term
+            .split_whitespace()
+            .map(|word| {
+                if known.words.contains_key(word) {
+                    return word.to_owned();
+                }
+
+                let best = known
+                    .words
+                    .iter()
+                    .filter_map(|(lower_word, original)| {
+                        edit_distance(word, lower_word, Self::max_distance(word))
+                            .map(|distance| (distance, original))
+                    })
+                    .min();
+
+                match best {
+                    Some((_, original)) => {
+                        corrected = true;
+                        original.to_owned()
+                    }
+                    None => word.to_owned(),
+                }
+            })
+            .collect();
+
+        let phrase = words.join(" ");
+        let phrase_lower = phrase.to_lowercase();
+        if corrected
+            && !suggestions
+                .iter()
+                .any(|suggestion| suggestion.to_lowercase() == phrase_lower)
+        {
+            suggestions.push(phrase);
+        }
+
+        suggestions.truncate(MAX_SUGGESTIONS);
+        suggestions
+    }
+
+    /// The maximum edit distance allowed for a suggestion given the length of
+    /// the misspelled text
+    fn max_distance(text: &str) -> usize {
+        match text.chars().count() {
+            0..=2 => 0,
+            3..=4 => 1,
+            _ => 2,
+        }
+    }
+}
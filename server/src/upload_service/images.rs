@@ -0,0 +1,152 @@
+use super::{
+    process,
+    ProcessingLimits,
+};
+use crate::http_service::HttpError;
+use log::error;
+use tokio::{
+    fs::{
+        remove_file,
+        File,
+    },
+    io::AsyncReadExt,
+    process::Command,
+};
+
+/// The widths of the web variants generated for each image. Images are never
+/// scaled up.
+const VARIANT_WIDTHS: [u32; 2] = [320, 960];
+
+/// The formats each variant is published in
+const VARIANT_EXTENSIONS: [&str; 2] = ["webp", "jpg"];
+
+/// The supported image formats
+#[derive(Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    WebP,
+}
+
+/// Gets the file names of the published variants of an image
+pub fn variant_file_names(file_name: &str) -> Vec<String> {
+    VARIANT_WIDTHS
+        .iter()
+        .flat_map(|width| {
+            VARIANT_EXTENSIONS
+                .iter()
+                .map(move |extension| format!("{}.{}.{}", file_name, width, extension))
+        })
+        .collect()
+}
+
+/// Removes the published variants of an image. Used to clean up after a
+/// failure.
+pub async fn remove_web_images(target_file: &str) {
+    for variant in variant_file_names(target_file) {
+        // Variants may not have been created
+        let _ = remove_file(variant).await;
+    }
+}
+
+/// Detects the format of an image file from its magic bytes
+pub async fn detect_format(in_file: &str) -> Result<Option<ImageFormat>, HttpError> {
+    let mut header = [0u8; 12];
+    let mut file = File::open(in_file).await.map_err(|error| {
+        error!("Failed to open image: {}", error);
+        HttpError::InternalError(None)
+    })?;
+
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..]).await {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(error) => {
+                error!("Failed to read image: {}", error);
+                return Err(HttpError::InternalError(None));
+            }
+        }
+    }
+
+    let header = &header[..read];
+    let format = if header.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some(ImageFormat::Png)
+    } else if header.starts_with(b"\xff\xd8\xff") {
+        Some(ImageFormat::Jpeg)
+    } else if header.len() == 12 && header.starts_with(b"RIFF") && &header[8..12] == b"WEBP" {
+        Some(ImageFormat::WebP)
+    } else {
+        None
+    };
+
+    Ok(format)
+}
+
+/// Generates web variants of an image without any of its metadata along with
+/// a tiny base64 encoded preview. Each variant is published as
+/// `<target_file>.<width>.webp` and `<target_file>.<width>.jpg`.
+pub async fn generate_web_images(
+    in_file: &str,
+    target_file: &str,
+    tmp_dir: &str,
+    limits: &ProcessingLimits,
+) -> Result<String, HttpError> {
+    let tmp_image = format!("{}/preview.jpg", tmp_dir);
+
+    // Overwrite any files left behind by an interrupted run
+    let mut command = Command::new("ffmpeg");
+    let command = command.arg("-y").args(&["-i", in_file]);
+
+    // Configure arguments for each variant. Dropping all metadata removes
+    // any EXIF data such as locations.
+    for width in VARIANT_WIDTHS.iter() {
+        let scale = format!("scale='min({},iw)':-2", width);
+        for extension in VARIANT_EXTENSIONS.iter() {
+            command
+                .args(&["-map_metadata", "-1"])
+                .args(&["-filter:v", &scale])
+                .args(&["-frames:v", "1"])
+                .arg(format!("{}.{}.{}", target_file, width, extension));
+        }
+    }
+
+    // Configure arguments for creating a preview image
+    let command = command
+        .args(&["-map_metadata", "-1"])
+        .args(&[
+            "-filter:v",
+            "scale=8:6:force_original_aspect_ratio=decrease,pad=8:6:(ow-iw)/2:(oh-ih)/2",
+        ])
+        .args(&["-frames:v", "1"])
+        .arg(&tmp_image);
+
+    // Execute the command and read the resulting preview file
+    let result = process::run(command, limits.process_timeout, limits)
+        .await
+        .map_err(|error| {
+            error!("Failed to run ffmpeg process: {}", error);
+            HttpError::InternalError(None)
+        })?;
+
+    if !result.status.success() {
+        // The image content is invalid despite its magic bytes
+        error!("ffmpeg image processing failed: {}", result.status);
+        error!("{}", String::from_utf8_lossy(&result.stderr));
+        return Err(HttpError::BadRequest("Unable to process the image".into()));
+    }
+
+    let mut file = File::open(tmp_image).await.map_err(|error| {
+        error!("Failed to open preview image: {}", error);
+        HttpError::InternalError(None)
+    })?;
+
+    let mut content = Vec::<u8>::new();
+    file.read_to_end(&mut content)
+        .await
+        .map(move |_| base64::encode(&content))
+        .map_err(|error| {
+            error!("Failed to read preview image: {}", error);
+            HttpError::InternalError(None)
+        })
+}
@@ -1,7 +1,38 @@
+mod images;
+mod process;
+mod quotas;
+mod resumable;
 mod service;
 mod service_config;
+mod storage;
+mod streaming;
+mod transcoder;
+mod validation;
+
+use rand::{
+    distributions::Alphanumeric,
+    thread_rng,
+    Rng,
+};
 
 pub use self::{
     service::Service,
-    service_config::ServiceConfig,
+    service_config::{
+        ProcessingLimits,
+        ServiceConfig,
+        TranscodeConfig,
+        TranscodeProfile,
+        UploadLimits,
+        UploadQuota,
+    },
+    transcoder::FINISHED_JOB_RETENTION,
 };
+
+/// Builds a random id for uploads and jobs
+fn build_random_id() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .map(char::from)
+        .take(24)
+        .collect::<String>()
+}
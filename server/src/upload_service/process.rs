@@ -0,0 +1,68 @@
+use super::ProcessingLimits;
+use std::{
+    io::{
+        self,
+        ErrorKind,
+    },
+    process::{
+        Output,
+        Stdio,
+    },
+    time::Duration,
+};
+use tokio::{
+    process::Command,
+    time,
+};
+
+/// Runs an ffmpeg or ffprobe command with limited resources and waits for
+/// its output. The process is killed if it does not finish within the
+/// timeout (in seconds).
+pub async fn run(
+    command: &mut Command,
+    timeout: u32,
+    limits: &ProcessingLimits,
+) -> io::Result<Output> {
+    let memory_limit = limits.memory_limit;
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    // Only async-signal-safe functions are called between fork and exec
+    unsafe {
+        command.pre_exec(move || {
+            let limits = [(libc::RLIMIT_AS, memory_limit), (libc::RLIMIT_CORE, 0)];
+            for (resource, value) in limits.iter() {
+                let limit = libc::rlimit {
+                    rlim_cur: *value as libc::rlim_t,
+                    rlim_max: *value as libc::rlim_t,
+                };
+
+                if libc::setrlimit(*resource, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+
+            Ok(())
+        });
+    }
+
+    // Dropping the child when the timeout elapses kills it
+    let child = command.spawn()?;
+    match time::timeout(
+        Duration::from_secs(timeout as u64),
+        child.wait_with_output(),
+    )
+    .await
+    {
+        Ok(result) => result,
+        Err(_) => {
+            Err(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("The process did not finish within {} seconds", timeout),
+            ))
+        }
+    }
+}
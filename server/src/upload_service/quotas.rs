@@ -0,0 +1,319 @@
+use super::{
+    build_random_id,
+    ServiceConfig,
+};
+use crate::{
+    database_adapters::couchbase::UploadUsage,
+    http_service::HttpError,
+    util,
+};
+use log::warn;
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+
+/// The uploads of a user within the window of a quota
+struct WindowUsage {
+    files: u64,
+    bytes: u64,
+    /// The number of seconds until the oldest upload leaves the window
+    reset_in: u64,
+}
+
+impl WindowUsage {
+    fn new(uploads: &[Value], now: u64, window: u64) -> Self {
+        let since = now.saturating_sub(window);
+        let mut usage = Self {
+            files: 0,
+            bytes: 0,
+            reset_in: 0,
+        };
+
+        let mut oldest: Option<u64> = None;
+        for upload in uploads {
+            let time = upload["time"].as_u64().unwrap_or(0);
+            if time < since {
+                continue;
+            }
+
+            usage.files += 1;
+            usage.bytes += upload["bytes"].as_u64().unwrap_or(0);
+            oldest = Some(oldest.map_or(time, |oldest| oldest.min(time)));
+        }
+
+        if let Some(oldest) = oldest {
+            usage.reset_in = (oldest + window).saturating_sub(now).max(1);
+        }
+
+        usage
+    }
+}
+
+/// Bytes reserved against the quotas of a user for an upload. Stored with
+/// resumable uploads so the bytes can be released if they are abandoned.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Reservation {
+    /// The id of the recorded upload. Nothing is recorded when no quotas are
+    /// configured.
+    id: Option<String>,
+    bytes: u64,
+}
+
+impl Reservation {
+    /// The number of bytes that can be uploaded
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+}
+
+/// Enforces the configured upload quotas of each user over rolling windows
+/// of time
+pub struct UploadQuotas {
+    config: Arc<ServiceConfig>,
+    adapter: Arc<UploadUsage>,
+}
+
+impl UploadQuotas {
+    pub fn new(config: Arc<ServiceConfig>, adapter: Arc<UploadUsage>) -> Self {
+        Self { config, adapter }
+    }
+
+    /// Reserves bytes for an upload of the specified length against the
+    /// user's quotas. Uploads of unknown length reserve the number of bytes
+    /// the user can still upload, up to `max_length`. Checking the quotas and
+    /// reserving the bytes is a single atomic update, so concurrent uploads
+    /// cannot exceed a quota.
+    pub async fn reserve(
+        &self,
+        user_id: &str,
+        length: Option<u64>,
+        max_length: u64,
+    ) -> Result<Reservation, HttpError> {
+        let max_window = match self.max_window() {
+            Some(max_window) => max_window,
+            None => {
+                return Ok(Reservation {
+                    id: None,
+                    bytes: length.unwrap_or(max_length),
+                })
+            }
+        };
+
+        let id = build_random_id();
+        let now = util::get_timestamp();
+        let mut outcome = Err(HttpError::InternalError(None));
+        let reserved = self
+            .adapter
+            .reserve(
+                user_id,
+                &id,
+                now,
+                now.saturating_sub(max_window),
+                |uploads| {
+                    outcome = self.check(user_id, uploads, now, length, max_length);
+                    outcome.as_ref().ok().copied()
+                },
+            )
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        let bytes = outcome?;
+        if !reserved {
+            return Err(HttpError::InternalError(None));
+        }
+
+        Ok(Reservation {
+            id: Some(id),
+            bytes,
+        })
+    }
+
+    /// Replaces the bytes reserved for an upload with the number of bytes
+    /// that were actually uploaded
+    pub async fn commit(
+        &self,
+        user_id: &str,
+        reservation: &Reservation,
+        length: u64,
+    ) -> Result<(), HttpError> {
+        let id = match &reservation.id {
+            Some(id) if length != reservation.bytes => id,
+            _ => return Ok(()),
+        };
+
+        self.adapter
+            .resize(user_id, id, length, util::get_timestamp())
+            .await
+            .map_err(|_| HttpError::InternalError(None))
+    }
+
+    /// Releases the bytes reserved for an upload that failed
+    pub async fn release(&self, user_id: &str, reservation: &Reservation) {
+        if let Some(id) = &reservation.id {
+            if self
+                .adapter
+                .release(user_id, id, util::get_timestamp())
+                .await
+                .is_err()
+            {
+                warn!("Unable to release upload quota {} of {}", id, user_id);
+            }
+        }
+    }
+
+    /// Gets the usage of a user against each quota
+    pub async fn get_usage(&self, user_id: &str) -> Result<Value, HttpError> {
+        let uploads = self.get_uploads(user_id).await?;
+        let now = util::get_timestamp();
+        let quotas = self
+            .config
+            .upload_quotas()
+            .iter()
+            .map(|quota| {
+                let usage = WindowUsage::new(&uploads, now, quota.window as u64);
+                json!({
+                    "window": quota.window,
+                    "maxFiles": quota.max_files,
+                    "maxBytes": quota.max_bytes,
+                    "files": usage.files,
+                    "bytes": usage.bytes,
+                    "resetIn": usage.reset_in,
+                })
+            })
+            .collect::<Vec<Value>>();
+
+        Ok(json!({
+            "userId": user_id,
+            "quotas": quotas,
+        }))
+    }
+
+    /// Clears all uploads recorded for a user
+    pub async fn reset(&self, user_id: &str) -> Result<(), HttpError> {
+        self.adapter
+            .reset(user_id)
+            .await
+            .map_err(|_| HttpError::InternalError(None))
+    }
+
+    /// Checks a user's uploads against each quota. Returns the number of
+    /// bytes to reserve for an upload of the specified length.
+    fn check(
+        &self,
+        user_id: &str,
+        uploads: &[Value],
+        now: u64,
+        length: Option<u64>,
+        max_length: u64,
+    ) -> Result<u64, HttpError> {
+        let mut remaining = max_length;
+        for quota in self.config.upload_quotas() {
+            let usage = WindowUsage::new(uploads, now, quota.window as u64);
+            if let Some(max_files) = quota.max_files {
+                if usage.files >= max_files as u64 {
+                    warn!(
+                        "User {} reached the upload limit of {} files",
+                        user_id, max_files
+                    );
+                    let message = format!(
+                        "Upload limit of {} files per {} seconds reached",
+                        max_files, quota.window
+                    );
+
+                    return Err(HttpError::TooManyRequests(
+                        Some(message.into()),
+                        usage.reset_in,
+                    ));
+                }
+            }
+
+            if let Some(max_bytes) = quota.max_bytes {
+                let left = max_bytes.saturating_sub(usage.bytes);
+                if left == 0 {
+                    warn!(
+                        "User {} reached the upload limit of {} bytes",
+                        user_id, max_bytes
+                    );
+                    let message = format!(
+                        "Upload limit of {} bytes per {} seconds reached",
+                        max_bytes, quota.window
+                    );
+
+                    return Err(HttpError::TooManyRequests(
+                        Some(message.into()),
+                        usage.reset_in,
+                    ));
+                }
+
+                if length.map_or(false, |length| length > left) {
+                    let message = format!(
+                        "The file exceeds the upload quota. Only {} more bytes can be uploaded \
+                         within {} seconds",
+                        left, quota.window
+                    );
+
+                    return Err(HttpError::PayloadTooLarge(Some(message.into())));
+                }
+
+                remaining = remaining.min(left);
+            }
+        }
+
+        Ok(length.unwrap_or(remaining))
+    }
+
+    /// Gets the longest window of the configured quotas. Only uploads within
+    /// it are kept.
+    fn max_window(&self) -> Option<u64> {
+        self.config
+            .upload_quotas()
+            .iter()
+            .map(|quota| quota.window as u64)
+            .max()
+    }
+
+    async fn get_uploads(&self, user_id: &str) -> Result<Vec<Value>, HttpError> {
+        self.adapter
+            .get(user_id)
+            .await
+            .map_err(|_| HttpError::InternalError(None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::WindowUsage;
+    use serde_json::json;
+
+    #[test]
+    fn counts_uploads_within_the_window() {
+        let uploads = [
+            json!({ "time": 1000, "bytes": 100 }),
+            json!({ "time": 5000, "bytes": 200 }),
+            json!({ "time": 8000, "bytes": 400 }),
+        ];
+
+        let usage = WindowUsage::new(&uploads, 9000, 5000);
+        assert_eq!((usage.files, usage.bytes), (2, 600));
+        assert_eq!(usage.reset_in, 1000);
+    }
+
+    #[test]
+    fn resets_immediately_without_uploads() {
+        let usage = WindowUsage::new(&[], 9000, 5000);
+        assert_eq!((usage.files, usage.bytes, usage.reset_in), (0, 0, 0));
+    }
+
+    #[test]
+    fn resets_in_at_least_a_second() {
+        let uploads = [json!({ "time": 4000, "bytes": 100 })];
+        let usage = WindowUsage::new(&uploads, 9000, 5000);
+        assert_eq!((usage.files, usage.reset_in), (1, 1));
+    }
+}
@@ -0,0 +1,328 @@
+use super::{
+    quotas::Reservation,
+    streaming::{
+        self,
+        WriteError,
+    },
+    transcoder::ClipOptions,
+};
+use crate::{
+    http_service::HttpError,
+    util,
+};
+use hyper::Body;
+use log::{
+    error,
+    info,
+    warn,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    collections::HashSet,
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::fs::{
+    self,
+    create_dir_all,
+    remove_dir_all,
+    OpenOptions,
+};
+
+/// The number of seconds an unfinished upload is kept for after it last
+/// received any bytes
+const UPLOAD_LIFETIME_SECS: u64 = 24 * 60 * 60;
+
+/// The stored information of a resumable upload
+#[derive(Serialize, Deserialize)]
+struct UploadRecord {
+    length: u64,
+    #[serde(rename = "userId")]
+    user_id: String,
+    created: u64,
+    #[serde(default)]
+    options: ClipOptions,
+    #[serde(default)]
+    reservation: Reservation,
+}
+
+/// An unfinished upload that was removed after it expired
+pub struct ExpiredUpload {
+    pub user_id: String,
+    /// The bytes reserved for the upload against the user's quotas
+    pub reservation: Reservation,
+}
+
+/// The state of a resumable upload
+pub struct UploadInfo {
+    /// The total length of the upload in bytes
+    pub length: u64,
+    /// The number of bytes received so far
+    pub offset: u64,
+    /// How the uploaded clip should be processed
+    pub options: ClipOptions,
+    /// The bytes reserved for the upload against the user's quotas
+    pub reservation: Reservation,
+}
+
+impl UploadInfo {
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.length
+    }
+}
+
+/// Removes an upload from the set of uploads being written to when dropped
+struct ActiveGuard<'a> {
+    active: &'a Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        self.active.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Manages resumable (tus-style) uploads. Each upload is stored in its own
+/// directory containing the received bytes and a record of its length and
+/// owner. See https://tus.io/protocols/resumable-upload.html for reference.
+pub struct ResumableUploads {
+    dir: String,
+    active: Mutex<HashSet<String>>,
+}
+
+impl ResumableUploads {
+    /// Creates a new instance storing uploads in the specified directory
+    pub fn new(dir: String) -> Self {
+        Self {
+            dir,
+            active: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Creates a new upload of the specified length returning its id
+    pub async fn create(
+        &self,
+        user_id: &str,
+        length: u64,
+        options: ClipOptions,
+        reservation: &Reservation,
+    ) -> Result<String, HttpError> {
+        let id = super::build_random_id();
+
+        let upload_dir = self.upload_dir(&id);
+        create_dir_all(&upload_dir).await.map_err(|error| {
+            error!("Could not create upload directory: {}", error);
+            HttpError::InternalError(None)
+        })?;
+
+        let record = UploadRecord {
+            length,
+            user_id: user_id.to_owned(),
+            created: util::get_timestamp(),
+            options,
+            reservation: reservation.clone(),
+        };
+
+        let result = async {
+            fs::write(self.record_path(&id), serde_json::to_vec(&record).unwrap()).await?;
+            fs::write(self.file_path(&id), []).await
+        }
+        .await;
+
+        if let Err(error) = result {
+            error!("Could not create upload files: {}", error);
+            self.remove(&id).await;
+            return Err(HttpError::InternalError(None));
+        }
+
+        info!("User {} started upload {} of size {}", user_id, id, length);
+        Ok(id)
+    }
+
+    /// Gets the state of an upload owned by the specified user
+    pub async fn get_info(&self, id: &str, user_id: &str) -> Result<UploadInfo, HttpError> {
+        let record = self.get_record(id, user_id).await?;
+        let offset = Self::get_file_length(&self.file_path(id)).await?;
+        Ok(UploadInfo {
+            length: record.length,
+            offset,
+            options: record.options,
+            reservation: record.reservation,
+        })
+    }
+
+    /// Appends a request body to an upload at the specified offset. The
+    /// offset must match the number of bytes received so far.
+    pub async fn append(
+        &self,
+        id: &str,
+        user_id: &str,
+        offset: u64,
+        body: Body,
+    ) -> Result<UploadInfo, HttpError> {
+        let record = self.get_record(id, user_id).await?;
+
+        // Only allow one request to write to an upload at a time
+        let _guard = {
+            let mut active = self.active.lock().unwrap();
+            if !active.insert(id.to_owned()) {
+                let message = "The upload is already being written to".into();
+                return Err(HttpError::Conflict(Some(message)));
+            }
+
+            ActiveGuard {
+                active: &self.active,
+                id: id.to_owned(),
+            }
+        };
+
+        let file_path = self.file_path(id);
+        let current_offset = Self::get_file_length(&file_path).await?;
+        if offset != current_offset {
+            let message = format!("Expected an upload offset of {}", current_offset);
+            return Err(HttpError::Conflict(Some(message.into())));
+        }
+
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(&file_path)
+            .await
+            .map_err(|error| {
+                error!("Could not open upload file: {}", error);
+                HttpError::InternalError(None)
+            })?;
+
+        let remaining = record.length - current_offset;
+        match streaming::write_body(body, &mut file, remaining).await {
+            Ok(written) => {
+                Ok(UploadInfo {
+                    length: record.length,
+                    offset: current_offset + written,
+                    options: record.options,
+                    reservation: record.reservation,
+                })
+            }
+            Err(error @ WriteError::Body(_)) => {
+                // Keep what was received so the upload can be resumed
+                Err(error.into_http_error())
+            }
+            Err(error) => {
+                // Discard the partially written request
+                if let Err(error) = file.set_len(current_offset).await {
+                    error!("Could not truncate upload file: {}", error);
+                }
+
+                Err(error.into_http_error())
+            }
+        }
+    }
+
+    /// Removes an upload and its files
+    pub async fn remove(&self, id: &str) {
+        if let Err(error) = remove_dir_all(self.upload_dir(id)).await {
+            error!("Failed to remove upload '{}': {}", id, error);
+        }
+    }
+
+    /// Gets the directory of an upload
+    pub fn upload_dir(&self, id: &str) -> String {
+        format!("{}/{}", self.dir, id)
+    }
+
+    /// Gets the path of the file holding the received bytes of an upload
+    pub fn file_path(&self, id: &str) -> String {
+        format!("{}/{}/file", self.dir, id)
+    }
+
+    fn record_path(&self, id: &str) -> String {
+        format!("{}/{}/upload.json", self.dir, id)
+    }
+
+    /// Gets the record of an upload. Uploads of other users are treated as
+    /// not found.
+    async fn get_record(&self, id: &str, user_id: &str) -> Result<UploadRecord, HttpError> {
+        if !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(HttpError::NotFound(None));
+        }
+
+        let content = fs::read(self.record_path(id))
+            .await
+            .map_err(|_| HttpError::NotFound(None))?;
+
+        let record: UploadRecord = serde_json::from_slice(&content).map_err(|error| {
+            error!("Invalid upload record for '{}': {}", id, error);
+            HttpError::InternalError(None)
+        })?;
+
+        if record.user_id != user_id {
+            warn!(
+                "User {} tried to access upload {} of another user",
+                user_id, id
+            );
+            return Err(HttpError::NotFound(None));
+        }
+
+        Ok(record)
+    }
+
+    /// Removes unfinished uploads that have not received any bytes for a
+    /// while. Returns the removed uploads whose records could be read.
+    pub async fn remove_expired(&self) -> Vec<ExpiredUpload> {
+        let mut expired_uploads = Vec::new();
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(_) => return expired_uploads,
+        };
+
+        let lifetime = Duration::from_secs(UPLOAD_LIFETIME_SECS);
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let id = entry.file_name().to_string_lossy().into_owned();
+            let last_modified = match fs::metadata(self.file_path(&id)).await {
+                Ok(metadata) => metadata.modified(),
+                Err(_) => {
+                    entry
+                        .metadata()
+                        .await
+                        .and_then(|metadata| metadata.modified())
+                }
+            };
+
+            let expired = last_modified
+                .ok()
+                .and_then(|modified| modified.elapsed().ok())
+                .map_or(false, |elapsed| elapsed > lifetime);
+
+            if expired && !self.active.lock().unwrap().contains(&id) {
+                info!("Removing expired upload {}", id);
+                let record = fs::read(self.record_path(&id))
+                    .await
+                    .ok()
+                    .and_then(|content| serde_json::from_slice::<UploadRecord>(&content).ok());
+
+                self.remove(&id).await;
+                if let Some(record) = record {
+                    expired_uploads.push(ExpiredUpload {
+                        user_id: record.user_id,
+                        reservation: record.reservation,
+                    });
+                }
+            }
+        }
+
+        expired_uploads
+    }
+
+    async fn get_file_length(file_path: &str) -> Result<u64, HttpError> {
+        fs::metadata(file_path)
+            .await
+            .map(|metadata| metadata.len())
+            .map_err(|error| {
+                error!("Could not read upload file metadata: {}", error);
+                HttpError::InternalError(None)
+            })
+    }
+}
@@ -1,23 +1,49 @@
-use super::ServiceConfig;
+use super::{
+    build_random_id,
+    images,
+    quotas::{
+        Reservation,
+        UploadQuotas,
+    },
+    resumable::{
+        ResumableUploads,
+        UploadInfo,
+    },
+    storage::MediaStore,
+    streaming,
+    transcoder::{
+        ClipOptions,
+        TranscodeJob,
+        TranscodeQueue,
+    },
+    validation,
+    ServiceConfig,
+};
 use crate::{
     auth_service::Session,
+    database_adapters::{
+        couchbase::{
+            UploadJobs,
+            UploadMedia,
+            UploadUsage,
+        },
+        AdapterError,
+    },
     http_service::{
         util as http_util,
         HttpError,
         HttpResult,
     },
-    util::get_timestamp_string,
 };
 use hyper::{
-    body::{
-        self,
-        Body,
-        Buf,
-    },
     header::{
         self,
         HeaderMap,
+        HeaderValue,
     },
+    http::response,
+    Body,
+    Response,
     StatusCode,
 };
 use lazy_static::lazy_static;
@@ -27,288 +53,653 @@ use log::{
     warn,
 };
 use regex::Regex;
-use serde_json::json;
-use std::fmt::Display;
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    fmt::Display,
+    io::ErrorKind,
+    sync::Arc,
+    time::Duration,
+};
 use tokio::{
     fs::{
         create_dir_all,
         remove_dir_all,
+        rename,
         File,
     },
-    io::{
-        AsyncReadExt,
-        AsyncWriteExt,
-    },
-    process::Command,
+    time,
 };
 
+/// The version of the tus resumable upload protocol that is supported
+const TUS_VERSION: &str = "1.0.0";
+
 pub struct Service {
-    config: ServiceConfig,
+    config: Arc<ServiceConfig>,
+    uploads: ResumableUploads,
+    queue: TranscodeQueue,
+    store: Arc<MediaStore>,
+    quotas: UploadQuotas,
+    jobs_adapter: Arc<UploadJobs>,
 }
 
 impl Service {
     /// Creates a new instance of the Service struct
-    pub fn new(config: ServiceConfig) -> Self {
-        Self { config }
+    pub fn new(
+        config: ServiceConfig,
+        jobs_adapter: Arc<UploadJobs>,
+        media_adapter: Arc<UploadMedia>,
+        usage_adapter: Arc<UploadUsage>,
+    ) -> Self {
+        let config = Arc::new(config);
+        let store = Arc::new(MediaStore::new(
+            config.publish_dir().to_owned(),
+            media_adapter,
+        ));
+
+        let uploads = ResumableUploads::new(format!("{}/resumable", config.video_tmp_dir()));
+
+        // Remove the files of any jobs interrupted by a restart
+        let jobs_dir = Self::get_jobs_dir(&config);
+        if let Err(error) = std::fs::remove_dir_all(&jobs_dir) {
+            if error.kind() != ErrorKind::NotFound {
+                error!(
+                    "Failed to clean up jobs directory '{}': {}",
+                    jobs_dir, error
+                );
+            }
+        }
+
+        // Periodically remove uploads that are no longer referenced
+        {
+            let store = store.clone();
+            let period = Duration::from_secs(config.media_gc_interval() as u64);
+            let grace_period = config.media_gc_grace_period() as u64;
+            tokio::spawn(async move {
+                let mut interval = time::interval(period);
+                loop {
+                    interval.tick().await;
+                    store.collect_garbage(grace_period).await;
+                }
+            });
+        }
+
+        Self {
+            queue: TranscodeQueue::new(config.clone(), jobs_adapter.clone(), store.clone()),
+            quotas: UploadQuotas::new(config.clone(), usage_adapter),
+            config,
+            uploads,
+            store,
+            jobs_adapter,
+        }
+    }
+
+    /// Handles a upload-api `GET` request
+    pub async fn handle_get_request(&self, path: &str, session: &Session) -> HttpResult {
+        lazy_static! {
+            static ref ID_REGEX: Regex = Regex::new(r"^(?P<id>[A-Za-z0-9]+)$").unwrap();
+        }
+
+        let user_id = Self::get_user_id(session)?;
+        let (root_path, relative_path) =
+            Self::extract_paths(path).map_err(|message| HttpError::BadRequest(message.into()))?;
+
+        let id = match (root_path, ID_REGEX.captures(relative_path)) {
+            ("jobs", Some(captures)) => captures.name("id").unwrap().as_str(),
+            ("usage", _) => return self.handle_get_usage(relative_path, session).await,
+            _ => return Err(Self::build_invalid_path_error(path)),
+        };
+
+        let mut job = self.jobs_adapter.get(id).await.map_err(|error| {
+            match error {
+                AdapterError::DocumentNotFound => HttpError::NotFound(None),
+                _ => HttpError::InternalError(None),
+            }
+        })?;
+
+        // Jobs of other users are treated as not found
+        let is_admin = match session {
+            Session::Valid(claims) | Session::Expired(claims) => claims.is_admin(),
+            _ => false,
+        };
+
+        if job["userId"].as_str() != Some(user_id) && !is_admin {
+            return Err(HttpError::NotFound(None));
+        }
+
+        if let Some(fields) = job.as_object_mut() {
+            fields.remove("type");
+            fields.remove("userId");
+            fields.insert("id".to_owned(), json!(id));
+        }
+
+        Ok(http_util::build_json_response(&job, StatusCode::OK))
     }
 
     /// Handles a upload-api `POST` request
     pub async fn handle_post_request(
         &self,
         path: &str,
+        query: Option<&str>,
         header_map: &HeaderMap,
         body: Body,
         session: &Session,
     ) -> HttpResult {
         // Get the user information.
-        let (user_id, user_name) = match session {
-            Session::Valid(claims) | Session::Expired(claims) => {
-                (claims.sub(), claims.screen_name())
-            }
-            _ => {
-                return Err(HttpError::Unauthorized(None));
-            }
-        };
+        let user_id = Self::get_user_id(session)?;
 
-        // Make sure the uploaded file is within the size limit.
-        let length = Self::get_content_length(&header_map)?;
-        if length > self.config.video_size_limit() {
-            warn!(
-                "User {}:{} tried to upload a large file of size {}",
-                user_name, user_id, length
-            );
+        let (root_path, relative_path) =
+            Self::extract_paths(path).map_err(|message| HttpError::BadRequest(message.into()))?;
 
-            return Err(HttpError::BadRequest("File is too big".into()));
-        }
+        // Reject uploads that are known to be too big up front. Uploads
+        // without a length (chunked) are limited while being received.
+        let limit = match root_path {
+            "image" => self.config.image_size_limit(),
+            _ => self.config.video_size_limit(),
+        };
+
+        let length = Self::get_length_header(header_map, header::CONTENT_LENGTH.as_str())?;
+        Self::check_size_limit(user_id, length, limit)?;
 
         info!(
-            "User {}:{} is uploading a file of size {}",
-            user_name, user_id, length
+            "User {} is uploading a file of size {}",
+            user_id,
+            length.map_or("unknown".to_owned(), |length| length.to_string())
         );
 
         // Route the request
-        let (root_path, _) =
-            Self::extract_paths(path).map_err(|message| HttpError::BadRequest(message.into()))?;
+        match (root_path, relative_path) {
+            ("video", "") => {
+                let options = ClipOptions::from_query(query)?;
+                let reservation = self.quotas.reserve(user_id, length, limit as u64).await?;
+                let result = self
+                    .handle_video_upload(body, user_id, options, &reservation)
+                    .await;
 
-        match root_path {
-            "video" => Self::handle_video_upload(body, &self.config).await,
-            _ => {
-                let message = format!(
-                    "Invalid upload-api path '{path}' for the specified method",
-                    path = path
-                );
+                if result.is_err() {
+                    self.quotas.release(user_id, &reservation).await;
+                }
+
+                result
+            }
+            ("image", "") => {
+                let reservation = self.quotas.reserve(user_id, length, limit as u64).await?;
+                let result = self.handle_image_upload(body, user_id, &reservation).await;
+
+                if result.is_err() {
+                    self.quotas.release(user_id, &reservation).await;
+                }
 
-                Err(HttpError::BadRequest(message.into()))
+                result
             }
+            ("video", "uploads") => {
+                let options = ClipOptions::from_query(query)?;
+                self.handle_create_upload(header_map, user_id, options)
+                    .await
+            }
+            _ => Err(Self::build_invalid_path_error(path)),
         }
     }
 
-    /// Handles video upload requests
-    async fn handle_video_upload(body: Body, config: &ServiceConfig) -> HttpResult {
-        let target_file_name = get_timestamp_string();
-        let target_file_path = format!("{}/{}", config.publish_dir(), target_file_name);
+    /// Handles a upload-api `HEAD` request
+    pub async fn handle_head_request(&self, path: &str, session: &Session) -> HttpResult {
+        let user_id = Self::get_user_id(session)?;
+        let id = Self::extract_upload_id(path)?;
+        let info = self.uploads.get_info(id, user_id).await?;
 
-        // Create the temporary directory
-        let tmp_dir = format!("{}/{}", config.video_tmp_dir(), get_timestamp_string());
+        Ok(Self::build_upload_response(StatusCode::OK, &info)
+            .body(Body::empty())
+            .unwrap())
+    }
 
-        create_dir_all(&tmp_dir).await.map_err(|error| {
-            error!("Could not create temporary directory: {}", error);
-            HttpError::InternalError(None)
-        })?;
+    /// Handles a upload-api `PATCH` request
+    pub async fn handle_patch_request(
+        &self,
+        path: &str,
+        header_map: &HeaderMap,
+        body: Body,
+        session: &Session,
+    ) -> HttpResult {
+        let user_id = Self::get_user_id(session)?;
+        let id = Self::extract_upload_id(path)?;
 
-        async fn remove_temp_dir(dir: &str) {
-            if let Err(error) = remove_dir_all(dir).await {
-                error!(
-                    "Failed to clean up temporary directory '{}': {}",
-                    &dir, error
+        let content_type = header_map
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok());
+
+        if content_type != Some("application/offset+octet-stream") {
+            let message = "Expected content of type 'application/offset+octet-stream'".into();
+            return Err(HttpError::BadRequest(message));
+        }
+
+        let offset = Self::get_length_header(header_map, "upload-offset")?
+            .ok_or_else(|| HttpError::BadRequest("Missing Upload-Offset header".into()))?;
+
+        let mut info = self.uploads.append(id, user_id, offset, body).await?;
+
+        // Queue the video for processing once this request completes the
+        // upload
+        if offset < info.length && info.is_complete() {
+            info!("Upload {} of user {} is complete", id, user_id);
+            let job_id = build_random_id();
+            let job_dir = format!("{}/{}", Self::get_jobs_dir(&self.config), job_id);
+            let options = std::mem::take(&mut info.options);
+            let result = async {
+                let moved = async {
+                    create_dir_all(Self::get_jobs_dir(&self.config)).await?;
+                    rename(self.uploads.upload_dir(id), &job_dir).await
+                }
+                .await;
+
+                if let Err(error) = moved {
+                    error!(
+                        "Could not move upload {} to its job directory: {}",
+                        id, error
+                    );
+                    self.uploads.remove(id).await;
+                    return Err(HttpError::InternalError(None));
+                }
+
+                let in_file = format!("{}/file", job_dir);
+                let limits = self.config.processing_limits();
+                if let Err(error) = validation::validate_video(&in_file, limits).await {
+                    if let Err(error) = remove_dir_all(&job_dir).await {
+                        error!("Failed to clean up job directory '{}': {}", &job_dir, error);
+                    }
+
+                    return Err(error);
+                }
+
+                let job = TranscodeJob::new(
+                    job_id.clone(),
+                    user_id.to_owned(),
+                    in_file,
+                    job_dir.clone(),
+                    options,
                 );
+
+                self.queue.enqueue(job).await
+            }
+            .await;
+
+            // The upload is gone, so its bytes no longer count against the
+            // user's quotas
+            if let Err(error) = result {
+                self.quotas.release(user_id, &info.reservation).await;
+                return Err(error);
             }
+
+            let content = serde_json::to_vec(&json!({ "jobId": job_id })).unwrap();
+            return Ok(Self::build_upload_response(StatusCode::OK, &info)
+                .header(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static(mime::APPLICATION_JSON.as_ref()),
+                )
+                .body(Body::from(content))
+                .unwrap());
         }
 
-        // Save the uploaded file there
-        let content = body::aggregate(body)
-            .await
-            .map(|mut buf| buf.copy_to_bytes(buf.remaining()))
-            .map_err(|error| {
-                error!("Unexpected error while collecting body: {}", error);
-                HttpError::InternalError(None)
-            })?;
+        Ok(Self::build_upload_response(StatusCode::NO_CONTENT, &info)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Handles a upload-api `DELETE` request
+    pub async fn handle_delete_request(&self, path: &str, session: &Session) -> HttpResult {
+        let (root_path, relative_path) =
+            Self::extract_paths(path).map_err(|message| HttpError::BadRequest(message.into()))?;
 
-        let saved_file = {
-            let result = Self::save_content(content.as_ref(), &tmp_dir).await;
-            if result.is_err() {
-                remove_temp_dir(&tmp_dir).await;
+        match root_path {
+            "usage" => {
+                let user_id = Self::get_usage_user_id(relative_path, session)?;
+                self.quotas.reset(user_id).await?;
+                info!("Reset the upload usage of user {}", user_id);
+
+                Ok(Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::empty())
+                    .unwrap())
             }
+            _ => Err(Self::build_invalid_path_error(path)),
+        }
+    }
 
-            result?
-        };
+    /// Handles requests for the upload usage of a user. Only available to
+    /// admins.
+    async fn handle_get_usage(&self, relative_path: &str, session: &Session) -> HttpResult {
+        let user_id = Self::get_usage_user_id(relative_path, session)?;
+        let usage = self.quotas.get_usage(user_id).await?;
+        Ok(http_util::build_json_response(&usage, StatusCode::OK))
+    }
 
-        // Get the video duration
-        let duration = {
-            let result = Self::get_video_duration(&saved_file).await;
-            if result.is_err() {
-                remove_temp_dir(&tmp_dir).await;
-            }
+    /// Handles requests to create a resumable upload
+    async fn handle_create_upload(
+        &self,
+        header_map: &HeaderMap,
+        user_id: &str,
+        options: ClipOptions,
+    ) -> HttpResult {
+        let length = Self::get_length_header(header_map, "upload-length")?
+            .ok_or_else(|| HttpError::BadRequest("Missing Upload-Length header".into()))?;
+
+        let limit = self.config.video_size_limit();
+        Self::check_size_limit(user_id, Some(length), limit)?;
+        if length == 0 {
+            return Err(HttpError::BadRequest(
+                "Invalid Upload-Length value: 0".into(),
+            ));
+        }
 
-            result?
+        // Abandoned uploads no longer count against their user's quotas
+        for upload in self.uploads.remove_expired().await {
+            self.quotas
+                .release(&upload.user_id, &upload.reservation)
+                .await;
+        }
+
+        // The whole length counts against the user's quotas up front
+        let reservation = self.quotas.reserve(user_id, Some(length), length).await?;
+        let id = match self
+            .uploads
+            .create(user_id, length, options, &reservation)
+            .await
+        {
+            Ok(id) => id,
+            Err(error) => {
+                self.quotas.release(user_id, &reservation).await;
+                return Err(error);
+            }
         };
 
-        // Generate the web media
-        let preview_data = {
-            let result =
-                Self::generate_web_media(&saved_file, duration / 2, target_file_path, &tmp_dir)
-                    .await;
+        let location = format!("/upload-api/v1/video/uploads/{}", id);
+        Ok(Response::builder()
+            .status(StatusCode::CREATED)
+            .header(header::LOCATION, location)
+            .header("tus-resumable", TUS_VERSION)
+            .body(Body::empty())
+            .unwrap())
+    }
+
+    /// Handles video upload requests. The video is processed in the
+    /// background; the response contains the id of the job to poll.
+    async fn handle_video_upload(
+        &self,
+        body: Body,
+        user_id: &str,
+        options: ClipOptions,
+        reservation: &Reservation,
+    ) -> HttpResult {
+        // Create the job's directory
+        let job_id = build_random_id();
+        let job_dir = format!("{}/{}", Self::get_jobs_dir(&self.config), job_id);
+
+        create_dir_all(&job_dir).await.map_err(|error| {
+            error!("Could not create job directory: {}", error);
+            HttpError::InternalError(None)
+        })?;
+
+        // Save the uploaded file there and make sure it can be processed
+        let result = async {
+            let (in_file, length) = Self::save_content(body, &job_dir, reservation.bytes()).await?;
+            self.quotas.commit(user_id, reservation, length).await?;
 
-            remove_temp_dir(&tmp_dir).await;
-            result?
+            validation::validate_video(&in_file, self.config.processing_limits()).await?;
+            Ok(in_file)
+        }
+        .await;
+
+        let in_file = match result {
+            Ok(in_file) => in_file,
+            Err(error) => {
+                if let Err(error) = remove_dir_all(&job_dir).await {
+                    error!("Failed to clean up job directory '{}': {}", &job_dir, error);
+                }
+
+                return Err(error);
+            }
         };
 
+        // Queue it for processing
+        let job = TranscodeJob::new(
+            job_id.clone(),
+            user_id.to_owned(),
+            in_file,
+            job_dir,
+            options,
+        );
+        self.queue.enqueue(job).await?;
+
+        let resp_body = json!({ "jobId": job_id });
+        let mut response = http_util::build_json_response(&resp_body, StatusCode::ACCEPTED);
+        response.headers_mut().insert(
+            header::LOCATION,
+            HeaderValue::from_str(&format!("/upload-api/v1/jobs/{}", job_id)).unwrap(),
+        );
+
+        Ok(response)
+    }
+
+    /// Handles image upload requests
+    async fn handle_image_upload(
+        &self,
+        body: Body,
+        user_id: &str,
+        reservation: &Reservation,
+    ) -> HttpResult {
+        // Create the temporary directory
+        let tmp_dir = format!(
+            "{}/images/{}",
+            self.config.video_tmp_dir(),
+            build_random_id()
+        );
+
+        create_dir_all(&tmp_dir).await.map_err(|error| {
+            error!("Could not create temporary directory: {}", error);
+            HttpError::InternalError(None)
+        })?;
+
+        // Save the uploaded file there and publish it
+        let result = async {
+            let (saved_file, length) =
+                Self::save_content(body, &tmp_dir, reservation.bytes()).await?;
+            self.quotas.commit(user_id, reservation, length).await?;
+
+            let format = images::detect_format(&saved_file).await?.ok_or_else(|| {
+                HttpError::BadRequest("Only PNG, JPEG and WebP images are supported".into())
+            })?;
+
+            validation::validate_image(&saved_file, self.config.processing_limits()).await?;
+
+            let hash = MediaStore::hash_file(&saved_file).await?;
+            self.publish_image(&saved_file, &hash, format, &tmp_dir)
+                .await
+                .map(|preview_data| (hash, preview_data))
+        }
+        .await;
+
+        if let Err(error) = remove_dir_all(&tmp_dir).await {
+            error!(
+                "Failed to clean up temporary directory '{}': {}",
+                &tmp_dir, error
+            );
+        }
+
+        let (file_name, preview_data) = result?;
         let resp_body = json!({
-            "fileName": target_file_name,
-            "previewData": preview_data
+            "fileName": file_name,
+            "previewData": preview_data,
+            "type": "image",
         });
 
         Ok(http_util::build_json_response(&resp_body, StatusCode::OK))
     }
 
-    /// Saves the specified content to disk for processing
-    async fn save_content(content: &[u8], out_dir: &str) -> Result<String, HttpError> {
+    /// Publishes a saved image under the hash of its content. Images that
+    /// were already published are reused rather than processed again.
+    async fn publish_image(
+        &self,
+        saved_file: &str,
+        hash: &str,
+        format: images::ImageFormat,
+        tmp_dir: &str,
+    ) -> Result<Value, HttpError> {
+        let _guard = self.store.lock(hash).await;
+        if let Some(media) = self.store.get(hash).await? {
+            info!("Uploaded image matches published image {}", hash);
+            return Ok(media["previewData"].clone());
+        }
+
+        info!("Processing uploaded {:?} image", format);
+        let target_file_path = self.store.file_path(hash);
+        let result = async {
+            let preview_data = images::generate_web_images(
+                saved_file,
+                &target_file_path,
+                tmp_dir,
+                self.config.processing_limits(),
+            )
+            .await?;
+
+            let files = images::variant_file_names(hash);
+            self.store
+                .publish(hash, "image", &files, &preview_data, json!([]))
+                .await
+                .map(|_| json!(preview_data))
+        }
+        .await;
+
+        if result.is_err() {
+            images::remove_web_images(&target_file_path).await;
+        }
+
+        result
+    }
+
+    /// Saves the specified request body to disk for processing. Returns the
+    /// path of the file and its length.
+    async fn save_content(
+        body: Body,
+        out_dir: &str,
+        limit: u64,
+    ) -> Result<(String, u64), HttpError> {
         let out_file = format!("{}/file", out_dir);
         let mut file = File::create(&out_file).await.map_err(|error| {
             error!("Could not create file: {}", error);
             HttpError::InternalError(None)
         })?;
 
-        file.write_all(content)
+        streaming::write_body(body, &mut file, limit)
             .await
-            .map(|_| out_file)
-            .map_err(|error| {
-                error!("Could not save content to file: {}", error);
-                HttpError::InternalError(None)
-            })
+            .map(|length| (out_file, length))
+            .map_err(|error| error.into_http_error())
     }
 
-    /// Gets the duration of the video
-    async fn get_video_duration(in_file: &str) -> Result<u64, HttpError> {
-        lazy_static! {
-            static ref DUR_REGEX: Regex = Regex::new(r"^(?P<duration>\d+)(?s).*$").unwrap();
-        }
+    /// Gets the directory holding the files of queued jobs
+    fn get_jobs_dir(config: &ServiceConfig) -> String {
+        format!("{}/jobs", config.video_tmp_dir())
+    }
 
-        let result = Command::new("ffprobe")
-            .args(&["-v", "error"])
-            .args(&["-show_entries", "format=duration"])
-            .args(&["-print_format", "default=noprint_wrappers=1:nokey=1"])
-            .arg(in_file)
-            .output()
-            .await
-            .map_err(|error| {
-                error!("Failed to run ffprobe process: {}", error);
-                HttpError::InternalError(None)
-            })?;
+    /// Gets the user id of a session
+    fn get_user_id(session: &Session) -> Result<&str, HttpError> {
+        match session {
+            Session::Valid(claims) | Session::Expired(claims) => Ok(claims.sub()),
+            _ => Err(HttpError::Unauthorized(None)),
+        }
+    }
 
-        if !result.status.success() {
-            error!("ffprobe execution failed: {}", result.status);
-            return Err(HttpError::InternalError(None));
+    /// Gets the id of the user from an upload usage path. e.g.
+    /// `v1/usage/{userId}`. Only admins can access the usage of users.
+    fn get_usage_user_id<'a>(
+        relative_path: &'a str,
+        session: &Session,
+    ) -> Result<&'a str, HttpError> {
+        lazy_static! {
+            static ref USER_ID_REGEX: Regex = Regex::new(r"^(?P<user_id>[\w:\-]+)$").unwrap();
         }
 
-        let duration_str = String::from_utf8_lossy(&result.stdout);
-        let captures = DUR_REGEX.captures(&duration_str).ok_or_else(|| {
-            error!("Unable to parse duration: {}", duration_str);
-            HttpError::InternalError(None)
-        })?;
+        match session {
+            Session::Valid(claims) | Session::Expired(claims) if claims.is_admin() => {}
+            _ => return Err(HttpError::Unauthorized(None)),
+        }
 
-        Ok(captures["duration"].parse::<u64>().unwrap())
+        USER_ID_REGEX
+            .captures(relative_path)
+            .map(|captures| captures.name("user_id").unwrap().as_str())
+            .ok_or_else(|| {
+                let message = format!("Invalid user id '{}'", relative_path);
+                HttpError::BadRequest(message.into())
+            })
     }
 
-    /// Processes a video file for web embedding into the same dir as the input
-    /// file. See https://developers.google.com/media/vp9/settings/vod/ and https://trac.ffmpeg.org/wiki/Encode/H.264 for reference.
-    async fn generate_web_media(
-        in_file: &str,
-        preview_point_secs: u64,
-        target_file: String,
-        tmp_dir: &str,
-    ) -> Result<String, HttpError> {
-        let target_webm = format!("{}.webm", target_file);
-        let target_mp4 = format!("{}.mp4", target_file);
-        let tmp_image = format!("{}/file.jpg", tmp_dir);
-
-        // Configure arguments for scaling and removing audio
-        let mut command = Command::new("ffmpeg");
-        let command = command
-            .arg("-an")
-            .args(&["-r", "25"])
-            .args(&["-i", &in_file])
-            .args(&[
-                "-filter_complex",
-                "[0:v]scale=320:-1,pad=320:240:(ow-iw)/2:(oh-ih)/2,split=2[webm][mp4]",
-            ]);
-
-        // Configure arguments for webm output
-        let command = command
-            .args(&["-map", "[webm]"])
-            .args(&["-c:v", "libvpx-vp9"])
-            .args(&["-crf", "37"])
-            .args(&["-tile-columns", "0"])
-            .args(&["-threads", "2"])
-            .arg("-an")
-            .arg(&target_webm);
-
-        // Configure arguments for mp4 output
-        let command = command
-            .args(&["-map", "[mp4]"])
-            .args(&["-c:v", "h264"])
-            .args(&["-threads", "2"])
-            .arg("-an")
-            .arg(&target_mp4);
-
-        // Configure arguments for creating a preview image
-        let seek_arg = format!("00:00:{:02}", preview_point_secs);
-        let command = command
-            .args(&["-ss", &seek_arg])
-            .args(&["-frames:v", "1"])
-            .args(&["-filter:v", "scale=8:-1,pad=8:6:(ow-iw)/2:(oh-ih)/2"])
-            .arg(&tmp_image);
-
-        // Execute the command and read the resulting preview file
-        let result = command.output().await.map_err(|error| {
-            error!("Failed to run ffmpeg process: {}", error);
-            HttpError::InternalError(None)
-        })?;
+    /// Makes sure an upload of the specified length is within the size limit
+    fn check_size_limit(user_id: &str, length: Option<u64>, limit: u32) -> Result<(), HttpError> {
+        match length {
+            Some(length) if length > limit as u64 => {
+                warn!(
+                    "User {} tried to upload a large file of size {}",
+                    user_id, length
+                );
 
-        if !result.status.success() {
-            error!("ffmpeg processing failed: {}", result.status);
-            error!("{}", String::from_utf8_lossy(&result.stderr));
-            return Err(HttpError::InternalError(None));
+                Err(HttpError::PayloadTooLarge(Some("File is too big".into())))
+            }
+            _ => Ok(()),
         }
+    }
 
-        let mut file = File::open(tmp_image).await.map_err(|error| {
-            error!("Failed to open preview image: {}", error);
-            HttpError::InternalError(None)
-        })?;
+    /// Gets the value of an optional length or offset header
+    fn get_length_header(header_map: &HeaderMap, name: &str) -> Result<Option<u64>, HttpError> {
+        let entry = match header_map.get(name) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
 
-        let mut content = Vec::<u8>::new();
-        file.read_to_end(&mut content)
-            .await
-            .map(move |_| base64::encode(&content))
-            .map_err(|error| {
-                error!("Failed to read preview image: {}", error);
-                HttpError::InternalError(None)
-            })
+        let convert_error = |error: &dyn Display| {
+            let message = format!("Invalid {} value: {}", name, error);
+            HttpError::BadRequest(message.into())
+        };
+
+        let value = entry.to_str().map_err(|error| convert_error(&error))?;
+        value
+            .parse::<u64>()
+            .map(Some)
+            .map_err(|error| convert_error(&error))
     }
 
-    /// Gets the content length header value
-    fn get_content_length(header_map: &HeaderMap) -> Result<u32, HttpError> {
-        let entry = header_map
-            .get(header::CONTENT_LENGTH)
-            .ok_or_else(|| HttpError::BadRequest("Missing Content-Length header".into()))?;
+    /// Builds a response with the tus headers describing an upload
+    fn build_upload_response(status: StatusCode, info: &UploadInfo) -> response::Builder {
+        Response::builder()
+            .status(status)
+            .header("tus-resumable", TUS_VERSION)
+            .header("upload-offset", info.offset)
+            .header("upload-length", info.length)
+            .header(header::CACHE_CONTROL, "no-store")
+    }
 
-        fn convert_error<T: Display>(error: T) -> HttpError {
-            let message = format!("Invalid Content-Length value: {}", error);
-            HttpError::BadRequest(message.into())
+    /// Extracts the id of a resumable upload from a path. e.g.
+    /// `v1/video/uploads/{id}`
+    fn extract_upload_id(path: &str) -> Result<&str, HttpError> {
+        lazy_static! {
+            static ref ID_REGEX: Regex = Regex::new(r"^uploads/+(?P<id>[A-Za-z0-9]+)$").unwrap();
         }
 
-        let length = entry.to_str().map_err(convert_error)?;
-        length.parse::<u32>().map_err(convert_error)
+        let (root_path, relative_path) =
+            Self::extract_paths(path).map_err(|message| HttpError::BadRequest(message.into()))?;
+
+        match (root_path, ID_REGEX.captures(relative_path)) {
+            ("video", Some(captures)) => Ok(captures.name("id").unwrap().as_str()),
+            _ => Err(Self::build_invalid_path_error(path)),
+        }
+    }
+
+    fn build_invalid_path_error(path: &str) -> HttpError {
+        let message = format!(
+            "Invalid upload-api path '{path}' for the specified method",
+            path = path
+        );
+
+        HttpError::BadRequest(message.into())
     }
 
     fn extract_paths<'a>(path: &'a str) -> Result<(&'a str, &'a str), String> {
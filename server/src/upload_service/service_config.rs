@@ -1,15 +1,36 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_derive::Deserialize;
+use std::collections::HashSet;
+
 pub struct ServiceConfig {
     publish_dir: String,
     video_tmp_dir: String,
-    video_size_limit: u32,
+    limits: UploadLimits,
+    transcode: TranscodeConfig,
+    processing_limits: ProcessingLimits,
+    media_gc_interval: u32,
+    media_gc_grace_period: u32,
 }
 
 impl ServiceConfig {
-    pub fn new(publish_dir: String, video_tmp_dir: String, video_size_limit: u32) -> Self {
+    pub fn new(
+        publish_dir: String,
+        video_tmp_dir: String,
+        limits: UploadLimits,
+        transcode: TranscodeConfig,
+        processing_limits: ProcessingLimits,
+        media_gc_interval: u32,
+        media_gc_grace_period: u32,
+    ) -> Self {
         Self {
             publish_dir,
             video_tmp_dir,
-            video_size_limit,
+            limits,
+            transcode,
+            processing_limits,
+            media_gc_interval,
+            media_gc_grace_period,
         }
     }
 
@@ -22,6 +43,317 @@ impl ServiceConfig {
     }
 
     pub fn video_size_limit(&self) -> u32 {
-        self.video_size_limit
+        self.limits.video_size_limit
+    }
+
+    pub fn image_size_limit(&self) -> u32 {
+        self.limits.image_size_limit
+    }
+
+    pub fn upload_quotas(&self) -> &[UploadQuota] {
+        &self.limits.quotas
+    }
+
+    pub fn transcode(&self) -> &TranscodeConfig {
+        &self.transcode
+    }
+
+    pub fn processing_limits(&self) -> &ProcessingLimits {
+        &self.processing_limits
+    }
+
+    /// The number of seconds between garbage collections of unreferenced
+    /// uploads
+    pub fn media_gc_interval(&self) -> u32 {
+        self.media_gc_interval
+    }
+
+    /// The number of seconds an unreferenced upload is kept for. Gives users
+    /// time to reference their uploads in a proposal.
+    pub fn media_gc_grace_period(&self) -> u32 {
+        self.media_gc_grace_period
+    }
+}
+
+/// The limits on the size and number of files users can upload
+pub struct UploadLimits {
+    video_size_limit: u32,
+    image_size_limit: u32,
+    quotas: Vec<UploadQuota>,
+}
+
+impl UploadLimits {
+    /// Creates new upload limits. Fails if any of the quotas are invalid.
+    pub fn new(
+        video_size_limit: u32,
+        image_size_limit: u32,
+        quotas: Vec<UploadQuota>,
+    ) -> Result<Self, String> {
+        for quota in &quotas {
+            quota.validate()?;
+        }
+
+        Ok(Self {
+            video_size_limit,
+            image_size_limit,
+            quotas,
+        })
+    }
+}
+
+/// Limits the files each user can upload within a rolling window of time
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct UploadQuota {
+    /// The length of the window in seconds
+    pub window: u32,
+    #[serde(default)]
+    pub max_files: Option<u32>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+impl UploadQuota {
+    fn validate(&self) -> Result<(), String> {
+        if self.window == 0 {
+            return Err("The window of an upload quota must be non zero".to_owned());
+        }
+
+        if self.max_files.is_none() && self.max_bytes.is_none() {
+            return Err(format!(
+                "The upload quota with a window of {} seconds has no limits",
+                self.window
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Limits on the uploaded media that is accepted and on the ffmpeg and
+/// ffprobe processes that handle it. Limits that are not configured take
+/// their default values.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct ProcessingLimits {
+    /// The longest video that is accepted in seconds
+    pub max_video_duration: u32,
+    pub max_video_width: u32,
+    pub max_video_height: u32,
+    pub max_image_width: u32,
+    pub max_image_height: u32,
+    /// The number of seconds an upload can take to be inspected
+    pub probe_timeout: u32,
+    /// The number of seconds each processing step can take
+    pub process_timeout: u32,
+    /// The number of bytes of memory each process can use
+    pub memory_limit: u64,
+}
+
+impl Default for ProcessingLimits {
+    fn default() -> Self {
+        Self {
+            max_video_duration: 120,
+            max_video_width: 3840,
+            max_video_height: 2160,
+            max_image_width: 8192,
+            max_image_height: 8192,
+            probe_timeout: 30,
+            process_timeout: 900,
+            memory_limit: 4 * 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// The configuration of the background video transcoding
+pub struct TranscodeConfig {
+    workers: u32,
+    queue_size: u32,
+    profiles: Vec<TranscodeProfile>,
+    preview_position: f64,
+}
+
+impl TranscodeConfig {
+    /// Creates a new transcoding configuration. Fails if any of the profiles
+    /// are invalid.
+    pub fn new(
+        workers: u32,
+        queue_size: u32,
+        profiles: Vec<TranscodeProfile>,
+        preview_position: f64,
+    ) -> Result<Self, String> {
+        if workers == 0 || queue_size == 0 {
+            return Err(
+                "The number of transcode workers and the queue size must be non zero".to_owned(),
+            );
+        }
+
+        if profiles.is_empty() {
+            return Err("At least one transcode profile is required".to_owned());
+        }
+
+        let mut names = HashSet::new();
+        for profile in &profiles {
+            profile.validate()?;
+            if !names.insert(profile.name.as_str()) {
+                return Err(format!("Duplicate transcode profile '{}'", profile.name));
+            }
+        }
+
+        if !(0.0..=1.0).contains(&preview_position) {
+            return Err("The preview position must be between 0 and 1".to_owned());
+        }
+
+        Ok(Self {
+            workers,
+            queue_size,
+            profiles,
+            preview_position,
+        })
+    }
+
+    pub fn workers(&self) -> u32 {
+        self.workers
+    }
+
+    pub fn queue_size(&self) -> u32 {
+        self.queue_size
+    }
+
+    /// The renditions each video is transcoded to
+    pub fn profiles(&self) -> &[TranscodeProfile] {
+        &self.profiles
+    }
+
+    /// The position of the preview frame as a fraction of the video's
+    /// duration
+    pub fn preview_position(&self) -> f64 {
+        self.preview_position
+    }
+}
+
+/// A rendition videos are transcoded to
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TranscodeProfile {
+    /// Used in the file names of the rendition
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub frame_rate: u32,
+    #[serde(default)]
+    pub keep_audio: bool,
+    /// The formats the rendition is published in. Clients use the first
+    /// format they support.
+    pub formats: Vec<VideoFormat>,
+}
+
+/// Reproduces the rendition of the original pipeline: 320x240 at 25fps
+/// without audio in VP9 and H.264
+impl Default for TranscodeProfile {
+    fn default() -> Self {
+        Self {
+            name: "small".to_owned(),
+            width: 320,
+            height: 240,
+            frame_rate: 25,
+            keep_audio: false,
+            formats: vec![
+                VideoFormat {
+                    container: "webm".to_owned(),
+                    codec: Some("libvpx-vp9".to_owned()),
+                    crf: 37,
+                },
+                VideoFormat {
+                    container: "mp4".to_owned(),
+                    codec: Some("h264".to_owned()),
+                    crf: 23,
+                },
+            ],
+        }
+    }
+}
+
+impl TranscodeProfile {
+    fn validate(&self) -> Result<(), String> {
+        lazy_static! {
+            static ref NAME_REGEX: Regex = Regex::new(r"^[A-Za-z0-9_\-]+$").unwrap();
+        }
+
+        if !NAME_REGEX.is_match(&self.name) {
+            return Err(format!("Invalid transcode profile name '{}'", self.name));
+        }
+
+        // Most encoders require even dimensions
+        if self.width == 0 || self.height == 0 || self.width % 2 != 0 || self.height % 2 != 0 {
+            return Err(format!(
+                "The size of transcode profile '{}' must be even and non zero",
+                self.name
+            ));
+        }
+
+        if self.frame_rate == 0 {
+            return Err(format!(
+                "Invalid frame rate for transcode profile '{}'",
+                self.name
+            ));
+        }
+
+        if self.formats.is_empty() {
+            return Err(format!(
+                "Transcode profile '{}' requires at least one format",
+                self.name
+            ));
+        }
+
+        for format in &self.formats {
+            if format.mime_type().is_none() {
+                return Err(format!(
+                    "Unsupported container '{}' in transcode profile '{}'",
+                    format.container, self.name
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A format a rendition is encoded in
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct VideoFormat {
+    /// Either `webm` or `mp4`
+    pub container: String,
+    /// The ffmpeg video encoder. Defaults to VP9 for webm and H.264 for mp4.
+    #[serde(default)]
+    pub codec: Option<String>,
+    /// The constant rate factor of the encoder. Lower values result in a
+    /// higher quality.
+    pub crf: u32,
+}
+
+impl VideoFormat {
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match self.container.as_str() {
+            "webm" => Some("video/webm"),
+            "mp4" => Some("video/mp4"),
+            _ => None,
+        }
+    }
+
+    pub fn video_codec(&self) -> &str {
+        match (&self.codec, self.container.as_str()) {
+            (Some(codec), _) => codec,
+            (None, "webm") => "libvpx-vp9",
+            (None, _) => "h264",
+        }
+    }
+
+    pub fn audio_codec(&self) -> &'static str {
+        match self.container.as_str() {
+            "webm" => "libopus",
+            _ => "aac",
+        }
     }
 }
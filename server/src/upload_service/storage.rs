@@ -0,0 +1,231 @@
+use crate::{
+    database_adapters::{
+        couchbase::UploadMedia,
+        AdapterError,
+    },
+    http_service::HttpError,
+    util::get_timestamp,
+};
+use crypto::{
+    digest::Digest,
+    sha2::Sha256,
+};
+use log::{
+    error,
+    info,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        Mutex,
+    },
+};
+use tokio::{
+    fs::{
+        remove_file,
+        File,
+    },
+    io::AsyncReadExt,
+    sync::{
+        Mutex as AsyncMutex,
+        OwnedMutexGuard,
+    },
+};
+
+/// The maximum number of unreferenced uploads removed per collection
+const GC_BATCH_SIZE: u16 = 100;
+
+/// The locks of the content hashes currently in use
+type HashLocks = Mutex<HashMap<String, Arc<AsyncMutex<()>>>>;
+
+/// Releases the lock on a content hash when dropped
+pub struct HashGuard<'a> {
+    locks: &'a HashLocks,
+    hash: String,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for HashGuard<'_> {
+    fn drop(&mut self) {
+        // Forget the lock unless other tasks are waiting for it. Only the
+        // map and this guard refer to the lock otherwise.
+        let mut locks = self.locks.lock().unwrap();
+        let unused = locks
+            .get(&self.hash)
+            .map_or(false, |lock| Arc::strong_count(lock) <= 2);
+
+        if unused {
+            locks.remove(&self.hash);
+        }
+    }
+}
+
+/// Content addressed storage of published uploads. Uploads are named by the
+/// SHA-256 hash of their original content so identical uploads are only
+/// processed and stored once. The documents and proposals referencing each
+/// upload are tracked so unreferenced uploads can be garbage collected.
+pub struct MediaStore {
+    publish_dir: String,
+    adapter: Arc<UploadMedia>,
+    locks: HashLocks,
+}
+
+impl MediaStore {
+    pub fn new(publish_dir: String, adapter: Arc<UploadMedia>) -> Self {
+        Self {
+            publish_dir,
+            adapter,
+            locks: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Computes the content hash of a file
+    pub async fn hash_file(in_file: &str) -> Result<String, HttpError> {
+        let convert_error = |error: std::io::Error| {
+            error!("Failed to hash file '{}': {}", in_file, error);
+            HttpError::InternalError(None)
+        };
+
+        let mut file = File::open(in_file).await.map_err(convert_error)?;
+        let mut hasher = Sha256::new();
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let count = file.read(&mut buffer).await.map_err(convert_error)?;
+            if count == 0 {
+                break;
+            }
+
+            hasher.input(&buffer[..count]);
+        }
+
+        Ok(hasher.result_str())
+    }
+
+    /// Derives the hash of a variant of some content, e.g. the content
+    /// processed with different options
+    pub fn hash_variant(hash: &str, variant: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input_str(&format!("{}:{}", hash, variant));
+        hasher.result_str()
+    }
+
+    /// Locks a content hash so that it is only published or removed by one
+    /// task at a time. Waits for any current holder to release it.
+    pub async fn lock(&self, hash: &str) -> HashGuard<'_> {
+        let lock = self
+            .locks
+            .lock()
+            .unwrap()
+            .entry(hash.to_owned())
+            .or_default()
+            .clone();
+
+        HashGuard {
+            locks: &self.locks,
+            hash: hash.to_owned(),
+            _guard: lock.lock_owned().await,
+        }
+    }
+
+    /// Gets the record of a published upload. The upload is marked as
+    /// recently used so it is not collected before it can be referenced.
+    pub async fn get(&self, hash: &str) -> Result<Option<Value>, HttpError> {
+        match self.adapter.get(hash).await {
+            Ok(media) => {
+                self.adapter
+                    .touch(hash, get_timestamp())
+                    .await
+                    .map_err(|_| HttpError::InternalError(None))?;
+
+                Ok(Some(media))
+            }
+            Err(AdapterError::DocumentNotFound) => Ok(None),
+            Err(_) => Err(HttpError::InternalError(None)),
+        }
+    }
+
+    /// Records a newly published upload, the files generated for it and the
+    /// metadata of any video renditions
+    pub async fn publish(
+        &self,
+        hash: &str,
+        media_type: &str,
+        files: &[String],
+        preview_data: &str,
+        renditions: Value,
+    ) -> Result<(), HttpError> {
+        let time_stamp = get_timestamp();
+        let media = json!({
+            "type": "upload_media",
+            "mediaType": media_type,
+            "files": files,
+            "previewData": preview_data,
+            "renditions": renditions,
+            "refs": [],
+            "created": time_stamp,
+            "updated": time_stamp,
+        });
+
+        self.adapter
+            .insert(hash, &media)
+            .await
+            .map_err(|_| HttpError::InternalError(None))
+    }
+
+    /// Gets the path of a published file
+    pub fn file_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.publish_dir, file_name)
+    }
+
+    /// Removes uploads that have not been referenced by any document or
+    /// proposal for the specified number of seconds. Returns the number of
+    /// uploads removed.
+    pub async fn collect_garbage(&self, grace_period: u64) -> usize {
+        let before = get_timestamp().saturating_sub(grace_period);
+        let hashes = match self.adapter.get_unreferenced(before, GC_BATCH_SIZE).await {
+            Ok(hashes) => hashes,
+            Err(error) => {
+                error!("Failed to get unreferenced uploads: {:?}", error);
+                return 0;
+            }
+        };
+
+        let mut removed = 0;
+        for hash in hashes.iter().filter_map(|hash| hash.as_str()) {
+            let _guard = self.lock(hash).await;
+            let media = match self.adapter.remove_unreferenced(hash).await {
+                Ok(Some(media)) => media,
+                Ok(None) => continue,
+                Err(error) => {
+                    error!("Failed to remove upload record '{}': {:?}", hash, error);
+                    continue;
+                }
+            };
+
+            let files = media["files"].as_array().map_or(&[][..], |files| files);
+            for file_name in files.iter().filter_map(|file| file.as_str()) {
+                // Files are named after the hash; never follow other paths
+                if !file_name.starts_with(hash) || file_name.contains('/') {
+                    continue;
+                }
+
+                if let Err(error) = remove_file(self.file_path(file_name)).await {
+                    error!("Failed to remove upload file '{}': {}", file_name, error);
+                }
+            }
+
+            removed += 1;
+        }
+
+        if removed > 0 {
+            info!("Removed {} unreferenced uploads", removed);
+        }
+
+        removed
+    }
+}
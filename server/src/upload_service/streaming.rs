@@ -0,0 +1,65 @@
+use crate::http_service::HttpError;
+use hyper::body::{
+    Body,
+    HttpBody,
+};
+use log::error;
+use tokio::{
+    fs::File,
+    io::AsyncWriteExt,
+};
+
+/// An error that occurred while writing a request body to a file
+#[derive(Debug)]
+pub enum WriteError {
+    /// The body exceeded the maximum number of bytes
+    TooLarge,
+    /// The body could not be received. e.g. the connection was dropped
+    Body(hyper::Error),
+    /// The body could not be written to the file
+    Io(std::io::Error),
+}
+
+impl WriteError {
+    pub fn into_http_error(self) -> HttpError {
+        match self {
+            WriteError::TooLarge => HttpError::PayloadTooLarge(Some("File is too big".into())),
+            WriteError::Body(error) => {
+                error!("Unexpected error while receiving body: {}", error);
+                HttpError::BadRequest("Incomplete request body".into())
+            }
+            WriteError::Io(error) => {
+                error!("Could not save content to file: {}", error);
+                HttpError::InternalError(None)
+            }
+        }
+    }
+}
+
+/// Writes a request body to a file chunk by chunk as it is received. Fails
+/// as soon as more than `limit` bytes are received. Returns the number of
+/// bytes written.
+pub async fn write_body(mut body: Body, file: &mut File, limit: u64) -> Result<u64, WriteError> {
+    let mut written: u64 = 0;
+    let result = loop {
+        let chunk = match body.data().await {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(error)) => break Err(WriteError::Body(error)),
+            None => break Ok(written),
+        };
+
+        if written + chunk.len() as u64 > limit {
+            break Err(WriteError::TooLarge);
+        }
+
+        if let Err(error) = file.write_all(&chunk).await {
+            break Err(WriteError::Io(error));
+        }
+
+        written += chunk.len() as u64;
+    };
+
+    // Make sure whatever was received is on disk
+    file.flush().await.map_err(WriteError::Io)?;
+    result
+}
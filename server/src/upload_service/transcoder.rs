@@ -0,0 +1,684 @@
+use super::{
+    process,
+    service_config::{
+        TranscodeConfig,
+        TranscodeProfile,
+        VideoFormat,
+    },
+    storage::MediaStore,
+    ProcessingLimits,
+    ServiceConfig,
+};
+use crate::{
+    database_adapters::couchbase::UploadJobs,
+    http_service::{
+        util as http_util,
+        HttpError,
+    },
+    util::get_timestamp,
+};
+use log::{
+    error,
+    info,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+use tokio::{
+    fs::{
+        remove_dir_all,
+        remove_file,
+        File,
+    },
+    io::AsyncReadExt,
+    process::Command,
+    sync::{
+        mpsc::{
+            self,
+            error::TrySendError,
+        },
+        Mutex,
+    },
+};
+
+/// The error reported for jobs that failed to process
+const PROCESSING_ERROR: &str = "Unable to process the video";
+
+/// The highest frame rate a clip can be published at
+const MAX_FRAME_RATE: u32 = 120;
+
+/// The number of seconds finished and failed jobs are kept for clients to
+/// poll
+pub const FINISHED_JOB_RETENTION: u32 = 7 * 24 * 60 * 60;
+
+/// How an uploaded clip should be cut and timed. Frames are counted from 0
+/// in the uploaded video.
+#[derive(Default, Serialize, Deserialize)]
+pub struct ClipOptions {
+    /// The first frame to keep
+    #[serde(rename = "trimStart", default)]
+    pub trim_start: Option<u32>,
+    /// The last frame to keep
+    #[serde(rename = "trimEnd", default)]
+    pub trim_end: Option<u32>,
+    /// The frame rate to publish the clip at instead of the profiles' rate
+    #[serde(rename = "frameRate", default)]
+    pub frame_rate: Option<u32>,
+}
+
+impl ClipOptions {
+    /// Reads the options from the `trimStart`, `trimEnd` and `frameRate`
+    /// query parameters of an upload request
+    pub fn from_query(query: Option<&str>) -> Result<Self, HttpError> {
+        let query_params = query.map(http_util::parse_query_string).unwrap_or_default();
+        let get_param = |name: &str| -> Result<Option<u32>, HttpError> {
+            query_params
+                .get(name)
+                .map(|value| value.parse::<u32>())
+                .transpose()
+                .map_err(|error| {
+                    let message = format!("Invalid {} value: {}", name, error);
+                    HttpError::BadRequest(message.into())
+                })
+        };
+
+        let options = Self {
+            trim_start: get_param("trimStart")?,
+            trim_end: get_param("trimEnd")?,
+            frame_rate: get_param("frameRate")?,
+        };
+
+        if let (Some(start), Some(end)) = (options.trim_start, options.trim_end) {
+            if end < start {
+                return Err(HttpError::BadRequest(
+                    "trimEnd must not be before trimStart".into(),
+                ));
+            }
+        }
+
+        if let Some(frame_rate) = options.frame_rate {
+            if frame_rate == 0 || frame_rate > MAX_FRAME_RATE {
+                let message = format!("frameRate must be between 1 and {}", MAX_FRAME_RATE);
+                return Err(HttpError::BadRequest(message.into()));
+            }
+        }
+
+        Ok(options)
+    }
+
+    /// Gets a key identifying the options if any are set
+    fn key(&self) -> Option<String> {
+        if self.trim_start.is_none() && self.trim_end.is_none() && self.frame_rate.is_none() {
+            return None;
+        }
+
+        let to_string = |value: Option<u32>| value.map_or(String::new(), |v| v.to_string());
+        Some(format!(
+            "trim={}-{};fps={}",
+            to_string(self.trim_start),
+            to_string(self.trim_end),
+            to_string(self.frame_rate)
+        ))
+    }
+}
+
+/// An uploaded video waiting to be processed
+pub struct TranscodeJob {
+    pub id: String,
+    pub user_id: String,
+    /// The uploaded video file
+    pub in_file: String,
+    /// The directory holding the uploaded file. Removed once processed.
+    pub job_dir: String,
+    pub options: ClipOptions,
+    created: u64,
+}
+
+impl TranscodeJob {
+    pub fn new(
+        id: String,
+        user_id: String,
+        in_file: String,
+        job_dir: String,
+        options: ClipOptions,
+    ) -> Self {
+        Self {
+            id,
+            user_id,
+            in_file,
+            job_dir,
+            options,
+            created: get_timestamp(),
+        }
+    }
+}
+
+/// A bounded queue of uploaded videos processed by a fixed number of
+/// background workers. The state of each job is persisted as an upload job
+/// record.
+pub struct TranscodeQueue {
+    sender: mpsc::Sender<TranscodeJob>,
+    jobs_adapter: Arc<UploadJobs>,
+}
+
+impl TranscodeQueue {
+    /// Creates the queue and starts its workers
+    pub fn new(
+        config: Arc<ServiceConfig>,
+        jobs_adapter: Arc<UploadJobs>,
+        store: Arc<MediaStore>,
+    ) -> Self {
+        let transcode_config = config.transcode();
+        let (sender, receiver) = mpsc::channel(transcode_config.queue_size() as usize);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..transcode_config.workers() {
+            tokio::spawn(run_worker(
+                receiver.clone(),
+                config.clone(),
+                jobs_adapter.clone(),
+                store.clone(),
+            ));
+        }
+
+        Self {
+            sender,
+            jobs_adapter,
+        }
+    }
+
+    /// Queues a job for processing. Fails if the queue is full, in which case
+    /// the job's directory is removed.
+    pub async fn enqueue(&self, job: TranscodeJob) -> Result<(), HttpError> {
+        if let Err(error) = update_job(&self.jobs_adapter, &job, "queued", json!({})).await {
+            remove_job_dir(&job).await;
+            return Err(error);
+        }
+
+        match self.sender.try_send(job) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(job)) | Err(TrySendError::Closed(job)) => {
+                let fields = json!({ "error": "The server is too busy" });
+                let _ = update_job(&self.jobs_adapter, &job, "failed", fields).await;
+                remove_job_dir(&job).await;
+
+                let message = "Too many videos are being processed. Try again later".into();
+                Err(HttpError::ServiceUnavailable(Some(message)))
+            }
+        }
+    }
+}
+
+/// Processes queued jobs one at a time until the queue is closed
+async fn run_worker(
+    receiver: Arc<Mutex<mpsc::Receiver<TranscodeJob>>>,
+    config: Arc<ServiceConfig>,
+    jobs_adapter: Arc<UploadJobs>,
+    store: Arc<MediaStore>,
+) {
+    loop {
+        let job = receiver.lock().await.recv().await;
+        let job = match job {
+            Some(job) => job,
+            None => break,
+        };
+
+        info!("Processing upload job {} of user {}", job.id, job.user_id);
+        let _ = update_job(&jobs_adapter, &job, "running", json!({})).await;
+
+        let result = publish_video(&job, &config, &store).await;
+        remove_job_dir(&job).await;
+
+        let _ = match result {
+            Ok(media) => update_job(&jobs_adapter, &job, "done", media).await,
+            Err(message) => {
+                update_job(&jobs_adapter, &job, "failed", json!({ "error": message })).await
+            }
+        };
+    }
+}
+
+/// Persists the status of a job along with any additional fields
+async fn update_job(
+    jobs_adapter: &UploadJobs,
+    job: &TranscodeJob,
+    status: &str,
+    fields: Value,
+) -> Result<(), HttpError> {
+    let mut record = json!({
+        "type": "upload_job",
+        "userId": job.user_id,
+        "status": status,
+        "created": job.created,
+        "updated": get_timestamp(),
+    });
+
+    if let (Some(record), Value::Object(fields)) = (record.as_object_mut(), fields) {
+        record.extend(fields);
+    }
+
+    let retention = match status {
+        "done" | "failed" => Some(FINISHED_JOB_RETENTION),
+        _ => None,
+    };
+
+    jobs_adapter
+        .upsert(&job.id, &record, retention)
+        .await
+        .map_err(|error| {
+            error!("Failed to update upload job {}: {:?}", job.id, error);
+            HttpError::InternalError(None)
+        })
+}
+
+async fn remove_job_dir(job: &TranscodeJob) {
+    if let Err(error) = remove_dir_all(&job.job_dir).await {
+        error!(
+            "Failed to clean up job directory '{}': {}",
+            &job.job_dir, error
+        );
+    }
+}
+
+/// Publishes the video of a job under the hash of its content and clip
+/// options. Videos that were already published are reused rather than
+/// processed again.
+async fn publish_video(
+    job: &TranscodeJob,
+    config: &ServiceConfig,
+    store: &MediaStore,
+) -> Result<Value, String> {
+    let content_hash = MediaStore::hash_file(&job.in_file)
+        .await
+        .map_err(|_| PROCESSING_ERROR.to_owned())?;
+
+    // The same upload trimmed or timed differently is a different clip
+    let hash = match job.options.key() {
+        Some(key) => MediaStore::hash_variant(&content_hash, &key),
+        None => content_hash,
+    };
+
+    let _guard = store.lock(&hash).await;
+    let published = store
+        .get(&hash)
+        .await
+        .map_err(|_| PROCESSING_ERROR.to_owned())?;
+
+    if let Some(media) = published {
+        info!("Upload job {} matches published video {}", job.id, hash);
+        return Ok(json!({
+            "fileName": hash,
+            "previewData": media["previewData"],
+            "renditions": media["renditions"],
+        }));
+    }
+
+    let (preview_data, renditions) = process_video(
+        &job.in_file,
+        &job.job_dir,
+        &hash,
+        &job.options,
+        config.transcode(),
+        config.processing_limits(),
+        store,
+    )
+    .await?;
+
+    let mut files = renditions
+        .iter()
+        .map(|rendition| rendition.file.clone())
+        .collect::<Vec<String>>();
+
+    for rendition in &renditions {
+        if !files.contains(&rendition.poster) {
+            files.push(rendition.poster.clone());
+        }
+    }
+
+    let renditions = renditions
+        .iter()
+        .map(|rendition| rendition.to_json())
+        .collect::<Vec<Value>>();
+
+    store
+        .publish(&hash, "video", &files, &preview_data, json!(renditions))
+        .await
+        .map_err(|_| PROCESSING_ERROR.to_owned())?;
+
+    Ok(json!({
+        "fileName": hash,
+        "previewData": preview_data,
+        "renditions": renditions,
+    }))
+}
+
+/// A published rendition of a video
+struct Rendition<'a> {
+    file: String,
+    /// The preview frame at the size of the rendition. Shared by the
+    /// renditions of a profile.
+    poster: String,
+    profile: &'a TranscodeProfile,
+    format: &'a VideoFormat,
+    frame_rate: u32,
+    /// The presentation time of each frame in seconds
+    frame_times: Vec<f64>,
+}
+
+impl Rendition<'_> {
+    /// Builds the metadata clients use to pick a source and step through
+    /// its frames
+    fn to_json(&self) -> Value {
+        json!({
+            "file": self.file,
+            "poster": self.poster,
+            "type": self.format.mime_type(),
+            "width": self.profile.width,
+            "height": self.profile.height,
+            "frameRate": self.frame_rate,
+            "frameCount": self.frame_times.len(),
+            "frameTimes": self.frame_times,
+        })
+    }
+}
+
+/// The frames of a video stream
+struct FrameInfo {
+    /// The start time of the container in seconds
+    start_time: f64,
+    /// The presentation time of each frame in seconds
+    frame_times: Vec<f64>,
+}
+
+/// Generates the web media for a saved video file. Returns the preview data
+/// and the published renditions.
+async fn process_video<'a>(
+    saved_file: &str,
+    tmp_dir: &str,
+    file_name: &str,
+    options: &ClipOptions,
+    config: &'a TranscodeConfig,
+    limits: &ProcessingLimits,
+    store: &MediaStore,
+) -> Result<(String, Vec<Rendition<'a>>), String> {
+    // Work out the range of frames to keep
+    let info = get_frame_info(saved_file, limits).await?;
+    let times = &info.frame_times;
+    let count = times.len() as u32;
+    let start = options.trim_start.unwrap_or(0);
+    let end = options.trim_end.unwrap_or_else(|| count.saturating_sub(1));
+    if count == 0 || start >= count || end >= count {
+        return Err(format!(
+            "The trim range is outside of the video's {} frames",
+            count
+        ));
+    }
+
+    // Seek to half way between frames so the kept frames do not depend on
+    // rounding
+    let (start, end) = (start as usize, end as usize);
+    let seek_start = match start {
+        0 => None,
+        _ => Some((times[start - 1] + times[start]) / 2.0 - info.start_time),
+    };
+
+    let seek_end = if end + 1 < times.len() {
+        Some((times[end] + times[end + 1]) / 2.0 - info.start_time)
+    } else {
+        None
+    };
+
+    let preview_frame = start + ((end - start) as f64 * config.preview_position()).round() as usize;
+    let clip = ClipRange {
+        seek_start,
+        seek_end,
+        preview_point: times[preview_frame] - times[start],
+    };
+
+    let mut renditions = config
+        .profiles()
+        .iter()
+        .flat_map(|profile| {
+            profile.formats.iter().map(move |format| {
+                Rendition {
+                    file: format!("{}.{}.{}", file_name, profile.name, format.container),
+                    poster: format!("{}.{}.jpg", file_name, profile.name),
+                    profile,
+                    format,
+                    frame_rate: options.frame_rate.unwrap_or(profile.frame_rate),
+                    frame_times: Vec::new(),
+                }
+            })
+        })
+        .collect::<Vec<Rendition>>();
+
+    // Generate the web media
+    let preview_data =
+        generate_web_media(saved_file, &clip, &renditions, store, tmp_dir, limits).await?;
+
+    // Record the frames of each rendition so clients can step through them
+    for rendition in renditions.iter_mut() {
+        let info = get_frame_info(&store.file_path(&rendition.file), limits).await?;
+        let first = info.frame_times.first().copied().unwrap_or(0.0);
+        rendition.frame_times = info
+            .frame_times
+            .iter()
+            .map(|time| ((time - first) * 1000.0).round() / 1000.0)
+            .collect();
+    }
+
+    Ok((preview_data, renditions))
+}
+
+/// Gets the presentation times of the frames of a video's first video stream
+async fn get_frame_info(in_file: &str, limits: &ProcessingLimits) -> Result<FrameInfo, String> {
+    let mut command = Command::new("ffprobe");
+    command
+        .args(&["-v", "error"])
+        .args(&["-select_streams", "v:0"])
+        .args(&[
+            "-show_entries",
+            "format=start_time:frame=best_effort_timestamp_time",
+        ])
+        .args(&["-print_format", "json"])
+        .arg(in_file);
+
+    let result = process::run(&mut command, limits.process_timeout, limits)
+        .await
+        .map_err(|error| {
+            error!("Failed to run ffprobe process: {}", error);
+            PROCESSING_ERROR.to_owned()
+        })?;
+
+    if !result.status.success() {
+        error!("ffprobe execution failed: {}", result.status);
+        return Err(PROCESSING_ERROR.to_owned());
+    }
+
+    let probe: Value = serde_json::from_slice(&result.stdout).map_err(|error| {
+        error!("Unable to parse ffprobe output: {}", error);
+        PROCESSING_ERROR.to_owned()
+    })?;
+
+    // ffprobe reports times as strings
+    let parse_time = |value: &Value| value.as_str().and_then(|time| time.parse::<f64>().ok());
+    let frame_times = probe["frames"]
+        .as_array()
+        .map_or(&[][..], |frames| frames)
+        .iter()
+        .filter_map(|frame| parse_time(&frame["best_effort_timestamp_time"]))
+        .collect();
+
+    Ok(FrameInfo {
+        start_time: parse_time(&probe["format"]["start_time"]).unwrap_or(0.0),
+        frame_times,
+    })
+}
+
+/// The part of a video that is published
+struct ClipRange {
+    /// The input position to start from in seconds
+    seek_start: Option<f64>,
+    /// The input position to stop at in seconds
+    seek_end: Option<f64>,
+    /// The position of the preview frame within the clip in seconds
+    preview_point: f64,
+}
+
+/// Processes a video file for web embedding into each of the specified
+/// renditions along with a poster frame for each profile. See https://developers.google.com/media/vp9/settings/vod/ and https://trac.ffmpeg.org/wiki/Encode/H.264 for reference.
+async fn generate_web_media(
+    in_file: &str,
+    clip: &ClipRange,
+    renditions: &[Rendition<'_>],
+    store: &MediaStore,
+    tmp_dir: &str,
+    limits: &ProcessingLimits,
+) -> Result<String, String> {
+    let tmp_image = format!("{}/file.jpg", tmp_dir);
+
+    // Renditions of the same profile share a poster
+    let mut posters: Vec<&Rendition> = Vec::new();
+    for rendition in renditions {
+        if !posters
+            .iter()
+            .any(|poster| poster.poster == rendition.poster)
+        {
+            posters.push(rendition);
+        }
+    }
+
+    // Build a filter graph that scales the video for each rendition and
+    // poster
+    let outputs = (0..renditions.len())
+        .map(|index| format!("[s{}]", index))
+        .chain((0..posters.len()).map(|index| format!("[q{}]", index)))
+        .collect::<String>();
+
+    let mut filter = format!("[0:v]split={}{}", renditions.len() + posters.len(), outputs);
+    for (index, rendition) in renditions.iter().enumerate() {
+        let profile = rendition.profile;
+        filter.push_str(&format!(
+            ";[s{index}]fps={rate},scale=w={width}:h={height}:force_original_aspect_ratio=decrease,\
+            pad={width}:{height}:(ow-iw)/2:(oh-ih)/2[v{index}]",
+            index = index,
+            rate = rendition.frame_rate,
+            width = profile.width,
+            height = profile.height
+        ));
+    }
+
+    for (index, poster) in posters.iter().enumerate() {
+        let profile = poster.profile;
+        filter.push_str(&format!(
+            ";[q{index}]scale=w={width}:h={height}:force_original_aspect_ratio=decrease,\
+            pad={width}:{height}:(ow-iw)/2:(oh-ih)/2[p{index}]",
+            index = index,
+            width = profile.width,
+            height = profile.height
+        ));
+    }
+
+    // Overwrite any files left behind by an interrupted run. Seeking the
+    // input while transcoding is frame accurate.
+    let mut command = Command::new("ffmpeg");
+    let command = command.arg("-y");
+    if let Some(seek_start) = clip.seek_start {
+        command.args(&["-ss", &format!("{:.6}", seek_start)]);
+    }
+
+    if let Some(seek_end) = clip.seek_end {
+        command.args(&["-to", &format!("{:.6}", seek_end)]);
+    }
+
+    let command = command
+        .args(&["-i", in_file])
+        .args(&["-filter_complex", &filter]);
+
+    // Configure arguments for each rendition
+    for (index, rendition) in renditions.iter().enumerate() {
+        let format = rendition.format;
+        command
+            .args(&["-map", &format!("[v{}]", index)])
+            .args(&["-c:v", format.video_codec()])
+            .args(&["-crf", &format.crf.to_string()])
+            .args(&["-threads", "2"]);
+
+        if format.video_codec() == "libvpx-vp9" {
+            command.args(&["-tile-columns", "0"]);
+        }
+
+        if rendition.profile.keep_audio {
+            command
+                .args(&["-map", "0:a?"])
+                .args(&["-c:a", format.audio_codec()]);
+        } else {
+            command.arg("-an");
+        }
+
+        command.arg(store.file_path(&rendition.file));
+    }
+
+    // Configure arguments for creating the posters and a preview image
+    let seek_arg = format!("{:.6}", clip.preview_point);
+    for (index, poster) in posters.iter().enumerate() {
+        command
+            .args(&["-map", &format!("[p{}]", index)])
+            .args(&["-ss", &seek_arg])
+            .args(&["-frames:v", "1"])
+            .arg(store.file_path(&poster.poster));
+    }
+
+    let command = command
+        .args(&["-map", "0:v"])
+        .args(&["-ss", &seek_arg])
+        .args(&["-frames:v", "1"])
+        .args(&["-filter:v", "scale=8:-1,pad=8:6:(ow-iw)/2:(oh-ih)/2"])
+        .arg(&tmp_image);
+
+    // Execute the command and read the resulting preview file
+    let succeeded = match process::run(command, limits.process_timeout, limits).await {
+        Ok(result) if result.status.success() => true,
+        Ok(result) => {
+            error!("ffmpeg processing failed: {}", result.status);
+            error!("{}", String::from_utf8_lossy(&result.stderr));
+            false
+        }
+        Err(error) => {
+            error!("Failed to run ffmpeg process: {}", error);
+            false
+        }
+    };
+
+    if !succeeded {
+        for rendition in renditions {
+            // Renditions may not have been created
+            let _ = remove_file(store.file_path(&rendition.file)).await;
+        }
+
+        for poster in posters {
+            let _ = remove_file(store.file_path(&poster.poster)).await;
+        }
+
+        return Err(PROCESSING_ERROR.to_owned());
+    }
+
+    let mut file = File::open(tmp_image).await.map_err(|error| {
+        error!("Failed to open preview image: {}", error);
+        PROCESSING_ERROR.to_owned()
+    })?;
+
+    let mut content = Vec::<u8>::new();
+    file.read_to_end(&mut content)
+        .await
+        .map(move |_| base64::encode(&content))
+        .map_err(|error| {
+            error!("Failed to read preview image: {}", error);
+            PROCESSING_ERROR.to_owned()
+        })
+}
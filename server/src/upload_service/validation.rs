@@ -0,0 +1,197 @@
+use super::{
+    process,
+    ProcessingLimits,
+};
+use crate::http_service::HttpError;
+use log::{
+    error,
+    warn,
+};
+use serde_json::Value;
+use std::io::ErrorKind;
+use tokio::process::Command;
+
+/// The containers videos are accepted in, as named by ffprobe
+const VIDEO_CONTAINERS: [&str; 6] = ["mov", "mp4", "matroska", "webm", "avi", "mpegts"];
+
+/// The types of streams a video can contain. Data streams hold things like
+/// time codes and are ignored.
+const VIDEO_STREAM_TYPES: [&str; 3] = ["video", "audio", "data"];
+
+/// A stream of an uploaded file
+struct Stream {
+    codec_type: String,
+    codec_name: Option<String>,
+    width: u32,
+    height: u32,
+    /// Cover art rather than actual video
+    attached_pic: bool,
+}
+
+/// The container and streams of an uploaded file
+struct Probe {
+    format_names: Vec<String>,
+    duration: Option<f64>,
+    streams: Vec<Stream>,
+}
+
+/// Makes sure an uploaded video is in a supported container, only has the
+/// expected streams and is within the duration and resolution limits
+pub async fn validate_video(in_file: &str, limits: &ProcessingLimits) -> Result<(), HttpError> {
+    let probe = probe(in_file, limits).await?;
+    let reject = |message: String| {
+        warn!("Rejected uploaded video: {}", message);
+        Err(HttpError::BadRequest(message.into()))
+    };
+
+    if !probe
+        .format_names
+        .iter()
+        .any(|name| VIDEO_CONTAINERS.contains(&name.as_str()))
+    {
+        return reject(format!(
+            "Unsupported video container '{}'",
+            probe.format_names.join(",")
+        ));
+    }
+
+    if let Some(stream) = probe
+        .streams
+        .iter()
+        .find(|stream| !VIDEO_STREAM_TYPES.contains(&stream.codec_type.as_str()))
+    {
+        return reject(format!("Unexpected {} stream in video", stream.codec_type));
+    }
+
+    let videos = probe
+        .streams
+        .iter()
+        .filter(|stream| stream.codec_type == "video" && !stream.attached_pic)
+        .collect::<Vec<&Stream>>();
+
+    let video = match videos.as_slice() {
+        [video] => video,
+        _ => return reject("Videos must have exactly one video stream".to_owned()),
+    };
+
+    if video.codec_name.is_none() {
+        return reject("Unsupported video codec".to_owned());
+    }
+
+    if video.width > limits.max_video_width || video.height > limits.max_video_height {
+        return reject(format!(
+            "Videos can be at most {}x{}",
+            limits.max_video_width, limits.max_video_height
+        ));
+    }
+
+    match probe.duration {
+        Some(duration) if duration <= limits.max_video_duration as f64 => Ok(()),
+        Some(_) => {
+            reject(format!(
+                "Videos can be at most {} seconds long",
+                limits.max_video_duration
+            ))
+        }
+        None => reject("Unable to determine the video's duration".to_owned()),
+    }
+}
+
+/// Makes sure an uploaded image is a single picture within the resolution
+/// limits
+pub async fn validate_image(in_file: &str, limits: &ProcessingLimits) -> Result<(), HttpError> {
+    let probe = probe(in_file, limits).await?;
+    let reject = |message: String| {
+        warn!("Rejected uploaded image: {}", message);
+        Err(HttpError::BadRequest(message.into()))
+    };
+
+    let image = match probe.streams.as_slice() {
+        [image] if image.codec_type == "video" => image,
+        _ => return reject("Images must have exactly one picture".to_owned()),
+    };
+
+    if image.width > limits.max_image_width || image.height > limits.max_image_height {
+        return reject(format!(
+            "Images can be at most {}x{}",
+            limits.max_image_width, limits.max_image_height
+        ));
+    }
+
+    Ok(())
+}
+
+/// Reads the container and streams of an uploaded file without decoding it
+async fn probe(in_file: &str, limits: &ProcessingLimits) -> Result<Probe, HttpError> {
+    let mut command = Command::new("ffprobe");
+    command
+        .args(&["-v", "error"])
+        .args(&[
+            "-show_entries",
+            "format=format_name,duration:stream=codec_type,codec_name,width,height:\
+             stream_disposition=attached_pic",
+        ])
+        .args(&["-print_format", "json"])
+        .arg(in_file);
+
+    let result = process::run(&mut command, limits.probe_timeout, limits)
+        .await
+        .map_err(|error| {
+            if error.kind() == ErrorKind::TimedOut {
+                warn!("ffprobe timed out inspecting an upload: {}", error);
+                HttpError::BadRequest("Unable to inspect the uploaded file".into())
+            } else {
+                error!("Failed to run ffprobe process: {}", error);
+                HttpError::InternalError(None)
+            }
+        })?;
+
+    // ffprobe fails on content it does not recognize
+    if !result.status.success() {
+        warn!(
+            "ffprobe rejected an upload: {}",
+            String::from_utf8_lossy(&result.stderr)
+        );
+
+        return Err(HttpError::BadRequest(
+            "The uploaded file is not a supported media file".into(),
+        ));
+    }
+
+    let probe: Value = serde_json::from_slice(&result.stdout).map_err(|error| {
+        error!("Unable to parse ffprobe output: {}", error);
+        HttpError::InternalError(None)
+    })?;
+
+    let streams = probe["streams"]
+        .as_array()
+        .map_or(&[][..], |streams| streams)
+        .iter()
+        .map(|stream| {
+            Stream {
+                codec_type: stream["codec_type"]
+                    .as_str()
+                    .unwrap_or("unknown")
+                    .to_owned(),
+                codec_name: stream["codec_name"].as_str().map(str::to_owned),
+                width: stream["width"].as_u64().unwrap_or(0) as u32,
+                height: stream["height"].as_u64().unwrap_or(0) as u32,
+                attached_pic: stream["disposition"]["attached_pic"].as_u64() == Some(1),
+            }
+        })
+        .collect();
+
+    // ffprobe reports the duration as a string
+    Ok(Probe {
+        format_names: probe["format"]["format_name"]
+            .as_str()
+            .unwrap_or("")
+            .split(',')
+            .map(str::to_owned)
+            .collect(),
+        duration: probe["format"]["duration"]
+            .as_str()
+            .and_then(|duration| duration.parse::<f64>().ok()),
+        streams,
+    })
+}
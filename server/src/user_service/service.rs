@@ -0,0 +1,250 @@
+use crate::{
+    auth_service::Session,
+    database_adapters::{
+        couchbase as couchbase_adapters,
+        AdapterError,
+    },
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResult,
+    },
+};
+use hyper::{
+    body::{
+        Body,
+        Buf,
+    },
+    StatusCode,
+};
+use lazy_static::lazy_static;
+use log::error;
+use regex::Regex;
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+
+/// The statuses proposals are counted by
+const PROPOSAL_STATUSES: [&str; 4] = ["pending", "approved", "rejected", "cancelled"];
+
+/// The maximum number of characters of a display name
+const MAX_DISPLAY_NAME_LENGTH: usize = 32;
+
+/// The maximum length of an avatar url
+const MAX_AVATAR_LENGTH: usize = 512;
+
+/// Serves the profiles of users along with statistics of their contributions
+pub struct Service {
+    users_adapter: Arc<couchbase_adapters::Users>,
+    props_adapter: Arc<couchbase_adapters::Proposals>,
+}
+
+impl Service {
+    pub fn new(
+        users_adapter: Arc<couchbase_adapters::Users>,
+        props_adapter: Arc<couchbase_adapters::Proposals>,
+    ) -> Self {
+        Self {
+            users_adapter,
+            props_adapter,
+        }
+    }
+
+    /// Handles a user-api `GET` request for the profile of a user, e.g.
+    /// `v1/users/u:1062434421398269953`, or of the current user (`v1/users/me`)
+    pub async fn handle_get_request(&self, path: &str, session: &Session) -> HttpResult {
+        let user_id = Self::get_user_id(path, session)?;
+        let profile = match self.users_adapter.get(&user_id).await {
+            Ok(profile) => Some(profile),
+            Err(AdapterError::DocumentNotFound) => None,
+            Err(AdapterError::InternalError) => return Err(HttpError::InternalError(None)),
+        };
+
+        let stats = self
+            .props_adapter
+            .get_author_stats(&user_id)
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        // Authors that have not signed in since profiles were introduced only
+        // have their proposals
+        let profile = match profile {
+            Some(profile) => profile,
+            None if !stats["firstProposal"].is_null() => {
+                json!({
+                    "id": user_id,
+                    "displayName": stats["latestName"],
+                    "avatar": null,
+                    "accounts": [],
+                    "joined": stats["firstProposal"],
+                })
+            }
+            None => return Err(HttpError::NotFound(None)),
+        };
+
+        Ok(http_util::build_json_response(
+            &Self::build_user(profile, stats),
+            StatusCode::OK,
+        ))
+    }
+
+    /// Handles a user-api `POST` request, which changes the display name or
+    /// avatar of a user. Users can only change their own profile unless they
+    /// are admins.
+    pub async fn handle_post_request(
+        &self,
+        path: &str,
+        body: Body,
+        session: &Session,
+    ) -> HttpResult {
+        let user_id = Self::get_user_id(path, session)?;
+        match session {
+            Session::Valid(claims) | Session::Expired(claims)
+                if claims.sub() == user_id || claims.is_admin() => {}
+            _ => return Err(HttpError::Unauthorized(None)),
+        }
+
+        let bytes = hyper::body::aggregate(body)
+            .await
+            .map(|mut buf| buf.copy_to_bytes(buf.remaining()))
+            .map_err(|error| {
+                error!("Unexpected error while collecting body: {}", error);
+                HttpError::InternalError(None)
+            })?;
+
+        let request: Value = serde_json::from_slice(&bytes)
+            .map_err(|error| HttpError::BadRequest(error.to_string().into()))?;
+
+        let display_name = match &request["displayName"] {
+            Value::Null => None,
+            Value::String(display_name) => Some(Self::validate_display_name(display_name)?),
+            _ => return Err(HttpError::BadRequest("Invalid display name".into())),
+        };
+
+        let avatar = match request.get("avatar") {
+            None => None,
+            Some(Value::Null) => Some(None),
+            Some(Value::String(avatar)) => Some(Some(Self::validate_avatar(avatar)?)),
+            Some(_) => return Err(HttpError::BadRequest("Invalid avatar".into())),
+        };
+
+        let profile = self
+            .users_adapter
+            .update_profile(&user_id, display_name, avatar)
+            .await
+            .map_err(|error| {
+                match error {
+                    AdapterError::DocumentNotFound => HttpError::NotFound(None),
+                    AdapterError::InternalError => HttpError::InternalError(None),
+                }
+            })?;
+
+        let stats = self
+            .props_adapter
+            .get_author_stats(&user_id)
+            .await
+            .map_err(|_| HttpError::InternalError(None))?;
+
+        Ok(http_util::build_json_response(
+            &Self::build_user(profile, stats),
+            StatusCode::OK,
+        ))
+    }
+
+    /// Combines the public fields of a profile with contribution statistics
+    fn build_user(profile: Value, stats: Value) -> Value {
+        let proposals = &stats["proposals"];
+        let counts = PROPOSAL_STATUSES
+            .iter()
+            .map(|status| {
+                (
+                    status.to_string(),
+                    proposals[status].as_u64().unwrap_or(0).into(),
+                )
+            })
+            .collect::<serde_json::Map<String, Value>>();
+
+        // Only reviewed proposals count towards the approval rate
+        let approved = counts["approved"].as_u64().unwrap_or(0);
+        let reviewed = approved + counts["rejected"].as_u64().unwrap_or(0);
+        let approval_rate = if reviewed > 0 {
+            Some(approved as f64 / reviewed as f64)
+        } else {
+            None
+        };
+
+        json!({
+            "id": profile["id"],
+            "displayName": profile["displayName"],
+            "avatar": profile["avatar"],
+            "accounts": profile["accounts"],
+            "joined": profile["joined"],
+            "stats": {
+                "proposals": counts,
+                "documentsTouched": stats["documentsTouched"].as_u64().unwrap_or(0),
+                "games": stats["games"],
+                "approvalRate": approval_rate,
+            },
+        })
+    }
+
+    /// Gets the id of the user a path refers to. `me` refers to the current
+    /// user.
+    fn get_user_id(path: &str, session: &Session) -> Result<String, HttpError> {
+        lazy_static! {
+            static ref PATH_REGEX: Regex = Regex::new(r"^v1/+users/+(?P<id>[\w:\-]+)/*$").unwrap();
+        }
+
+        let captures = PATH_REGEX.captures(path).ok_or_else(|| {
+            let message = format!(
+                "Invalid user-api path '{path}' for the specified method",
+                path = path
+            );
+
+            HttpError::BadRequest(message.into())
+        })?;
+
+        match (&captures["id"], session) {
+            ("me", Session::Valid(claims)) | ("me", Session::Expired(claims)) => {
+                Ok(claims.sub().to_owned())
+            }
+            ("me", _) => Err(HttpError::Unauthorized(None)),
+            (id, _) => Ok(id.to_owned()),
+        }
+    }
+
+    fn validate_display_name(display_name: &str) -> Result<&str, HttpError> {
+        let display_name = display_name.trim();
+        let length = display_name.chars().count();
+        if length == 0
+            || length > MAX_DISPLAY_NAME_LENGTH
+            || display_name.chars().any(|c| c.is_control())
+        {
+            let message = format!(
+                "Display names must have between 1 and {} characters",
+                MAX_DISPLAY_NAME_LENGTH
+            );
+
+            return Err(HttpError::BadRequest(message.into()));
+        }
+
+        Ok(display_name)
+    }
+
+    /// Avatars are either uploaded images or images on other secure sites
+    fn validate_avatar(avatar: &str) -> Result<&str, HttpError> {
+        let valid = avatar.len() <= MAX_AVATAR_LENGTH
+            && (avatar.starts_with("https://") || avatar.starts_with("/uploads/"))
+            && !avatar
+                .chars()
+                .any(|c| c.is_whitespace() || c.is_control() || c == '"' || c == '<' || c == '>');
+
+        if !valid {
+            return Err(HttpError::BadRequest("Invalid avatar".into()));
+        }
+
+        Ok(avatar)
+    }
+}
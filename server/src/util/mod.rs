@@ -3,11 +3,67 @@ use std::time::{
     UNIX_EPOCH,
 };
 
-/// Gets a string representation of the current time
-pub fn get_timestamp_string() -> String {
+/// Gets the current time in seconds since the unix epoch
+pub fn get_timestamp() -> u64 {
     let current_time = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .expect("Unexpected time result.");
 
-    current_time.as_secs().to_string()
+    current_time.as_secs()
+}
+
+/// Gets a string representation of the current time
+pub fn get_timestamp_string() -> String {
+    get_timestamp().to_string()
+}
+
+/// Formats a time in seconds since the unix epoch as an RFC 3339 date and
+/// time in UTC, e.g. `2021-07-01T12:30:00Z`
+pub fn format_rfc3339(time_stamp: u64) -> String {
+    let days = (time_stamp / 86400) as i64;
+    let seconds = time_stamp % 86400;
+
+    // Convert the days since the epoch to a civil date
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Escapes text for use in html content and attribute values
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
 }
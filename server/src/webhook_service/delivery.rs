@@ -0,0 +1,195 @@
+use super::{
+    ServiceConfig,
+    Webhook,
+};
+use crate::{
+    database_adapters::couchbase::WebhookDeliveries,
+    util,
+};
+use crypto::{
+    hmac::Hmac,
+    mac::Mac,
+    sha2::Sha256,
+};
+use hyper::{
+    client::HttpConnector,
+    header,
+    Body,
+    Client,
+    Method,
+    Request,
+};
+use hyper_rustls::HttpsConnector;
+use log::{
+    info,
+    warn,
+};
+use serde_json::{
+    json,
+    Value,
+};
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+use tokio::time;
+
+/// The longest delay between two delivery attempts in seconds
+const MAX_RETRY_DELAY: u64 = 24 * 60 * 60;
+
+pub type HttpsClient = Client<HttpsConnector<HttpConnector>, Body>;
+
+/// An event to send to a webhook
+pub struct Delivery {
+    pub id: String,
+    pub event: String,
+    pub game: String,
+    pub body: Vec<u8>,
+}
+
+/// Sends an event to the webhook at the specified index, retrying with an
+/// exponential backoff until it is accepted or runs out of attempts. Each
+/// attempt is recorded in the delivery log.
+pub async fn deliver(
+    client: HttpsClient,
+    config: Arc<ServiceConfig>,
+    adapter: Arc<WebhookDeliveries>,
+    webhook_index: usize,
+    delivery: Delivery,
+) {
+    let webhook = &config.webhooks()[webhook_index];
+    let mut attempts = Vec::new();
+    let mut log = json!({
+        "type": "webhook_delivery",
+        "id": delivery.id,
+        "url": webhook.url,
+        "event": delivery.event,
+        "game": delivery.game,
+        "created": util::get_timestamp(),
+        "status": "pending",
+    });
+
+    for attempt in 1..=config.max_attempts() {
+        let time = util::get_timestamp();
+        let result = send(&client, webhook, &delivery, config.timeout()).await;
+        let (status, error) = match &result {
+            Ok(status) if (200..300).contains(status) => (Some(*status), None),
+            Ok(status) => (Some(*status), Some(format!("Unexpected status {}", status))),
+            Err(error) => (None, Some(error.clone())),
+        };
+
+        attempts.push(json!({
+            "time": time,
+            "status": status,
+            "error": error,
+        }));
+
+        let done = error.is_none() || attempt == config.max_attempts();
+        log["attempts"] = Value::Array(attempts.clone());
+        log["status"] = match (done, &error) {
+            (true, None) => "delivered".into(),
+            (true, Some(_)) => "failed".into(),
+            _ => "pending".into(),
+        };
+
+        if adapter
+            .upsert(&delivery.id, &log, config.log_retention())
+            .await
+            .is_err()
+        {
+            warn!("Unable to log webhook delivery {}", delivery.id);
+        }
+
+        match (done, error) {
+            (true, None) => {
+                info!(
+                    "Delivered {} {} to {}",
+                    delivery.event, delivery.id, webhook.url
+                );
+                break;
+            }
+            (true, Some(error)) => {
+                warn!(
+                    "Giving up on delivering {} {} to {}: {}",
+                    delivery.event, delivery.id, webhook.url, error
+                );
+                break;
+            }
+            (false, _) => {
+                let delay = 2u64
+                    .checked_pow(attempt - 1)
+                    .map_or(MAX_RETRY_DELAY, |factor| {
+                        factor.saturating_mul(config.retry_delay() as u64)
+                    })
+                    .min(MAX_RETRY_DELAY);
+
+                time::sleep(Duration::from_secs(delay)).await;
+            }
+        }
+    }
+}
+
+/// Posts an event to a webhook. Returns the status code of the response.
+async fn send(
+    client: &HttpsClient,
+    webhook: &Webhook,
+    delivery: &Delivery,
+    timeout: u32,
+) -> Result<u16, String> {
+    let time_stamp = util::get_timestamp_string();
+    let signature = sign(&webhook.secret, &time_stamp, &delivery.body);
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(&webhook.url)
+        .header(header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(header::USER_AGENT, "framebastard-webhooks")
+        .header("X-Webhook-Event", &delivery.event)
+        .header("X-Webhook-Delivery", &delivery.id)
+        .header("X-Webhook-Timestamp", &time_stamp)
+        .header("X-Webhook-Signature", format!("sha256={}", signature))
+        .body(Body::from(delivery.body.clone()))
+        .map_err(|error| error.to_string())?;
+
+    match time::timeout(Duration::from_secs(timeout as u64), client.request(request)).await {
+        Ok(Ok(response)) => Ok(response.status().as_u16()),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(_) => Err(format!("No response within {} seconds", timeout)),
+    }
+}
+
+/// Signs the time stamp and body of a delivery with HMAC-SHA256. Including
+/// the time stamp lets receivers reject replayed deliveries.
+fn sign(secret: &str, time_stamp: &str, body: &[u8]) -> String {
+    let mut hmac = Hmac::new(Sha256::new(), secret.as_bytes());
+    hmac.input(time_stamp.as_bytes());
+    hmac.input(b".");
+    hmac.input(body);
+    hmac.result()
+        .code()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    fn signs_time_stamp_and_body() {
+        let body = br#"{"event":"proposal.created"}"#;
+        assert_eq!(
+            sign("secret", "1625097600", body),
+            "b0773656f9bcae0a92eb766a21f669fe1ceaadc8c26b5c8e930d3301662b3836"
+        );
+    }
+
+    #[test]
+    fn signature_depends_on_time_stamp() {
+        let body = br#"{"event":"proposal.created"}"#;
+        assert_eq!(
+            sign("secret", "1625097601", body),
+            "997b33a12c2567c5f2965a5a001198077e2634693456fe76e2e6b14f9cbd9271"
+        );
+    }
+}
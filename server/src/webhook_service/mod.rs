@@ -0,0 +1,11 @@
+mod delivery;
+mod service;
+mod service_config;
+
+pub use self::{
+    service::Service,
+    service_config::{
+        ServiceConfig,
+        Webhook,
+    },
+};
@@ -0,0 +1,184 @@
+use super::{
+    delivery::{
+        self,
+        Delivery,
+        HttpsClient,
+    },
+    ServiceConfig,
+};
+use crate::{
+    auth_service::Session,
+    database_adapters::{
+        self,
+        couchbase::WebhookDeliveries,
+    },
+    http_service::{
+        util as http_util,
+        HttpError,
+        HttpResult,
+    },
+    util,
+};
+use hyper::{
+    Client,
+    StatusCode,
+};
+use hyper_rustls::HttpsConnector;
+use lazy_static::lazy_static;
+use rand::{
+    distributions::Alphanumeric,
+    thread_rng,
+    Rng,
+};
+use regex::Regex;
+use serde_json::{
+    json,
+    Value,
+};
+use std::sync::Arc;
+
+/// Sends proposal and document events to the configured webhooks
+pub struct Service {
+    config: Arc<ServiceConfig>,
+    adapter: Arc<WebhookDeliveries>,
+    client: HttpsClient,
+}
+
+impl Service {
+    pub fn new(config: ServiceConfig, adapter: Arc<WebhookDeliveries>) -> Self {
+        let connector = HttpsConnector::with_native_roots();
+        Self {
+            config: Arc::new(config),
+            adapter,
+            client: Client::builder().build(connector),
+        }
+    }
+
+    /// Sends an event of a game to the webhooks that subscribe to it.
+    /// Deliveries happen in the background.
+    pub fn dispatch(&self, event: &str, game: &str, data: Value) {
+        let webhooks = self
+            .config
+            .webhooks()
+            .iter()
+            .enumerate()
+            .filter(|(_, webhook)| webhook.accepts(event, game))
+            .map(|(index, _)| index)
+            .collect::<Vec<usize>>();
+
+        self.send_to(&webhooks, event, game, data);
+    }
+
+    /// Handles a webhook-api `GET` request. Only available to admins.
+    pub async fn handle_get_request(
+        &self,
+        path: &str,
+        query: Option<&str>,
+        session: &Session,
+    ) -> HttpResult {
+        Self::authorize(session)?;
+        match path {
+            "v1/deliveries" => {
+                let query_params = http_util::parse_query_string(query.unwrap_or(""));
+                let (offset, limit) = http_util::get_paging_options(&query_params)
+                    .map_err(|error| HttpError::BadRequest(error.into()))?;
+
+                let status = query_params.get("status").copied();
+                let result = self.adapter.get_list(offset, limit, status).await;
+                database_adapters::build_http_result(result)
+            }
+            _ => Err(Self::build_invalid_path_error(path)),
+        }
+    }
+
+    /// Handles a webhook-api `POST` request. Only available to admins.
+    pub async fn handle_post_request(&self, path: &str, session: &Session) -> HttpResult {
+        lazy_static! {
+            static ref PING_REGEX: Regex = Regex::new(r"^v1/+ping(?:/+(?P<index>\d+))?$").unwrap();
+        }
+
+        Self::authorize(session)?;
+        let captures = PING_REGEX
+            .captures(path)
+            .ok_or_else(|| Self::build_invalid_path_error(path))?;
+
+        // Ping a single webhook or all of them regardless of their filters
+        let count = self.config.webhooks().len();
+        let webhooks = match captures.name("index") {
+            Some(index) => {
+                let index = index.as_str().parse::<usize>().unwrap_or(count);
+                if index >= count {
+                    return Err(HttpError::NotFound(None));
+                }
+
+                vec![index]
+            }
+            None => (0..count).collect(),
+        };
+
+        let ids = self.send_to(&webhooks, "ping", "", json!({}));
+        Ok(http_util::build_json_response(
+            &json!({ "deliveries": ids }),
+            StatusCode::ACCEPTED,
+        ))
+    }
+
+    /// Sends an event to the webhooks at the specified indices. Returns the
+    /// ids of the deliveries.
+    fn send_to(&self, webhooks: &[usize], event: &str, game: &str, data: Value) -> Vec<String> {
+        let mut ids = Vec::new();
+        for index in webhooks {
+            let id = Self::build_delivery_id();
+            let body = json!({
+                "id": id,
+                "event": event,
+                "game": game,
+                "created": util::get_timestamp(),
+                "data": data,
+            });
+
+            let delivery = Delivery {
+                id: id.clone(),
+                event: event.to_owned(),
+                game: game.to_owned(),
+                body: serde_json::to_vec(&body).unwrap(),
+            };
+
+            tokio::spawn(delivery::deliver(
+                self.client.clone(),
+                self.config.clone(),
+                self.adapter.clone(),
+                *index,
+                delivery,
+            ));
+
+            ids.push(id);
+        }
+
+        ids
+    }
+
+    fn authorize(session: &Session) -> Result<(), HttpError> {
+        match session {
+            Session::Valid(claims) | Session::Expired(claims) if claims.is_admin() => Ok(()),
+            _ => Err(HttpError::Unauthorized(None)),
+        }
+    }
+
+    fn build_delivery_id() -> String {
+        thread_rng()
+            .sample_iter(&Alphanumeric)
+            .map(char::from)
+            .take(24)
+            .collect::<String>()
+    }
+
+    fn build_invalid_path_error(path: &str) -> HttpError {
+        let message = format!(
+            "Invalid webhook-api path '{path}' for the specified method",
+            path = path
+        );
+
+        HttpError::BadRequest(message.into())
+    }
+}
@@ -0,0 +1,106 @@
+use serde_derive::Deserialize;
+
+/// The events webhooks can subscribe to
+pub const WEBHOOK_EVENTS: [&str; 3] = ["proposal.created", "proposal.closed", "document.published"];
+
+/// The maximum number of times a delivery can be attempted
+const MAX_ATTEMPTS: u32 = 20;
+
+/// An endpoint that is sent events as signed JSON
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Webhook {
+    pub url: String,
+    /// The key payloads are signed with
+    pub secret: String,
+    /// The events sent to the endpoint
+    pub events: Vec<String>,
+    /// The ids of the games whose events are sent. Events of all games are
+    /// sent if empty.
+    #[serde(default)]
+    pub games: Vec<String>,
+}
+
+impl Webhook {
+    /// Whether an event of a game is sent to the endpoint
+    pub fn accepts(&self, event: &str, game: &str) -> bool {
+        self.events.iter().any(|e| e == event)
+            && (self.games.is_empty() || self.games.iter().any(|g| g == game))
+    }
+}
+
+pub struct ServiceConfig {
+    webhooks: Vec<Webhook>,
+    max_attempts: u32,
+    retry_delay: u32,
+    timeout: u32,
+    log_retention: u32,
+}
+
+impl ServiceConfig {
+    pub fn new(
+        webhooks: Vec<Webhook>,
+        max_attempts: u32,
+        retry_delay: u32,
+        timeout: u32,
+        log_retention: u32,
+    ) -> Result<Self, String> {
+        for webhook in &webhooks {
+            if !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://") {
+                return Err(format!("Invalid webhook url '{}'", webhook.url));
+            }
+
+            if let Some(event) = webhook
+                .events
+                .iter()
+                .find(|event| !WEBHOOK_EVENTS.contains(&event.as_str()))
+            {
+                return Err(format!("Invalid webhook event '{}'", event));
+            }
+        }
+
+        if max_attempts == 0 {
+            return Err("Webhooks need at least one delivery attempt".to_owned());
+        }
+
+        if max_attempts > MAX_ATTEMPTS {
+            return Err(format!(
+                "Webhooks can be attempted at most {} times",
+                MAX_ATTEMPTS
+            ));
+        }
+
+        Ok(Self {
+            webhooks,
+            max_attempts,
+            retry_delay,
+            timeout,
+            log_retention,
+        })
+    }
+
+    pub fn webhooks(&self) -> &[Webhook] {
+        &self.webhooks
+    }
+
+    /// The number of times a delivery is attempted before it fails
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// The number of seconds before the first retry. The delay doubles with
+    /// each retry.
+    pub fn retry_delay(&self) -> u32 {
+        self.retry_delay
+    }
+
+    /// The number of seconds an endpoint has to respond
+    pub fn timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// The number of seconds deliveries are kept in the log
+    pub fn log_retention(&self) -> u32 {
+        self.log_retention
+    }
+}